@@ -0,0 +1,53 @@
+"""Python wrapper of libzeal, zeal-cli's search engine over a C ABI.
+
+Build the library with `cargo build --release --features ffi`, then point
+`ZEAL_LIB` at it (`target/release/libzeal.so`, `libzeal.dylib` or
+`zeal.dll`) or put it where the system's loader finds it.
+
+    import zeal
+    zeal.list_docsets()
+    zeal.search("Rust", "vec push", limit=5)
+"""
+
+import ctypes
+import ctypes.util
+import json
+import os
+
+_lib = ctypes.CDLL(os.environ.get("ZEAL_LIB") or ctypes.util.find_library("zeal") or "libzeal.so")
+_lib.zeal_list_docsets.argtypes = [ctypes.c_char_p]
+_lib.zeal_list_docsets.restype = ctypes.c_void_p
+_lib.zeal_search.argtypes = [ctypes.c_char_p, ctypes.c_char_p, ctypes.c_char_p, ctypes.c_size_t]
+_lib.zeal_search.restype = ctypes.c_void_p
+_lib.zeal_last_error.argtypes = []
+_lib.zeal_last_error.restype = ctypes.c_char_p
+_lib.zeal_string_free.argtypes = [ctypes.c_void_p]
+_lib.zeal_string_free.restype = None
+
+
+class ZealError(Exception):
+    pass
+
+
+def _encode(s):
+    return None if s is None else s.encode("utf-8")
+
+
+def _json(ptr):
+    if not ptr:
+        error = _lib.zeal_last_error()
+        raise ZealError(error.decode("utf-8") if error else "unknown error")
+    try:
+        return json.loads(ctypes.string_at(ptr).decode("utf-8"))
+    finally:
+        _lib.zeal_string_free(ptr)
+
+
+def list_docsets(docsets_dir=None):
+    """Names of the installed docsets."""
+    return _json(_lib.zeal_list_docsets(_encode(docsets_dir)))
+
+
+def search(docset, query, limit=0, docsets_dir=None):
+    """Results of `query` in `docset`, best first, as dicts."""
+    return _json(_lib.zeal_search(_encode(docsets_dir), _encode(docset), _encode(query), limit))
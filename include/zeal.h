@@ -0,0 +1,36 @@
+/* C ABI of zeal-cli's search engine, built as libzeal with
+ * `cargo build --release --features ffi`.
+ *
+ * Strings are UTF-8 and NUL-terminated. Functions returning `char *` give
+ * JSON the caller frees with zeal_string_free(), or NULL on failure, after
+ * which zeal_last_error() says what went wrong. A NULL docsets_dir means
+ * Zeal's docsets directory.
+ */
+#ifndef ZEAL_H
+#define ZEAL_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Names of the installed docsets, as a JSON array of strings. */
+char *zeal_list_docsets(const char *docsets_dir);
+
+/* At most `limit` results (0 for all) of `query` in the docset named
+ * `docset`, best first, as a JSON array of objects with `score`, `name`,
+ * `type_`, `path`, `docset` and `container`. */
+char *zeal_search(const char *docsets_dir, const char *docset, const char *query, size_t limit);
+
+/* Why the last call on this thread returned NULL, or NULL. Not to be freed. */
+const char *zeal_last_error(void);
+
+/* Frees a string returned by the functions above; NULL is ignored. */
+void zeal_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
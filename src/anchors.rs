@@ -0,0 +1,89 @@
+use rayon::prelude::*;
+use scraper::Html;
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::search::{self, Budget, Entry};
+use crate::{archive, cache, toc};
+
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("zeal-cli")
+        .join("anchors")
+}
+
+/// Cache file of a docset, keyed on its index's modification time so an
+/// updated docset's pages are read again.
+fn cache_path(docset_path: &Path) -> Option<PathBuf> {
+    let key = cache::docset_key(docset_path, &[])?;
+    Some(cache_dir().join(format!("{}.json", key)))
+}
+
+/// The Dash anchors of a docset's pages that its index has no entry for,
+/// as entries of their own: the methods of a class page, say, when the
+/// index only lists the class. `indexed` is the docset's whole index, whose
+/// pages are each read once the first time, then the anchors are kept until
+/// the docset changes. Once `budget` is spent the pages left are skipped
+/// and what was found is not kept.
+pub fn entries(docset_path: &Path, indexed: &[Entry], budget: Option<&Budget>) -> Vec<Entry> {
+    let cache = cache_path(docset_path);
+    if let Some(body) = cache.as_deref().and_then(|path| fs::read(path).ok()) {
+        if let Ok(entries) = serde_json::from_slice(&body) {
+            return entries;
+        }
+    }
+    let _span =
+        tracing::debug_span!("anchors", docset = %search::docset_name(docset_path)).entered();
+    let known: HashSet<String> = indexed.iter().map(|e| key(&e.path)).collect();
+    let pages: BTreeSet<PathBuf> = indexed
+        .iter()
+        .filter(|e| !e.path.to_string_lossy().contains("://"))
+        .map(|e| toc::strip_fragment(&e.path))
+        .collect();
+    // Unpacked before the pages are read in parallel, which would each
+    // start unpacking an archived docset.
+    if let Some(page) = pages.first() {
+        if let Err(e) = archive::materialize(page) {
+            tracing::debug!("Cannot unpack {}: {}", docset_path.display(), e);
+        }
+    }
+    let entries: Vec<Entry> = pages
+        .par_iter()
+        .flat_map_iter(|page| {
+            let anchors = if budget.is_some_and(Budget::expired) {
+                Vec::new()
+            } else {
+                read(page).unwrap_or_else(|e| {
+                    tracing::debug!("Skipping {}: {}", page.display(), e);
+                    Vec::new()
+                })
+            };
+            anchors.into_iter().map(move |anchor| Entry {
+                name: anchor.name,
+                type_: anchor.type_,
+                path: PathBuf::from(format!("{}#{}", page.display(), anchor.anchor)),
+            })
+        })
+        .filter(|e| !known.contains(&key(&e.path)))
+        .collect();
+    tracing::debug!(anchors = entries.len(), "read page anchors");
+    // A budget spent on the index or the pages left some out.
+    if let Some(cache) = cache.filter(|_| !budget.is_some_and(Budget::truncated)) {
+        cache::save(&cache, &entries);
+    }
+    entries
+}
+
+/// An entry's path as compared with the anchors', which pages and indexes
+/// may percent-encode differently.
+fn key(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    urlencoding::decode(&path).map_or_else(|_| path.to_string(), |p| p.into_owned())
+}
+
+fn read(page: &Path) -> std::io::Result<Vec<toc::TocEntry>> {
+    let html = fs::read_to_string(page)?;
+    Ok(toc::dash_anchors(&Html::parse_document(&html)))
+}
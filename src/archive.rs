@@ -0,0 +1,471 @@
+use flate2::read::GzDecoder;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{cache, search, toc};
+
+/// Archive suffixes read as docsets, longest first.
+const SUFFIXES: &[&str] = &[".tar.gz", ".tgz", ".zip"];
+/// Files unpacked when an archive is first seen, below the docset directory;
+/// everything else is unpacked when a page is first needed.
+const EAGER: &[&str] = &[
+    "Contents/Info.plist",
+    "Contents/Resources/docSet.dsidx",
+    "meta.json",
+    "icon.png",
+    "icon@2x.png",
+];
+/// Records which archive a cache directory mirrors.
+const SOURCE_FILE: &str = "archive.json";
+/// Marks a cache directory whose archive is unpacked in full.
+const UNPACKED_FILE: &str = "unpacked";
+
+#[derive(Serialize, Deserialize)]
+struct Source {
+    archive: PathBuf,
+    /// Directory the docset lies in within the archive, `Rust.docset/`,
+    /// or empty when `Contents/` is at the top.
+    prefix: String,
+    /// Whether the archive holds a search index at all.
+    docset: bool,
+}
+
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("zeal-cli")
+        .join("archives")
+}
+
+/// The docset name of an archive in a docsets directory, `Rust` for
+/// `Rust.tgz` or `Rust.docset.zip`.
+pub(crate) fn archive_name(path: &Path) -> Option<String> {
+    let file = path.file_name()?.to_str()?;
+    let lower = file.to_ascii_lowercase();
+    let suffix = SUFFIXES.iter().find(|s| lower.ends_with(*s))?;
+    let stem = &file[..file.len() - suffix.len()];
+    let stem = match stem.len().checked_sub(".docset".len()) {
+        Some(at) if stem[at..].eq_ignore_ascii_case(".docset") => &stem[..at],
+        _ => stem,
+    };
+    Some(stem.to_string()).filter(|s| !s.is_empty() && !s.starts_with('.'))
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Cache directory for an archive, keyed on its path, size and
+/// modification time so a replaced archive is read afresh.
+fn key_dir(archive: &Path) -> io::Result<PathBuf> {
+    let meta = fs::metadata(archive)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let key = cache::key(&[
+        &archive.to_string_lossy(),
+        &meta.len().to_string(),
+        &mtime.to_string(),
+    ]);
+    Ok(cache_dir().join(key))
+}
+
+/// The docsets kept as archives in a docsets directory, each as the cache
+/// directory standing in for it. Archives that can't be read are skipped.
+pub fn docsets(docsets_dir: &Path) -> Vec<PathBuf> {
+    let Ok(dir) = fs::read_dir(docsets_dir) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<PathBuf> = dir
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && archive_name(p).is_some())
+        .collect();
+    archives.sort();
+    archives
+        .iter()
+        .filter_map(|archive| match open(archive) {
+            Ok(docset) => docset,
+            Err(e) => {
+                tracing::warn!("Skipping {}: {}", archive.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The cache directory standing in for the docset in `archive`, holding
+/// only its index and metadata until pages are read, see [`materialize`].
+/// `None` when the archive has no search index.
+pub fn open(archive: &Path) -> io::Result<Option<PathBuf>> {
+    let name = archive_name(archive).unwrap_or_default();
+    let dir = key_dir(archive)?;
+    let docset = dir.join(format!("{}.docset", name));
+    if let Ok(body) = fs::read(dir.join(SOURCE_FILE)) {
+        let source: Source = serde_json::from_slice(&body)?;
+        return Ok(source.docset.then_some(docset));
+    }
+    let _span = tracing::debug_span!("archive_open", archive = %archive.display()).entered();
+    let prefix = prefix(archive)?;
+    let wanted: BTreeSet<String> = EAGER.iter().map(|f| format!("{}{}", prefix, f)).collect();
+    let unpacked = extract(archive, Some(&wanted), &docset, &prefix)?;
+    let source = Source {
+        archive: archive.to_path_buf(),
+        docset: unpacked.iter().any(|f| f.ends_with("docSet.dsidx")),
+        prefix,
+    };
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(SOURCE_FILE), serde_json::to_vec(&source)?)?;
+    prune(archive, &dir);
+    Ok(source.docset.then_some(docset))
+}
+
+/// Removes caches of earlier versions of `archive`.
+fn prune(archive: &Path, keep: &Path) {
+    let Ok(dir) = fs::read_dir(cache_dir()) else {
+        return;
+    };
+    for path in dir.filter_map(|e| e.ok().map(|e| e.path())) {
+        if path == keep {
+            continue;
+        }
+        let source: Option<Source> = fs::read(path.join(SOURCE_FILE))
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok());
+        if source.is_some_and(|s| s.archive == archive) {
+            let _ = fs::remove_dir_all(&path);
+        }
+    }
+}
+
+/// The archive a cached docset was opened from, `None` for other docsets.
+pub fn archive_of(docset: &Path) -> Option<PathBuf> {
+    let body = fs::read(docset.parent()?.join(SOURCE_FILE)).ok()?;
+    let source: Source = serde_json::from_slice(&body).ok()?;
+    Some(source.archive)
+}
+
+/// Cache directories no docset stands in for any more: those of archives
+/// since replaced or removed, and opens that never finished.
+pub fn orphans() -> Vec<PathBuf> {
+    let Ok(dir) = fs::read_dir(cache_dir()) else {
+        return Vec::new();
+    };
+    dir.filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .filter(|path| {
+            let source: Option<Source> = fs::read(path.join(SOURCE_FILE))
+                .ok()
+                .and_then(|b| serde_json::from_slice(&b).ok());
+            match source {
+                Some(source) => key_dir(&source.archive).ok().as_ref() != Some(path),
+                None => true,
+            }
+        })
+        .collect()
+}
+
+/// Unpacks the rest of the archive an entry path lies in the cache of, the
+/// first time one of its pages is needed, so pages and the files they load
+/// are read without decompressing the archive again. Other paths are left
+/// alone.
+pub fn materialize(path: &Path) -> io::Result<()> {
+    let page = toc::strip_fragment(path);
+    if page.exists() {
+        return Ok(());
+    }
+    let Some(docset) = page.ancestors().find(|a| search::is_docset_dir(a)) else {
+        return Ok(());
+    };
+    let Some(dir) = docset.parent() else {
+        return Ok(());
+    };
+    if dir.join(UNPACKED_FILE).exists() {
+        return Ok(());
+    }
+    let Some(source) = fs::read(dir.join(SOURCE_FILE))
+        .ok()
+        .and_then(|b| serde_json::from_slice::<Source>(&b).ok())
+    else {
+        return Ok(());
+    };
+    let _span =
+        tracing::debug_span!("archive_unpack", archive = %source.archive.display()).entered();
+    extract(&source.archive, None, docset, &source.prefix)?;
+    fs::write(dir.join(UNPACKED_FILE), "")
+}
+
+/// Local files a page loads, resolved against it.
+pub(crate) fn assets(page: &Path) -> Vec<PathBuf> {
+    let Ok(html) = fs::read_to_string(page) else {
+        return Vec::new();
+    };
+    let Some(base) = page.parent() else {
+        return Vec::new();
+    };
+    let doc = Html::parse_document(&html);
+    let selector = Selector::parse("img[src], script[src], link[rel~=stylesheet][href]").unwrap();
+    doc.select(&selector)
+        .filter_map(|el| el.value().attr("src").or_else(|| el.value().attr("href")))
+        .filter(|src| !src.contains("://") && !src.starts_with("data:") && !src.starts_with('/'))
+        .filter_map(|src| {
+            let src = src.split(['#', '?']).next()?;
+            let src = urlencoding::decode(src).ok()?;
+            Some(normalize(&base.join(src.as_ref())))
+        })
+        .collect()
+}
+
+/// Resolves `.` and `..` without touching the file system, as the files
+/// may not exist yet.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// The path of the `*.docset` directory an archive member lies in, up to
+/// and including its first component so named; `./` components are
+/// dropped.
+pub(crate) fn docset_root(member: &Path) -> Option<PathBuf> {
+    let mut root = PathBuf::new();
+    for component in member.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(name) => {
+                root.push(name);
+                let is_docset = Path::new(name)
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("docset"));
+                if is_docset {
+                    return Some(root);
+                }
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The directory the docset lies in within the archive: the first
+/// `*.docset` directory among its member paths, if any.
+fn prefix(archive: &Path) -> io::Result<String> {
+    let as_prefix = |root: PathBuf| format!("{}/", root.to_string_lossy());
+    if is_zip(archive) {
+        let zip = zip::ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+        return Ok(zip
+            .file_names()
+            .flatten()
+            .find_map(|name| docset_root(Path::new(name.as_ref())))
+            .map(as_prefix)
+            .unwrap_or_default());
+    }
+    // Leading members, such as a README beside the docset, are read past.
+    let mut tar = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+    for entry in tar.entries()? {
+        if let Some(root) = docset_root(&entry?.path()?) {
+            return Ok(as_prefix(root));
+        }
+    }
+    Ok(String::new())
+}
+
+/// Unpacks the `wanted` archive members below `docset`, or all of them
+/// when `None`, dropping `prefix` from their paths, and returns the ones
+/// found. Files already unpacked are kept. A gzipped tarball is read up to
+/// the last wanted member; a zip is read only where they are.
+fn extract(
+    archive: &Path,
+    wanted: Option<&BTreeSet<String>>,
+    docset: &Path,
+    prefix: &str,
+) -> io::Result<Vec<String>> {
+    let mut found = Vec::new();
+    let unpack = |name: &str, reader: &mut dyn Read| -> io::Result<()> {
+        let Some(rel) = name.strip_prefix(prefix) else {
+            return Ok(());
+        };
+        let rel = Path::new(rel);
+        if !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+            return Ok(());
+        }
+        let target = docset.join(rel);
+        if target.exists() {
+            return Ok(());
+        }
+        let dir = target.parent().unwrap_or(docset);
+        fs::create_dir_all(dir)?;
+        // Written aside and renamed so concurrent readers never see half a file.
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        io::copy(reader, &mut tmp)?;
+        tmp.persist(&target).map_err(|e| e.error)?;
+        Ok(())
+    };
+    if is_zip(archive) {
+        let mut zip = zip::ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+        let Some(wanted) = wanted else {
+            for i in 0..zip.len() {
+                let mut file = zip.by_index(i).map_err(io::Error::other)?;
+                let name = file.name().map_err(io::Error::other)?;
+                let name = name.trim_start_matches("./").to_string();
+                if file.is_file() && name.starts_with(prefix) {
+                    unpack(&name, &mut file)?;
+                    found.push(name);
+                }
+            }
+            return Ok(found);
+        };
+        for name in wanted {
+            match zip.by_name(name) {
+                Ok(mut file) => {
+                    unpack(name, &mut file)?;
+                    found.push(name.clone());
+                }
+                Err(zip::result::ZipError::FileNotFound) => {}
+                Err(e) => return Err(io::Error::other(e)),
+            }
+        }
+        return Ok(found);
+    }
+    let mut tar = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let name = name.trim_start_matches("./");
+        let is_wanted = wanted.map_or(name.starts_with(prefix), |w| w.contains(name));
+        if entry.header().entry_type().is_file() && is_wanted {
+            unpack(name, &mut entry)?;
+            found.push(name.to_string());
+            if wanted.is_some_and(|w| found.len() == w.len()) {
+                break;
+            }
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_docset_directory_past_dot_and_parents() {
+        let root = |member: &str| docset_root(Path::new(member));
+        assert_eq!(
+            root("Foo.docset/Contents/Info.plist"),
+            Some("Foo.docset".into())
+        );
+        assert_eq!(root("./Foo.docset/Contents"), Some("Foo.docset".into()));
+        assert_eq!(root("dist/./Foo.DOCSET/x"), Some("dist/Foo.DOCSET".into()));
+        assert_eq!(root("./README.md"), None);
+        assert_eq!(root("../Foo.docset/x"), None);
+    }
+
+    const MEMBERS: &[(&str, &str)] = &[
+        ("README.md", "readme"),
+        ("Foo.docset/Contents/Info.plist", "plist"),
+        ("Foo.docset/Contents/Resources/docSet.dsidx", "index"),
+        ("Foo.docset/Contents/Resources/Documents/a.html", "page"),
+        ("Foo.docset/../escape.html", "escape"),
+    ];
+
+    fn tarball(path: &Path) {
+        let out = flate2::write::GzEncoder::new(File::create(path).unwrap(), Default::default());
+        let mut tar = tar::Builder::new(out);
+        for (name, body) in MEMBERS {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(body.len() as u64);
+            header.set_mode(0o644);
+            // `append_data` refuses `..`, which a hostile archive may hold.
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_cksum();
+            tar.append(&header, body.as_bytes()).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn zipfile(path: &Path) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, body) in MEMBERS {
+            zip.start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            io::Write::write_all(&mut zip, body.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Unpacks the index of `archive`, then its pages once one is asked for.
+    fn unpacks_lazily(archive: &Path, cache: &Path) {
+        assert_eq!(prefix(archive).unwrap(), "Foo.docset/");
+        let docset = cache.join("Foo.docset");
+        let wanted: BTreeSet<String> = EAGER.iter().map(|f| format!("Foo.docset/{}", f)).collect();
+        let mut found = extract(archive, Some(&wanted), &docset, "Foo.docset/").unwrap();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                "Foo.docset/Contents/Info.plist",
+                "Foo.docset/Contents/Resources/docSet.dsidx"
+            ]
+        );
+        let page = docset.join("Contents/Resources/Documents/a.html");
+        assert!(!page.exists());
+
+        let source = Source {
+            archive: archive.to_path_buf(),
+            prefix: "Foo.docset/".into(),
+            docset: true,
+        };
+        fs::write(
+            cache.join(SOURCE_FILE),
+            serde_json::to_vec(&source).unwrap(),
+        )
+        .unwrap();
+        materialize(&page.with_file_name("a.html#section")).unwrap();
+        assert_eq!(fs::read_to_string(&page).unwrap(), "page");
+        assert!(cache.join(UNPACKED_FILE).exists());
+        assert!(!cache.join("escape.html").exists());
+        assert!(!docset.join("README.md").exists());
+    }
+
+    #[test]
+    fn unpacks_a_tarball_lazily() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("Foo.tgz");
+        tarball(&archive);
+        unpacks_lazily(&archive, &dir.path().join("cache"));
+    }
+
+    #[test]
+    fn unpacks_a_zip_lazily() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("Foo.docset.zip");
+        zipfile(&archive);
+        unpacks_lazily(&archive, &dir.path().join("cache"));
+    }
+
+    #[test]
+    fn names_archives_after_their_docset() {
+        let name = |file: &str| archive_name(Path::new(file));
+        assert_eq!(name("Rust.tgz").as_deref(), Some("Rust"));
+        assert_eq!(name("Rust.docset.ZIP").as_deref(), Some("Rust"));
+        assert_eq!(name("Go.tar.gz").as_deref(), Some("Go"));
+        assert_eq!(name(".tgz"), None);
+        assert_eq!(name("Rust.docset"), None);
+    }
+}
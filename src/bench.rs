@@ -0,0 +1,147 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::output::{self, OutputFormat};
+use crate::search::{self, MatchMode, SearchOptions};
+
+const STAGES: [&str; 4] = ["fetch", "score", "sort", "output"];
+
+/// Times each stage of a search over `iterations` runs (after one warm-up
+/// run) and prints latency percentiles per match mode.
+///
+/// The stages are the SQL fetch of `searchIndex`, scoring, sorting and
+/// formatting the results in `format` into memory, so terminal speed does
+/// not skew the numbers.
+pub fn run(
+    docset_path: &Path,
+    query: &str,
+    modes: &[MatchMode],
+    iterations: usize,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let iterations = iterations.max(1);
+    println!(
+        "{} '{}', {} iterations",
+        search::docset_name(docset_path),
+        query,
+        iterations
+    );
+    for &mode in modes {
+        let opts = SearchOptions {
+            mode,
+            ..Default::default()
+        };
+        let mut timings: [Vec<Duration>; 4] = Default::default();
+        let mut matches = 0;
+        for i in 0..=iterations {
+            let mut stage = [Duration::ZERO; 4];
+
+            let start = Instant::now();
+            let entries = search::load_entries(docset_path)?;
+            stage[0] = start.elapsed();
+
+            let start = Instant::now();
+            let mut results = search::score_entries(&entries, query, &opts);
+            stage[1] = start.elapsed();
+
+            let start = Instant::now();
+            search::sort_matches(&mut results, query);
+            stage[2] = start.elapsed();
+
+            let start = Instant::now();
+            let mut sink = Vec::new();
+            output::write_results(&mut sink, &results, format, &Default::default())?;
+            stage[3] = start.elapsed();
+
+            matches = results.len();
+            // The first run warms the page cache and is not recorded.
+            if i > 0 {
+                for (t, d) in timings.iter_mut().zip(stage) {
+                    t.push(d);
+                }
+            }
+        }
+
+        println!();
+        println!("{:?}: {} matches", mode, matches);
+        println!(
+            "{:<8} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "stage", "min", "p50", "p90", "p99", "max", "mean"
+        );
+        let totals: Vec<Duration> = (0..iterations)
+            .map(|i| timings.iter().map(|t| t[i]).sum())
+            .collect();
+        for (name, t) in STAGES.iter().zip(&timings) {
+            print_row(name, t);
+        }
+        print_row("total", &totals);
+    }
+    Ok(())
+}
+
+/// Counts and stage durations of a single search, for `search --timing`.
+#[derive(Debug, Default)]
+pub struct Timing {
+    pub docsets: usize,
+    /// Docsets answered from the result cache, skipping fetch and score.
+    pub cached: usize,
+    /// `searchIndex` rows read.
+    pub fetched: usize,
+    /// Rows scored, those passing the type filter.
+    pub scored: usize,
+    pub matched: usize,
+    pub shown: usize,
+    pub fetch: Duration,
+    pub score: Duration,
+    pub merge: Duration,
+    pub output: Duration,
+}
+
+impl Timing {
+    /// One line summing up the search.
+    pub fn summary(&self) -> String {
+        let total = self.fetch + self.score + self.merge + self.output;
+        format!(
+            "timing: {} docset{} ({} cached), {} fetched in {}, {} scored and {} matched in {}, \
+             merged in {}, {} shown in {}, {} total",
+            self.docsets,
+            if self.docsets == 1 { "" } else { "s" },
+            self.cached,
+            self.fetched,
+            ms(self.fetch),
+            self.scored,
+            self.matched,
+            ms(self.score),
+            ms(self.merge),
+            self.shown,
+            ms(self.output),
+            ms(total)
+        )
+    }
+}
+
+fn print_row(name: &str, samples: &[Duration]) {
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let mean = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+    println!(
+        "{:<8} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        name,
+        ms(sorted[0]),
+        ms(percentile(&sorted, 50)),
+        ms(percentile(&sorted, 90)),
+        ms(percentile(&sorted, 99)),
+        ms(sorted[sorted.len() - 1]),
+        ms(mean)
+    );
+}
+
+/// Nearest-rank percentile of already sorted samples.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn ms(d: Duration) -> String {
+    format!("{:.3}ms", d.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::search::SearchResult;
+
+fn bookmarks_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("zeal-cli").join("bookmarks.json"))
+}
+
+/// The bookmarked entries, oldest first.
+pub fn load() -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+    let Some(path) = bookmarks_path() else {
+        return Ok(Vec::new());
+    };
+    match fs::read(&path) {
+        Ok(body) => {
+            Ok(serde_json::from_slice(&body).map_err(|e| format!("{}: {}", path.display(), e))?)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+    }
+}
+
+fn save(bookmarks: &[SearchResult]) -> Result<(), Box<dyn std::error::Error>> {
+    let path = bookmarks_path().ok_or("No data directory on this platform")?;
+    let dir = path.parent().ok_or("No data directory on this platform")?;
+    fs::create_dir_all(dir)?;
+    // Written aside and renamed so a failed write keeps the old bookmarks.
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer_pretty(&mut tmp, bookmarks)?;
+    tmp.persist(&path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Bookmarks the entries not bookmarked yet, returning how many were.
+pub fn add(results: &[SearchResult]) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut bookmarks = load()?;
+    let before = bookmarks.len();
+    for m in results {
+        if !bookmarks.iter().any(|b| b.path == m.path) {
+            bookmarks.push(SearchResult {
+                score: 0,
+                note: None,
+                enrichment: None,
+                ..m.clone()
+            });
+        }
+    }
+    let added = bookmarks.len() - before;
+    if added > 0 {
+        save(&bookmarks)?;
+    }
+    Ok(added)
+}
+
+/// Removes the bookmarks of entries named `name`, of one docset or any,
+/// returning how many there were.
+pub fn remove(docset: Option<&str>, name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut bookmarks = load()?;
+    let before = bookmarks.len();
+    bookmarks.retain(|b| b.name != name || docset.is_some_and(|d| d != b.docset));
+    let removed = before - bookmarks.len();
+    if removed > 0 {
+        save(&bookmarks)?;
+    }
+    Ok(removed)
+}
@@ -0,0 +1,124 @@
+use scraper::{Html, Selector};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::search::SearchResult;
+use crate::{archive, toc, types};
+
+/// Separator between the crumbs of a trail, for output that shows it as text.
+pub const SEPARATOR: &str = " › ";
+
+/// What a page tells about where its entries sit.
+#[derive(Default)]
+struct Page {
+    /// Its first `<h1>`, else its `<title>`.
+    title: Option<String>,
+    /// Headings with an `id`, as `(level, id, text)` in document order.
+    headings: Vec<(usize, String, String)>,
+}
+
+/// Fills in the breadcrumbs of guide and section results; other results
+/// are named well enough on their own. Each page is read once.
+pub fn annotate(results: &mut [SearchResult]) {
+    let mut pages: HashMap<PathBuf, Page> = HashMap::new();
+    for m in results {
+        if !matches!(types::normalize(&m.type_), "Guide" | "Section") {
+            continue;
+        }
+        let file = toc::strip_fragment(&m.path);
+        let page = pages
+            .entry(file.clone())
+            .or_insert_with(|| read_page(&file));
+        let crumbs = trail(&m.path, &m.name, page);
+        m.breadcrumbs = (!crumbs.is_empty()).then_some(crumbs);
+    }
+}
+
+fn read_page(file: &Path) -> Page {
+    let html = archive::materialize(file).and_then(|_| fs::read_to_string(file));
+    let Ok(html) = html else {
+        return Page::default();
+    };
+    let doc = Html::parse_document(&html);
+    let text = |el: scraper::ElementRef| {
+        let text = el.text().collect::<String>();
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    };
+    let h1 = Selector::parse("h1").unwrap();
+    let title = Selector::parse("title").unwrap();
+    let headings = Selector::parse("h1[id], h2[id], h3[id], h4[id], h5[id], h6[id]").unwrap();
+    Page {
+        title: doc
+            .select(&h1)
+            .chain(doc.select(&title))
+            .map(text)
+            .find(|t| !t.is_empty()),
+        headings: doc
+            .select(&headings)
+            .filter_map(|h| {
+                let level = h.value().name()[1..].parse().ok()?;
+                Some((level, h.value().attr("id")?.to_string(), text(h)))
+            })
+            .collect(),
+    }
+}
+
+/// The crumbs leading to the entry at `path` named `name`: the directories
+/// of its page below Documents, then the headings its anchor lies under,
+/// or the page's title when the anchor isn't a heading.
+fn trail(path: &Path, name: &str, page: &Page) -> Vec<String> {
+    let path = path.to_string_lossy();
+    let relative = path
+        .split_once("/Contents/Resources/Documents/")
+        .map_or(path.as_ref(), |(_, rest)| rest);
+    let (file, anchor) = match relative.split_once('#') {
+        Some((file, anchor)) => (file, Some(anchor)),
+        None => (relative, None),
+    };
+    let mut crumbs: Vec<String> = file
+        .rsplit_once('/')
+        .map(|(dirs, _)| {
+            dirs.split('/')
+                .filter(|d| !d.is_empty())
+                .map(humanize)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let heading = anchor.and_then(|a| page.headings.iter().position(|(_, id, _)| id == a));
+    match heading {
+        Some(at) => {
+            let mut level = page.headings[at].0;
+            let mut above = Vec::new();
+            for (l, _, text) in page.headings[..at].iter().rev() {
+                if *l < level {
+                    above.push(text.clone());
+                    level = *l;
+                }
+            }
+            // Pages often title themselves with an `<h1>` that has no id.
+            let titled = |t: &String| above.contains(t) || page.headings[at].2 == *t;
+            crumbs.extend(page.title.clone().filter(|t| !titled(t)));
+            crumbs.extend(above.into_iter().rev());
+        }
+        None if anchor.is_some() => crumbs.extend(page.title.clone()),
+        // A guide is its page; only its directories lead to it.
+        None => {}
+    }
+    crumbs.dedup();
+    if crumbs.last().is_some_and(|c| c == name) {
+        crumbs.pop();
+    }
+    crumbs
+}
+
+/// A directory name as a crumb: `getting-started` as `Getting started`.
+fn humanize(dir: &str) -> String {
+    let words = dir.replace(['-', '_'], " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
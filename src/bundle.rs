@@ -0,0 +1,293 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::install::{self, DocsetMeta};
+use crate::{archive, search};
+
+const BUNDLE_VERSION: u32 = 1;
+/// The bundle's first member, describing the rest.
+const MANIFEST: &str = "manifest.json";
+
+/// `manifest.json` of a bundle.
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    /// Version of the zeal-cli that wrote it.
+    created_by: String,
+    /// Unix time it was written.
+    created_at: u64,
+    docsets: Vec<Bundled>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Bundled {
+    name: String,
+    /// Its directory, or archive for docsets kept as one, in the bundle and
+    /// once imported: `Rust.docset`, `Rust.tgz`.
+    file: String,
+    /// What was installed from a feed, so the bundle records what it pins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    meta: Option<DocsetMeta>,
+}
+
+/// Whether a bundle is compressed with zstd, by its `.tar.zst` name, rather
+/// than gzip.
+fn is_zstd(bundle: &Path) -> bool {
+    let name = bundle.to_string_lossy().to_ascii_lowercase();
+    name.ends_with(".tar.zst") || name.ends_with(".tzst")
+}
+
+/// Runs the `zstd` program with `args`; flate2 has no zstd, so bundles
+/// compressed with it go through the program.
+fn zstd(args: &[&std::ffi::OsStr], stdin: Stdio, stdout: Stdio) -> std::io::Result<Child> {
+    Command::new("zstd")
+        .args(args)
+        .stdin(stdin)
+        .stdout(stdout)
+        .spawn()
+        .map_err(|e| std::io::Error::other(format!("Cannot run zstd for a .tar.zst bundle: {}", e)))
+}
+
+/// Waits for a `zstd` run, failing unless it succeeded.
+fn finish_zstd(mut child: Child) -> Result<(), Box<dyn std::error::Error>> {
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("zstd exited with {}", status).into());
+    }
+    Ok(())
+}
+
+/// Writes the docsets to `out`, a tarball of a manifest and each docset's
+/// directory or archive, gzipped or, for `.tar.zst`, compressed with zstd.
+/// Symlinked docsets are packed as what they point at.
+pub fn create(docsets: &[PathBuf], out: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let name = out.to_string_lossy().to_ascii_lowercase();
+    if !name.ends_with(".tar.gz") && !name.ends_with(".tgz") && !is_zstd(out) {
+        return Err(format!(
+            "{}: bundles are compressed tarballs; name it `.tar.gz`, `.tgz` or `.tar.zst`",
+            out.display()
+        )
+        .into());
+    }
+    let mut bundled = Vec::new();
+    let mut members = Vec::new();
+    for docset in docsets {
+        let (file, source) = match archive::archive_of(docset) {
+            Some(archive) => {
+                let file = archive
+                    .file_name()
+                    .ok_or("archive without a file name")?
+                    .to_string_lossy()
+                    .into_owned();
+                (file, archive)
+            }
+            None => (
+                format!("{}.docset", search::docset_name(docset)),
+                fs::canonicalize(docset)?,
+            ),
+        };
+        bundled.push(Bundled {
+            name: search::docset_name(docset),
+            file: file.clone(),
+            meta: install::read_meta(docset),
+        });
+        members.push((file, source));
+    }
+    let manifest = Manifest {
+        version: BUNDLE_VERSION,
+        created_by: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        docsets: bundled,
+    };
+
+    // Written aside and renamed so a failed bundle never looks complete.
+    let dir = out
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let tmp = tempfile::NamedTempFile::new_in(dir)?;
+    if is_zstd(out) {
+        let stdout = Stdio::from(tmp.as_file().try_clone()?);
+        let mut child = zstd(&["-q".as_ref(), "-c".as_ref()], Stdio::piped(), stdout)?;
+        let stdin = child.stdin.take().ok_or("zstd stdin unavailable")?;
+        // Closing its input lets zstd finish.
+        drop(pack(stdin, &manifest, &members)?);
+        finish_zstd(child)?;
+    } else {
+        pack(
+            GzEncoder::new(tmp.as_file(), Compression::default()),
+            &manifest,
+            &members,
+        )?
+        .finish()?;
+    }
+    tmp.persist(out).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Writes the manifest and the docsets' `(file, source)` members as a
+/// tarball to `out`, which is returned to be finished.
+fn pack<W: Write>(
+    out: W,
+    manifest: &Manifest,
+    members: &[(String, PathBuf)],
+) -> std::io::Result<W> {
+    let mut builder = tar::Builder::new(out);
+    let body = serde_json::to_vec_pretty(manifest)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(body.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST, body.as_slice())?;
+    for (file, source) in members {
+        tracing::debug!(file, source = %source.display(), "bundling");
+        if source.is_dir() {
+            builder.append_dir_all(file, source)?;
+        } else {
+            builder.append_path_with_name(source, file)?;
+        }
+    }
+    builder.into_inner()
+}
+
+/// A docset installed from a bundle.
+pub struct Imported {
+    pub name: String,
+    /// The version the bundle pins, when it records one.
+    pub version: Option<String>,
+}
+
+/// Installs the docsets of a bundle in `docsets_dir`, replacing installed
+/// ones of the same name, whether kept as a directory or an archive.
+///
+/// The bundle is unpacked into a hidden staging directory first, so an
+/// interrupted import leaves the installed docsets as they were.
+pub fn import(
+    bundle: &Path,
+    docsets_dir: &Path,
+) -> Result<Vec<Imported>, Box<dyn std::error::Error>> {
+    fs::create_dir_all(docsets_dir)?;
+    let _lock = install::lock(docsets_dir)?;
+    let staging = tempfile::Builder::new()
+        .prefix(".bundle.")
+        .tempdir_in(docsets_dir)?;
+    let unpack = |reader: &mut dyn Read| {
+        // Members outside the staging directory are skipped by `unpack`.
+        tar::Archive::new(reader)
+            .unpack(staging.path())
+            .map_err(|e| format!("{}: {}", bundle.display(), e))
+    };
+    if is_zstd(bundle) {
+        let args = [
+            "-q".as_ref(),
+            "-d".as_ref(),
+            "-c".as_ref(),
+            bundle.as_os_str(),
+        ];
+        let mut child = zstd(&args, Stdio::null(), Stdio::piped())?;
+        let mut stdout = child.stdout.take().ok_or("zstd stdout unavailable")?;
+        unpack(&mut stdout)?;
+        drop(stdout);
+        finish_zstd(child)?;
+    } else {
+        unpack(&mut GzDecoder::new(fs::File::open(bundle)?))?;
+    }
+    let manifest: Manifest = fs::read(staging.path().join(MANIFEST))
+        .map_err(|_| format!("{} is not a zeal-cli bundle", bundle.display()))
+        .and_then(|body| {
+            serde_json::from_slice(&body).map_err(|e| format!("{}: {}", bundle.display(), e))
+        })?;
+    if manifest.version > BUNDLE_VERSION {
+        return Err(format!(
+            "{} was written by a newer zeal-cli ({}, bundle version {})",
+            bundle.display(),
+            manifest.created_by,
+            manifest.version
+        )
+        .into());
+    }
+
+    // Checked up front so a bad bundle replaces nothing.
+    for docset in &manifest.docsets {
+        let mut components = Path::new(&docset.file).components();
+        let plain =
+            matches!(components.next(), Some(Component::Normal(_))) && components.next().is_none();
+        if !plain || !staging.path().join(&docset.file).exists() {
+            return Err(format!(
+                "{}: '{}' is missing from the bundle",
+                bundle.display(),
+                docset.file
+            )
+            .into());
+        }
+    }
+    for docset in &manifest.docsets {
+        let unpacked = staging.path().join(&docset.file);
+        // A directory bundled without its `meta.json` gets the one the
+        // manifest recorded, so `update` and `verify` know what it is.
+        if let Some(meta) = &docset.meta {
+            let meta_file = unpacked.join("meta.json");
+            if unpacked.is_dir() && !meta_file.exists() {
+                fs::write(meta_file, serde_json::to_vec_pretty(meta)?)?;
+            }
+        }
+        install::replace(
+            &unpacked,
+            &docsets_dir.join(&docset.file),
+            &staging.path().join(format!("previous.{}", docset.file)),
+        )?;
+        // The same docset kept in another form, `Rust.docset` beside an
+        // imported `Rust.tgz`, would be found twice.
+        for (i, other) in other_forms(docsets_dir, docset).into_iter().enumerate() {
+            fs::rename(
+                &other,
+                staging
+                    .path()
+                    .join(format!("replaced.{}.{}", i, docset.file)),
+            )?;
+        }
+    }
+    Ok(manifest
+        .docsets
+        .into_iter()
+        .map(|d| {
+            let version = d
+                .meta
+                .filter(|meta| !meta.version.is_empty())
+                .map(|meta| install::describe_version(&meta.version, &meta.revision));
+            Imported {
+                name: d.name,
+                version,
+            }
+        })
+        .collect())
+}
+
+/// The installed copies of a bundled docset other than its own file: its
+/// directory when it was bundled as an archive, its archives otherwise.
+fn other_forms(docsets_dir: &Path, docset: &Bundled) -> Vec<PathBuf> {
+    let Ok(dir) = fs::read_dir(docsets_dir) else {
+        return Vec::new();
+    };
+    dir.filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|path| path.file_name().is_some_and(|f| f != docset.file.as_str()))
+        .filter(|path| {
+            if path.is_dir() {
+                search::is_docset_dir(path) && search::docset_name(path) == docset.name
+            } else {
+                archive::archive_name(path).as_deref() == Some(docset.name.as_str())
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::search::{SearchOptions, SearchResult};
+
+/// Result sets kept before the least recently used ones are evicted.
+const MAX_ENTRIES: usize = 512;
+
+#[derive(Serialize, Deserialize, Default)]
+struct Counters {
+    hits: u64,
+    misses: u64,
+}
+
+pub struct Stats {
+    pub dir: PathBuf,
+    pub entries: usize,
+    pub bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("zeal-cli")
+        .join("results")
+}
+
+fn counters_path() -> PathBuf {
+    cache_dir().join("stats.json")
+}
+
+/// The name zeal-cli's caches give what `parts` identify: the hex of the
+/// first 16 bytes of their SHA-256, the parts separated by NULs.
+pub fn key(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            hasher.update([0]);
+        }
+        hasher.update(part.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .take(16)
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// The modification time of `file` in nanoseconds, for cache keys that
+/// must change when the file does.
+pub fn mtime(file: &Path) -> Option<String> {
+    let mtime = fs::metadata(file).and_then(|m| m.modified()).ok()?;
+    Some(
+        mtime
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_nanos()
+            .to_string(),
+    )
+}
+
+/// The [`key`] of what a docset's caches hold for `parts`, keyed on the
+/// docset's path and its index's modification time, so an updated docset
+/// is read again. `None` for a docset without an index.
+pub fn docset_key(docset_path: &Path, parts: &[&str]) -> Option<String> {
+    let mtime = mtime(&docset_path.join("Contents/Resources/docSet.dsidx"))?;
+    let path = docset_path.to_string_lossy();
+    let mut all = vec![path.as_ref(), mtime.as_str()];
+    all.extend_from_slice(parts);
+    Some(key(&all))
+}
+
+/// Writes `value` as JSON to `path`, aside and renamed so readers never see
+/// half a file. Failures are only logged: a cache is never worth failing
+/// over, and what it would have held is worked out again next time.
+pub fn save<T: Serialize + ?Sized>(path: &Path, value: &T) {
+    let write = || -> Result<(), Box<dyn std::error::Error>> {
+        let dir = path.parent().ok_or("no cache directory")?;
+        fs::create_dir_all(dir)?;
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        serde_json::to_writer(&mut tmp, value)?;
+        tmp.persist(path).map_err(|e| e.error)?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        tracing::warn!("Could not write {}: {}", path.display(), e);
+    }
+}
+
+/// Cache file for a search, keyed on the index's modification time so an
+/// updated docset never serves stale results.
+fn entry_path(docset_path: &Path, query: &str, opts: &SearchOptions) -> Option<PathBuf> {
+    let key = docset_key(docset_path, &[query, &format!("{:?}", opts)])?;
+    Some(cache_dir().join(format!("{}.json", key)))
+}
+
+fn count(hit: bool) {
+    let path = counters_path();
+    let _ = fs::create_dir_all(cache_dir());
+    let mut counters: Counters = fs::read(&path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default();
+    if hit {
+        counters.hits += 1;
+    } else {
+        counters.misses += 1;
+    }
+    if let Ok(body) = serde_json::to_vec(&counters) {
+        let _ = fs::write(path, body);
+    }
+}
+
+/// Sorted results of an earlier identical search, if still cached.
+pub fn get(docset_path: &Path, query: &str, opts: &SearchOptions) -> Option<Vec<SearchResult>> {
+    let path = entry_path(docset_path, query, opts)?;
+    let results = fs::read(&path)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok());
+    count(results.is_some());
+    if results.is_some() {
+        // The modification time doubles as the last-use time for eviction.
+        if let Ok(file) = File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        tracing::debug!(path = %path.display(), "result cache hit");
+    }
+    results
+}
+
+/// Stores sorted results, evicting the least recently used entries beyond
+/// the cache's capacity. Failures only cost a future cache miss.
+pub fn put(docset_path: &Path, query: &str, opts: &SearchOptions, results: &[SearchResult]) {
+    let Some(path) = entry_path(docset_path, query, opts) else {
+        return;
+    };
+    save(&path, results);
+    evict();
+}
+
+fn entries() -> Vec<(PathBuf, SystemTime, u64)> {
+    let Ok(dir) = fs::read_dir(cache_dir()) else {
+        return Vec::new();
+    };
+    dir.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p != &counters_path() && p.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|p| {
+            let meta = fs::metadata(&p).ok()?;
+            Some((p, meta.modified().ok()?, meta.len()))
+        })
+        .collect()
+}
+
+fn evict() {
+    let mut entries = entries();
+    if entries.len() <= MAX_ENTRIES {
+        return;
+    }
+    entries.sort_by_key(|(_, used, _)| *used);
+    for (path, _, _) in &entries[..entries.len() - MAX_ENTRIES] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Removes every cached result set, returning how many there were.
+pub fn clear() -> std::io::Result<usize> {
+    let entries = entries();
+    for (path, _, _) in &entries {
+        fs::remove_file(path)?;
+    }
+    match fs::remove_file(counters_path()) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    Ok(entries.len())
+}
+
+pub fn stats() -> Stats {
+    let entries = entries();
+    let counters: Counters = fs::read(counters_path())
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .unwrap_or_default();
+    Stats {
+        dir: cache_dir(),
+        entries: entries.len(),
+        bytes: entries.iter().map(|(_, _, len)| len).sum(),
+        hits: counters.hits,
+        misses: counters.misses,
+    }
+}
@@ -0,0 +1,72 @@
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::path::Path;
+
+use crate::clipboard::CopyField;
+use crate::export::ExportFormat;
+use crate::listing::SortKey;
+use crate::output::{GroupBy, OutputFormat, PathStyle};
+use crate::preview::ImageProtocol;
+use crate::ranking::MergeStrategy;
+use crate::search::{MatchField, MatchMode, Matcher, Prefilter};
+use crate::{config, i18n, plugin, serve};
+
+/// Bumped when a field changes meaning or goes away; new fields don't.
+const SCHEMA: u32 = 1;
+
+/// The command line spellings of a value enum's variants.
+fn names<T: ValueEnum>() -> Vec<String> {
+    T::value_variants()
+        .iter()
+        .filter_map(T::to_possible_value)
+        .map(|v| v.get_name().to_string())
+        .collect()
+}
+
+/// What this build supports, for wrappers to detect features by rather than
+/// by version: the values each option takes, the subcommands and server
+/// methods, optional features and the config file in use.
+pub fn capabilities(commands: Vec<String>, config_override: Option<&Path>) -> Value {
+    let config_path = config_override
+        .map(Path::to_path_buf)
+        .or_else(config::default_config_path);
+    json!({
+        "schema": SCHEMA,
+        "version": env!("CARGO_PKG_VERSION"),
+        "commands": commands,
+        "output_formats": names::<OutputFormat>(),
+        "match_modes": names::<MatchMode>(),
+        "matchers": names::<Matcher>(),
+        "prefilters": names::<Prefilter>(),
+        "match_fields": names::<MatchField>(),
+        "path_styles": names::<PathStyle>(),
+        "group_by": names::<GroupBy>(),
+        "merge_strategies": names::<MergeStrategy>(),
+        "sort_keys": names::<SortKey>(),
+        "export_formats": names::<ExportFormat>(),
+        "copy_fields": names::<CopyField>(),
+        "image_protocols": names::<ImageProtocol>(),
+        "serve": {
+            "methods": serve::METHODS,
+            "transports": transports(),
+        },
+        "features": {
+            "archives": ["tar.gz", "tgz", "zip"],
+            "languages": i18n::languages(),
+            "plugin_api": plugin::API,
+            "tls": true,
+        },
+        "config": {
+            "path": config_path,
+            "exists": config_path.as_ref().is_some_and(|p| p.is_file()),
+        },
+    })
+}
+
+fn transports() -> Vec<&'static str> {
+    let mut transports = vec!["stdio", "lsp", "msgpack", "http"];
+    if cfg!(unix) {
+        transports.push("socket");
+    }
+    transports
+}
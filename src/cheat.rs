@@ -0,0 +1,121 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use scraper::{ElementRef, Html, Selector};
+use std::fs;
+use std::path::Path;
+
+use crate::render::{self, Flavor};
+use crate::{archive, search};
+
+/// One entry of a cheat sheet: a task with the commands that perform it.
+pub struct CheatEntry {
+    pub category: String,
+    pub name: String,
+    pub commands: Vec<String>,
+    pub notes: String,
+}
+
+/// Whether the docset is a Dash cheat sheet, as generated by `cheatset`.
+pub fn is_cheatsheet(docset_path: &Path) -> bool {
+    search::info_plist(docset_path)
+        .and_then(|i| i.get("DashDocSetFamily")?.as_string().map(str::to_string))
+        .is_some_and(|family| family.eq_ignore_ascii_case("cheatsheet"))
+}
+
+/// Reads every entry of a cheat sheet from its single HTML page, in page order.
+///
+/// Entries are `<tr id="//dash_ref_<Category>/Entry/<Name>/0">` rows holding
+/// `.name`, `.command` and `.notes` elements.
+pub fn entries(docset_path: &Path) -> Result<Vec<CheatEntry>, Box<dyn std::error::Error>> {
+    let index = search::info_plist(docset_path)
+        .and_then(|i| i.get("dashIndexFilePath")?.as_string().map(str::to_string))
+        .unwrap_or_else(|| "index.html".to_string());
+    let page = docset_path.join("Contents/Resources/Documents").join(index);
+    archive::materialize(&page)?;
+    let html = fs::read_to_string(page)?;
+    let doc = Html::parse_document(&html);
+
+    let rows = Selector::parse("tr[id], div[id]").unwrap();
+    let name_sel = Selector::parse(".name").unwrap();
+    let command_sel = Selector::parse(".command").unwrap();
+    let code_sel = Selector::parse("code").unwrap();
+    let notes_sel = Selector::parse(".notes").unwrap();
+
+    let mut entries = Vec::new();
+    for row in doc.select(&rows) {
+        let id = urlencoding::decode(row.value().id().unwrap_or_default())
+            .map(|s| s.into_owned())
+            .unwrap_or_default();
+        let Some((category, rest)) = id
+            .strip_prefix("//dash_ref_")
+            .and_then(|r| r.split_once("/Entry/"))
+        else {
+            continue;
+        };
+        let text = |el: ElementRef| el.text().collect::<String>().trim().to_string();
+        let name = row
+            .select(&name_sel)
+            .next()
+            .map(text)
+            .unwrap_or_else(|| rest.rsplit_once('/').map_or(rest, |(n, _)| n).to_string());
+        let commands = row
+            .select(&command_sel)
+            .flat_map(|c| {
+                let codes: Vec<String> = c.select(&code_sel).map(text).collect();
+                if codes.is_empty() {
+                    vec![text(c)]
+                } else {
+                    codes
+                }
+            })
+            .filter(|c| !c.is_empty())
+            .collect();
+        let notes = row
+            .select(&notes_sel)
+            .map(|n| render::element_to(n, Flavor::Text))
+            .collect::<Vec<_>>()
+            .join("\n");
+        entries.push(CheatEntry {
+            category: category.to_string(),
+            name,
+            commands,
+            notes,
+        });
+    }
+    Ok(entries)
+}
+
+/// Prints a cheat sheet grouped by category, keeping only the entries whose
+/// name, category or command fuzzily matches `query` when one is given.
+pub fn show(docset_path: &Path, query: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    let matcher = SkimMatcherV2::default();
+    let entries: Vec<CheatEntry> = entries(docset_path)?
+        .into_iter()
+        .filter(|e| {
+            query.is_empty()
+                || std::iter::once(&e.name)
+                    .chain(std::iter::once(&e.category))
+                    .chain(&e.commands)
+                    .any(|field| matcher.fuzzy_match(field, query).is_some())
+        })
+        .collect();
+
+    let mut category = None;
+    for entry in &entries {
+        if category != Some(&entry.category) {
+            if category.is_some() {
+                println!();
+            }
+            println!("{}", entry.category);
+            category = Some(&entry.category);
+        }
+        println!("  {}", entry.name);
+        for command in &entry.commands {
+            println!("    {}", command);
+        }
+        for line in entry.notes.lines() {
+            println!("      {}", line);
+        }
+    }
+    Ok(entries.len())
+}
@@ -0,0 +1,69 @@
+use console::{Key, Term};
+use std::io::IsTerminal;
+use std::path::PathBuf;
+
+use crate::{listing, search};
+
+/// Docsets numbered for picking with one key.
+const SHOWN: usize = 9;
+
+/// Asks on the terminal which of `docsets` to search, for `search` given
+/// no docset and with no default to fall back on. A digit picks one of the
+/// docsets shown, other keys narrow them down by fuzzy name, Enter picks
+/// the first and Esc searches all of them. `None` when there is no one to
+/// ask, as when stdin or stderr isn't a terminal, or nothing to choose
+/// from.
+pub fn choose(docsets: &[PathBuf]) -> Option<Vec<PathBuf>> {
+    if docsets.len() < 2 || !std::io::stdin().is_terminal() {
+        return None;
+    }
+    let term = Term::stderr();
+    if !term.is_term() {
+        return None;
+    }
+    let mut filter = String::new();
+    let mut drawn = 0;
+    let picked = loop {
+        let shown: Vec<PathBuf> = listing::filter(docsets.to_vec(), &filter)
+            .into_iter()
+            .take(SHOWN)
+            .collect();
+        term.clear_last_lines(drawn).ok()?;
+        term.write_line(&format!(
+            "Search which docset? 1-{} picks, typing filters, Enter the first, Esc all: {}",
+            shown.len().max(1),
+            filter
+        ))
+        .ok()?;
+        for (i, docset) in shown.iter().enumerate() {
+            term.write_line(&format!("  {}  {}", i + 1, search::docset_name(docset)))
+                .ok()?;
+        }
+        drawn = shown.len() + 1;
+        match term.read_key().ok()? {
+            Key::Char(c) if c.is_ascii_digit() && c != '0' => {
+                let n = c.to_digit(10).unwrap_or_default() as usize;
+                if let Some(docset) = shown.get(n - 1) {
+                    break vec![docset.clone()];
+                }
+            }
+            Key::Char(c) if !c.is_control() => filter.push(c),
+            Key::Backspace => {
+                filter.pop();
+            }
+            Key::Enter => {
+                if let Some(docset) = shown.first() {
+                    break vec![docset.clone()];
+                }
+            }
+            Key::Escape => break docsets.to_vec(),
+            Key::CtrlC => {
+                let _ = term.clear_last_lines(drawn);
+                std::process::exit(130);
+            }
+            _ => {}
+        }
+    };
+    term.clear_last_lines(drawn).ok()?;
+    Some(picked)
+}
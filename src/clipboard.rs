@@ -0,0 +1,77 @@
+use clap::ValueEnum;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::open;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum CopyField {
+    /// Local file path, including any `#anchor`
+    Path,
+    /// `file://` (or remote) URL suitable for a browser
+    Url,
+    /// Entry name
+    Name,
+}
+
+/// Text of the chosen field for an entry.
+pub fn field_text(field: CopyField, name: &str, path: &Path) -> String {
+    match field {
+        CopyField::Path => path.display().to_string(),
+        CopyField::Url => open::entry_url(path),
+        CopyField::Name => name.to_string(),
+    }
+}
+
+/// Puts `text` on the system clipboard.
+///
+/// On Linux the selection is owned by a process, so it vanishes when we exit
+/// unless a clipboard manager grabs it. `wl-copy`, `xclip` and `xsel` fork a
+/// helper that keeps serving it, so they're preferred when installed.
+pub fn set_text(text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let tools: &[(&str, &[&str])] = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            &[("wl-copy", &[])]
+        } else {
+            &[
+                ("xclip", &["-selection", "clipboard"]),
+                ("xsel", &["--clipboard", "--input"]),
+            ]
+        };
+        for (tool, args) in tools {
+            if which::which(tool).is_ok() {
+                return pipe_to(tool, args, text);
+            }
+        }
+    }
+    arboard::Clipboard::new()?.set_text(text)?;
+    Ok(())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn pipe_to(tool: &str, args: &[&str], text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or("Clipboard tool stdin unavailable")?
+        .write_all(text.as_bytes())?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", tool, status).into());
+    }
+    Ok(())
+}
+
+pub fn copy(field: CopyField, name: &str, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let text = field_text(field, name, path);
+    set_text(&text)?;
+    eprintln!("Copied {}", text);
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::history::History;
+use crate::plugin::Plugin;
+use crate::ranking::Ranking;
+use crate::theme::Colors;
+use crate::types;
+
+/// User configuration, read from `config.toml` in the zeal-cli config directory.
+///
+/// ```toml
+/// # Searched by `search <query>` when no docset is named
+/// default_docsets = ["Rust"]
+/// # Count opened entries for `stats --usage`
+/// track_usage = true
+/// # The docsets directory is on a network mount, see `--slow-fs`
+/// slow_fs = true
+/// # Links `check-links` verifies besides `zeal://Docset/name`
+/// link_patterns = ['\[\[(?P<docset>\w+):(?P<name>[^\]]+)\]\]']
+///
+/// [groups]
+/// web = ["HTML", "CSS", "JavaScript"]
+/// backend = ["Rust", "PostgreSQL"]
+///
+/// # Query terms expanded before matching, per docset or for all ("*")
+/// [synonyms.Rust]
+/// dict = "HashMap"
+/// println = ["std::fmt", "print"]
+///
+/// # `search py:os.path` searches the docsets listed for `py`
+/// [keywords]
+/// py = "Python_3"
+///
+/// # How results of several docsets are combined, see `ranking::Ranking`
+/// [ranking]
+/// strategy = "global-score"
+///
+/// # Entries left out of results, per docset or for all ("*")
+/// [ignore.Java]
+/// types = ["Sample", "Test"]
+/// names = ["^sun\\.", "Internal$"]
+///
+/// # Past queries kept shell-history style, see `history::History`
+/// [history]
+/// enabled = true
+///
+/// # Results shown when no `--limit` is given, by `--output` format,
+/// # `menu` launcher (`rofi`, `dmenu`, `wofi`) or `serve` requests;
+/// # `serve` requests get 100 unless set otherwise
+/// [limits]
+/// rofi = 15
+/// quickfix = 50
+///
+/// # Programs fed results as JSON, see `plugin::Plugin`
+/// [plugins.jira]
+/// command = ["jira-links"]
+/// transform = true
+/// ```
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Named docset lists, searched with `search @name <query>`.
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Docset name (or `*`) to query term to canonical names.
+    pub synonyms: BTreeMap<String, BTreeMap<String, OneOrMany>>,
+    pub ranking: Ranking,
+    /// Search keywords to docset names, for `search py:os.path`. Docsets
+    /// also answer to keywords they declare themselves.
+    pub keywords: BTreeMap<String, OneOrMany>,
+    /// Docsets searched by `search` when no docset is named.
+    pub default_docsets: Vec<String>,
+    /// Docset name (or `*`) to entries to leave out of its results.
+    pub ignore: BTreeMap<String, Ignore>,
+    /// Record each opened entry in the usage log, see `usage`.
+    pub track_usage: bool,
+    /// Treat the docsets directory as a network mount, see `slow_fs`.
+    pub slow_fs: bool,
+    /// Colours of icons and highlighting, see `theme::Colors`.
+    pub colors: Colors,
+    /// Patterns of doc links `check-links` verifies besides `zeal://` ones,
+    /// capturing `docset` and `name`.
+    #[serde(deserialize_with = "regexes")]
+    pub link_patterns: Vec<Regex>,
+    /// The file of past queries, see `history::History`.
+    pub history: History,
+    /// External programs that rewrite or print results, by name.
+    pub plugins: BTreeMap<String, Plugin>,
+    /// Default result limits by output target; targets without one here
+    /// or in `DEFAULT_LIMITS` are unlimited.
+    pub limits: BTreeMap<String, usize>,
+}
+
+/// Limits of targets the user sets none for: a server answers requests
+/// that name none with a page of results rather than the whole docset.
+const DEFAULT_LIMITS: &[(&str, usize)] = &[("serve", 100)];
+
+/// Entries to leave out of a docset's results.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Ignore {
+    /// Entry types, in any spelling `--type` accepts.
+    pub types: Vec<String>,
+    /// Patterns searched for in entry names.
+    #[serde(deserialize_with = "regexes")]
+    pub names: Vec<Regex>,
+}
+
+fn regexes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Regex>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+impl Ignore {
+    /// Whether an entry named `name` of type `raw_type` is left out.
+    pub fn ignores(&self, name: &str, raw_type: &str) -> bool {
+        self.types.iter().any(|t| types::matches(raw_type, t))
+            || self.names.iter().any(|re| re.is_match(name))
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            OneOrMany::One(s) => std::slice::from_ref(s),
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+impl Config {
+    /// The configured limit for an output target such as `quickfix` or `rofi`.
+    pub fn limit_for(&self, target: &str) -> Option<usize> {
+        self.limits
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(target))
+            .map(|(_, limit)| *limit)
+            .or_else(|| {
+                DEFAULT_LIMITS
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(target))
+                    .map(|(_, limit)| *limit)
+            })
+    }
+
+    /// The ignore rules for `docset`, those for all docsets (`*`) included.
+    pub fn ignore_for(&self, docset: &str) -> Ignore {
+        let mut merged = Ignore::default();
+        for (name, ignore) in &self.ignore {
+            if name == "*" || name.eq_ignore_ascii_case(docset) {
+                merged.types.extend(ignore.types.iter().cloned());
+                merged.names.extend(ignore.names.iter().cloned());
+            }
+        }
+        merged
+    }
+
+    /// Alternative queries for `docset` with each term that has synonyms
+    /// replaced by its canonical names. Terms compare case-insensitively.
+    pub fn expand_query(&self, docset: &str, query: &str) -> Vec<String> {
+        let tables = ["*", docset]
+            .into_iter()
+            .filter_map(|key| {
+                self.synonyms
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                    .map(|(_, table)| table)
+            })
+            .collect::<Vec<_>>();
+        let lookup = |term: &str| -> Vec<&String> {
+            tables
+                .iter()
+                .flat_map(|table| table.iter())
+                .filter(|(key, _)| key.eq_ignore_ascii_case(term))
+                .flat_map(|(_, names)| names.as_slice())
+                .collect()
+        };
+
+        let mut expanded = Vec::new();
+        // The whole query may be a key itself, e.g. `"hash map" = "HashMap"`.
+        expanded.extend(lookup(query.trim()).into_iter().cloned());
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        if terms.len() > 1 {
+            for (i, term) in terms.iter().enumerate() {
+                for canonical in lookup(term) {
+                    let mut replaced = terms.clone();
+                    replaced[i] = canonical;
+                    expanded.push(replaced.join(" "));
+                }
+            }
+        }
+        expanded.dedup();
+        expanded
+    }
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("zeal-cli").join("config.toml"))
+}
+
+/// Loads the config from `override_path` or the default location. A missing
+/// file yields the default config; a malformed one is an error.
+pub fn load(override_path: Option<&Path>) -> Result<Config, Box<dyn std::error::Error>> {
+    let Some(path) = override_path
+        .map(Path::to_path_buf)
+        .or_else(default_config_path)
+    else {
+        return Ok(Config::default());
+    };
+    tracing::debug!(path = %path.display(), "loading config");
+    match fs::read_to_string(&path) {
+        Ok(body) => toml::from_str(&body).map_err(|e| format!("{}: {}", path.display(), e).into()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && override_path.is_none() => {
+            Ok(Config::default())
+        }
+        Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+    }
+}
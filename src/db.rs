@@ -0,0 +1,143 @@
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{platform, search, slow_fs};
+
+/// Tries before falling back to a copy, each waiting twice as long.
+const RETRIES: u32 = 4;
+const FIRST_BACKOFF: Duration = Duration::from_millis(50);
+/// How long SQLite itself waits on a lock within one try.
+const BUSY_TIMEOUT: Duration = Duration::from_millis(250);
+
+type Error = Box<dyn std::error::Error>;
+
+/// What went wrong reading an index, for choosing what to do about it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Failure {
+    /// Another process, usually Zeal updating the docset, holds a lock.
+    Busy,
+    /// Not a readable database, possibly because it is being rewritten.
+    Corrupt,
+    Other,
+}
+
+fn failure(e: &Error) -> Failure {
+    let code = e
+        .downcast_ref::<rusqlite::Error>()
+        .and_then(rusqlite::Error::sqlite_error_code);
+    match code {
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => Failure::Busy,
+        Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => Failure::Corrupt,
+        _ => Failure::Other,
+    }
+}
+
+fn open(db_path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(
+        platform::long_path(db_path),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    Ok(conn)
+}
+
+/// Runs `read` against a docset's `docSet.dsidx`, opened read-only.
+///
+/// A locked index is retried with backoff. One that stays locked or reads
+/// as corrupt, as happens while Zeal rewrites it, is copied aside and the
+/// copy read instead. Failing that, the error says what to do about it.
+pub fn read<T>(
+    docset_path: &Path,
+    read: impl Fn(&Connection) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let mut db_path = docset_path.join("Contents/Resources/docSet.dsidx");
+    if slow_fs::enabled() {
+        match slow_fs::local_index(&db_path) {
+            Ok(copy) => db_path = copy,
+            Err(e) => tracing::debug!(db = %db_path.display(), "not copying the index: {}", e),
+        }
+    }
+    let mut backoff = FIRST_BACKOFF;
+    let mut last = None;
+    for attempt in 0..=RETRIES {
+        let result = open(&db_path)
+            .map_err(Error::from)
+            .and_then(|conn| read(&conn));
+        let e = match result {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        match failure(&e) {
+            Failure::Busy if attempt < RETRIES => {
+                tracing::debug!(db = %db_path.display(), attempt, "index busy, retrying");
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+            Failure::Busy | Failure::Corrupt => {
+                last = Some(e);
+                break;
+            }
+            Failure::Other => return Err(e),
+        }
+    }
+    let e = last.expect("only busy or corrupt reads leave the loop");
+    match read_copy(&db_path, &read) {
+        Ok(value) => {
+            tracing::warn!(
+                "{} was unreadable ({}); read a copy of it",
+                db_path.display(),
+                e
+            );
+            Ok(value)
+        }
+        Err(copy_error) => {
+            tracing::debug!(error = %copy_error, "reading a copy failed too");
+            Err(remedy(docset_path, &db_path, e))
+        }
+    }
+}
+
+/// Copies the index, with its write-ahead log if any, to a temporary
+/// directory and reads the copy, which no other process can lock.
+fn read_copy<T>(
+    db_path: &Path,
+    read: impl Fn(&Connection) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let dir = tempfile::tempdir()?;
+    let copy = dir.path().join("docSet.dsidx");
+    fs::copy(platform::long_path(db_path), &copy)?;
+    let wal = db_path.with_extension("dsidx-wal");
+    if wal.is_file() {
+        fs::copy(
+            platform::long_path(&wal),
+            dir.path().join("docSet.dsidx-wal"),
+        )?;
+    }
+    // Not read-only, so SQLite can replay the copied log.
+    let conn = Connection::open(&copy)?;
+    read(&conn)
+}
+
+/// The error for an index that couldn't be read, with what to do about it.
+fn remedy(docset_path: &Path, db_path: &Path, e: Error) -> Error {
+    let name = search::docset_name(docset_path);
+    match failure(&e) {
+        Failure::Busy => format!(
+            "{} is locked, most likely by Zeal updating the docset; \
+             try again once the update has finished, or quit Zeal",
+            db_path.display()
+        )
+        .into(),
+        Failure::Corrupt => format!(
+            "{} is damaged ({}); if Zeal is updating the docset try again once \
+             it has finished, otherwise reinstall it from Zeal or with `zeal-cli install {}`",
+            db_path.display(),
+            e,
+            name
+        )
+        .into(),
+        Failure::Other => e,
+    }
+}
@@ -0,0 +1,189 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::search::{self, Entry};
+use crate::{archive, install, versions};
+
+/// An entry as `diff` reports it.
+#[derive(Serialize, Clone, Debug)]
+pub struct Symbol {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Below the docset's Documents directory, fragment included.
+    pub path: String,
+}
+
+/// How the entries of one version of a docset differ from another's.
+#[derive(Serialize, Default)]
+pub struct Diff {
+    pub added: Vec<Symbol>,
+    pub removed: Vec<Symbol>,
+    pub renamed: Vec<Renamed>,
+}
+
+/// A removed entry whose page and anchor an added entry of the same type
+/// took over.
+#[derive(Serialize)]
+pub struct Renamed {
+    pub from: Symbol,
+    pub to: Symbol,
+}
+
+/// The docset `against` names for `docset_path` to be compared with: a
+/// docset directory, a docset archive, or a version of it in the archive
+/// store that `pin` and `rollback` use.
+pub fn other_version(
+    docset_path: &Path,
+    against: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = Path::new(against);
+    if path.is_dir() {
+        if !search::is_docset_dir(path) {
+            return Err(format!("{} is not a docset directory", path.display()).into());
+        }
+        return Ok(path.to_path_buf());
+    }
+    if path.is_file() {
+        return archive::open(path)?
+            .ok_or_else(|| format!("{} holds no docset index", path.display()).into());
+    }
+    let name = install::read_meta(docset_path)
+        .map(|m| m.name)
+        .unwrap_or_else(|| search::docset_name(docset_path));
+    let manifest = versions::load(&name);
+    let stored = manifest
+        .versions
+        .iter()
+        .rev()
+        .find(|v| v.meta.version == against)
+        .ok_or_else(|| {
+            let stored: Vec<&str> = manifest
+                .versions
+                .iter()
+                .map(|v| v.meta.version.as_str())
+                .collect();
+            format!(
+                "'{}' is no docset directory, archive or stored version of {} (stored: {})",
+                against,
+                name,
+                if stored.is_empty() {
+                    "none".to_string()
+                } else {
+                    stored.join(", ")
+                }
+            )
+        })?;
+    let archive = versions::store_dir(&name)
+        .ok_or("No data directory for the archive store")?
+        .join(&stored.archive);
+    archive::open(&archive)?
+        .ok_or_else(|| format!("{} holds no docset index", archive.display()).into())
+}
+
+fn symbol(entry: &Entry) -> Symbol {
+    Symbol {
+        name: entry.name.clone(),
+        type_: entry.type_.clone(),
+        path: search::relative_path(&entry.path).to_string(),
+    }
+}
+
+/// Compares entries by name and type; where several share both, as
+/// overloads do, one of them stands for all.
+pub fn diff(old: &[Entry], new: &[Entry]) -> Diff {
+    let by_key = |entries: &[Entry]| -> BTreeMap<(String, String), Symbol> {
+        entries
+            .iter()
+            .map(|e| ((e.name.clone(), e.type_.clone()), symbol(e)))
+            .collect()
+    };
+    let (old, new) = (by_key(old), by_key(new));
+    let mut removed: Vec<Symbol> = old
+        .iter()
+        .filter(|(key, _)| !new.contains_key(*key))
+        .map(|(_, s)| s.clone())
+        .collect();
+    let added: Vec<Symbol> = new
+        .iter()
+        .filter(|(key, _)| !old.contains_key(*key))
+        .map(|(_, s)| s.clone())
+        .collect();
+
+    // A page and anchor that stayed while the name changed is a rename;
+    // pages without anchors hold too many entries to tell.
+    let mut diff = Diff::default();
+    for symbol in added {
+        let old = removed.iter().position(|r| {
+            symbol.path.contains('#') && r.type_ == symbol.type_ && r.path == symbol.path
+        });
+        match old {
+            Some(i) => diff.renamed.push(Renamed {
+                from: removed.remove(i),
+                to: symbol,
+            }),
+            None => diff.added.push(symbol),
+        }
+    }
+    diff.removed = removed;
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, type_: &str, path: &str) -> Entry {
+        Entry {
+            name: name.to_string(),
+            type_: type_.to_string(),
+            path: PathBuf::from("/d/Ex.docset/Contents/Resources/Documents").join(path),
+        }
+    }
+
+    fn names(symbols: &[Symbol]) -> Vec<&str> {
+        symbols.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn reports_added_removed_and_renamed_entries() {
+        let old = [
+            entry("Vec::push", "Method", "vec.html#push"),
+            entry("Vec::len", "Method", "vec.html#len"),
+            entry("Vec::drain_filter", "Method", "vec.html#extract_if"),
+            entry("Guide", "Guide", "guide.html"),
+        ];
+        let new = [
+            entry("Vec::push", "Method", "vec.html#push"),
+            entry("Vec::extract_if", "Method", "vec.html#extract_if"),
+            entry("Vec::pop", "Method", "vec.html#pop"),
+            entry("Tutorial", "Guide", "guide.html"),
+        ];
+        let changes = diff(&old, &new);
+        // Pages without an anchor hold too many entries to call a rename.
+        assert_eq!(names(&changes.added), ["Tutorial", "Vec::pop"]);
+        assert_eq!(names(&changes.removed), ["Guide", "Vec::len"]);
+        assert_eq!(changes.renamed.len(), 1);
+        assert_eq!(changes.renamed[0].from.name, "Vec::drain_filter");
+        assert_eq!(changes.renamed[0].to.name, "Vec::extract_if");
+    }
+
+    #[test]
+    fn overloads_sharing_name_and_type_count_once() {
+        let old = [
+            entry("max", "Function", "max.html#max-1"),
+            entry("max", "Function", "max.html#max-2"),
+        ];
+        let new = [entry("max", "Function", "max.html#max-1")];
+        let changes = diff(&old, &new);
+        assert!(changes.added.is_empty());
+        assert!(changes.removed.is_empty());
+        assert!(changes.renamed.is_empty());
+
+        let new = [entry("max", "Method", "max.html#max-1")];
+        let changes = diff(&old, &new);
+        assert_eq!(names(&changes.added), ["max"]);
+        assert_eq!(names(&changes.removed), ["max"]);
+    }
+}
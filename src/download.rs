@@ -0,0 +1,239 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::net::Net;
+
+/// Kapeli's docset CDN mirrors, all serving the same `/feeds/<Name>.tgz` paths.
+const KAPELI_MIRRORS: &[&str] = &[
+    "sanfrancisco",
+    "newyork",
+    "london",
+    "frankfurt",
+    "tokyo",
+    "sydney",
+];
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub struct DownloadOptions {
+    pub retries: u32,
+    pub progress: bool,
+    /// Refuse archives the feed publishes no checksum for, and check the
+    /// installed files against the archive's.
+    pub verify: bool,
+}
+
+/// Expands a URL on one Kapeli mirror into the same URL on every mirror,
+/// keeping the original first. Other URLs are returned unchanged.
+pub fn mirrors(url: &str) -> Vec<String> {
+    let Some(rest) = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"))
+    else {
+        return vec![url.to_string()];
+    };
+    let scheme = &url[..url.len() - rest.len()];
+    let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+    let Some(city) = host.strip_suffix(".kapeli.com") else {
+        return vec![url.to_string()];
+    };
+    if !KAPELI_MIRRORS.contains(&city) {
+        return vec![url.to_string()];
+    }
+
+    let mut urls = vec![url.to_string()];
+    urls.extend(
+        KAPELI_MIRRORS
+            .iter()
+            .filter(|m| **m != city)
+            .map(|m| format!("{}{}.kapeli.com/{}", scheme, m, path)),
+    );
+    urls
+}
+
+/// Orders candidate URLs by how fast they answer a `HEAD` request, dropping
+/// none: unreachable mirrors are kept at the end so they can still be retried.
+pub fn rank_by_latency(net: &Net, urls: Vec<String>) -> Vec<String> {
+    if urls.len() < 2 {
+        return urls;
+    }
+    let mut timed: Vec<(Duration, String)> = std::thread::scope(|s| {
+        let handles: Vec<_> = urls
+            .iter()
+            .map(|url| {
+                s.spawn(move || {
+                    let Ok(builder) = net.builder_for(url) else {
+                        return Duration::MAX;
+                    };
+                    let agent = builder.timeout(PROBE_TIMEOUT).build();
+                    let start = Instant::now();
+                    match agent.head(url).call() {
+                        Ok(_) => start.elapsed(),
+                        Err(_) => Duration::MAX,
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .zip(urls.iter().cloned())
+            .map(|(h, url)| (h.join().unwrap_or(Duration::MAX), url))
+            .collect()
+    });
+    timed.sort_by_key(|(latency, _)| *latency);
+    timed.into_iter().map(|(_, url)| url).collect()
+}
+
+/// Location of the resumable partial download of `url` as of `version`.
+///
+/// Feeds serve every version of a docset from the same URL, so a partial
+/// file is only resumed for the same URL and version it was started for.
+pub fn partial_path(url: &str, version: &str) -> PathBuf {
+    let key = sha256_hex(format!("{}\0{}", url, version).as_bytes()).unwrap_or_default();
+    let file_name = url.rsplit('/').next().unwrap_or_default();
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("zeal-cli")
+        .join("downloads")
+        .join(format!("{:.16}-{}.part", key, file_name))
+}
+
+/// Downloads the first reachable URL into `dest`, resuming from whatever a
+/// previous attempt left there and retrying with exponential backoff.
+pub fn download(
+    net: &Net,
+    urls: &[String],
+    dest: &Path,
+    opts: &DownloadOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    net.ensure_online()?;
+    let mut last_err: Box<dyn std::error::Error> = "No download URL".into();
+
+    for attempt in 0..=opts.retries {
+        if attempt > 0 {
+            let backoff = Duration::from_secs(1 << (attempt - 1).min(5));
+            eprintln!(
+                "Download failed ({}), retrying in {}s...",
+                last_err,
+                backoff.as_secs()
+            );
+            std::thread::sleep(backoff);
+        }
+        // Rotate through mirrors so a dead mirror doesn't eat every retry.
+        let url = &urls[attempt as usize % urls.len()];
+        match net
+            .agent_for(url)
+            .and_then(|agent| fetch(&agent, url, dest, opts.progress))
+        {
+            Ok(()) => return Ok(()),
+            Err(e) => last_err = e,
+        }
+    }
+    Err(last_err)
+}
+
+fn fetch(
+    agent: &ureq::Agent,
+    url: &str,
+    dest: &Path,
+    progress: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let offset = fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
+    let mut request = agent.get(url);
+    if offset > 0 {
+        request = request.set("Range", &format!("bytes={}-", offset));
+    }
+
+    let response = match request.call() {
+        // The partial file is already complete (or the server disagrees about
+        // its size); start over rather than guessing.
+        Err(ureq::Error::Status(416, _)) => {
+            fs::remove_file(dest)?;
+            return Err("Server rejected resume range".into());
+        }
+        other => other?,
+    };
+
+    let resumed = response.status() == 206;
+    let mut file = if resumed {
+        OpenOptions::new().append(true).open(dest)?
+    } else {
+        File::create(dest)?
+    };
+    let start = if resumed { offset } else { 0 };
+    let total = response
+        .header("Content-Length")
+        .and_then(|l| l.parse::<u64>().ok())
+        .map(|l| l + start);
+
+    let bar = if progress && io::stderr().is_terminal() {
+        let bar = total.map_or_else(ProgressBar::no_length, ProgressBar::new);
+        bar.set_style(ProgressStyle::with_template(
+            "{bar:40} {bytes}/{total_bytes} {bytes_per_sec} eta {eta}",
+        )?);
+        bar.set_position(start);
+        bar
+    } else {
+        ProgressBar::hidden()
+    };
+
+    let mut reader = response.into_reader();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n])?;
+        bar.inc(n as u64);
+    }
+    file.flush()?;
+    bar.finish_and_clear();
+
+    if let Some(total) = total {
+        let len = fs::metadata(dest)?.len();
+        if len != total {
+            return Err(format!("Incomplete download: {} of {} bytes", len, total).into());
+        }
+    }
+    Ok(())
+}
+
+pub fn sha256_hex(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Fails when the file's SHA-256 digest differs from `expected` (hex, any case).
+pub fn verify_sha256(path: &Path, expected: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let actual = sha256_hex(File::open(path)?)?;
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        return Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            path.display(),
+            expected.trim(),
+            actual
+        )
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{anchors, archive, cache, install, listing, search, slow_fs, symbols, versions};
+
+/// Disk use of one installed docset.
+pub struct Usage {
+    pub name: String,
+    /// The docset's directory, or the archive it is kept as.
+    pub docset: u64,
+    /// Its symbol indexes, see `index build --symbols`.
+    pub index: u64,
+    /// Archives stored for `rollback`.
+    pub versions: u64,
+}
+
+pub fn usage(docset_path: &Path) -> Usage {
+    let docset = match archive::archive_of(docset_path) {
+        Some(archive) => fs::metadata(archive).map(|m| m.len()).unwrap_or(0),
+        // Symlinked docsets count at the size of what they point at.
+        None => listing::dir_size(
+            &fs::canonicalize(docset_path).unwrap_or_else(|_| docset_path.to_path_buf()),
+        ),
+    };
+    let name = search::docset_name(docset_path);
+    let versions = versions::store_dir(&name)
+        .map(|dir| listing::dir_size(&dir))
+        .unwrap_or(0);
+    Usage {
+        docset,
+        index: symbols::size(docset_path),
+        versions,
+        name,
+    }
+}
+
+/// What `--prune-orphans` removes: staging directories of aborted installs
+/// in the docset roots, symbol indexes of docsets since updated or removed,
+/// and caches of archives since replaced or removed.
+pub fn orphans(roots: &[PathBuf]) -> Vec<PathBuf> {
+    roots
+        .iter()
+        .flat_map(|root| install::abandoned(root))
+        .chain(symbols::orphans())
+        .chain(archive::orphans())
+        .collect()
+}
+
+/// Removes the orphans, returning how many there were and the bytes freed.
+/// Each docset root's install lock is held while its staging directories
+/// are removed, so installs running meanwhile keep theirs.
+pub fn prune_orphans(roots: &[PathBuf]) -> Result<(usize, u64), Box<dyn std::error::Error>> {
+    let mut count = 0;
+    let mut bytes = 0;
+    let mut remove = |path: &Path| -> std::io::Result<()> {
+        let size = listing::dir_size(path);
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+        tracing::debug!(path = %path.display(), size, "removed orphan");
+        count += 1;
+        bytes += size;
+        Ok(())
+    };
+    for root in roots {
+        if install::abandoned(root).is_empty() {
+            continue;
+        }
+        let _lock = install::lock(root)?;
+        for path in install::abandoned(root) {
+            remove(&path)?;
+        }
+    }
+    for path in symbols::orphans().into_iter().chain(archive::orphans()) {
+        remove(&path)?;
+    }
+    Ok((count, bytes))
+}
+
+/// A byte count in binary units, `12.3 MiB`.
+pub fn size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Prints each docset's disk use, then the totals of everything zeal-cli
+/// keeps on disk.
+pub fn print(docsets: &[Usage], roots: &[PathBuf]) {
+    let name_width = docsets
+        .iter()
+        .map(|u| u.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Docset".len());
+    println!(
+        "{:<name_width$}  {:>10}  {:>10}  {:>10}",
+        "Docset", "Size", "Index", "Versions"
+    );
+    for u in docsets {
+        println!(
+            "{:<name_width$}  {:>10}  {:>10}  {:>10}",
+            u.name,
+            size(u.docset),
+            size(u.index),
+            size(u.versions)
+        );
+    }
+
+    let orphans = orphans(roots);
+    let totals = [
+        ("Docsets", docsets.iter().map(|u| u.docset).sum()),
+        ("Symbol indexes", listing::dir_size(&symbols::index_dir())),
+        (
+            "Stored versions",
+            versions::store_root()
+                .map(|dir| listing::dir_size(&dir))
+                .unwrap_or(0),
+        ),
+        ("Archive cache", listing::dir_size(&archive::cache_dir())),
+        ("Index copies", listing::dir_size(&slow_fs::index_dir())),
+        ("Anchor cache", listing::dir_size(&anchors::cache_dir())),
+        ("Result cache", cache::stats().bytes),
+    ];
+    println!();
+    for (label, bytes) in &totals {
+        println!("{:<16}  {:>10}", label, size(*bytes));
+    }
+    println!(
+        "{:<16}  {:>10}",
+        "Total",
+        size(totals.iter().map(|(_, b)| b).sum())
+    );
+    if !orphans.is_empty() {
+        let bytes: u64 = orphans.iter().map(|p| listing::dir_size(p)).sum();
+        println!();
+        println!(
+            "{} left by aborted installs and stale indexes, in {} files and directories; \
+             reclaim it with `du --prune-orphans`",
+            size(bytes),
+            orphans.len()
+        );
+    }
+}
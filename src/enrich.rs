@@ -0,0 +1,179 @@
+use regex::Regex;
+use scraper::{ElementRef, Html};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::search::SearchResult;
+use crate::{cache, preview, related};
+
+/// What an entry's page declares about it beyond its name and type.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Enrichment {
+    /// The declaration, `pub fn push(&mut self, value: T)`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// The deprecation notice, when the entry has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+    /// The version the entry appeared in, `1.0.0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+}
+
+/// Classes of the elements documentation generators put declarations in:
+/// rustdoc, Sphinx, Javadoc, Doxygen and MDN among them.
+const SIGNATURE_CLASSES: &[&str] = &[
+    "code-header",
+    "item-decl",
+    "sig",
+    "signature",
+    "member-signature",
+    "memproto",
+    "declaration",
+    "syntaxbox",
+];
+
+fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("zeal-cli")
+        .join("enrich")
+}
+
+/// Cache file of a docset, keyed on its index's modification time so an
+/// updated docset's pages are read again.
+fn cache_path(docset_path: &Path) -> Option<PathBuf> {
+    let key = cache::docset_key(docset_path, &[])?;
+    Some(cache_dir().join(format!("{}.json", key)))
+}
+
+type Cached = BTreeMap<String, Enrichment>;
+
+fn load(path: &Path) -> Cached {
+    fs::read(path)
+        .ok()
+        .and_then(|body| serde_json::from_slice(&body).ok())
+        .unwrap_or_default()
+}
+
+/// Fills in what the results' pages declare about them. What was read from
+/// a docset's pages is kept, so each entry is only parsed once.
+pub fn annotate(results: &mut [SearchResult]) {
+    let mut by_docset: HashMap<Option<PathBuf>, Vec<&mut SearchResult>> = HashMap::new();
+    for m in results {
+        by_docset
+            .entry(related::docset_of(&m.path))
+            .or_default()
+            .push(m);
+    }
+    for (docset, results) in by_docset {
+        let cache = docset.as_deref().and_then(cache_path);
+        let mut cached = cache.as_deref().map(load).unwrap_or_default();
+        let mut changed = false;
+        for m in results {
+            let key = m.path.to_string_lossy().into_owned();
+            let found = cached
+                .entry(key)
+                .or_insert_with(|| {
+                    changed = true;
+                    read(&m.path, &m.name)
+                })
+                .clone();
+            m.enrichment = (found != Enrichment::default()).then_some(found);
+        }
+        if let Some(cache) = cache.filter(|_| changed) {
+            cache::save(&cache, &cached);
+        }
+    }
+}
+
+/// Reads the part of the page the entry at `path` points at.
+fn read(path: &Path, name: &str) -> Enrichment {
+    let Ok(region) = preview::region(path) else {
+        return Enrichment::default();
+    };
+    let fragment = Html::parse_fragment(&region);
+    let mut elements: Vec<ElementRef> = fragment
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .collect();
+    // A whole page is described by its top, before its members' sections.
+    if !path.to_string_lossy().contains('#') {
+        let members = elements
+            .iter()
+            .position(|el| matches!(el.value().name(), "h2" | "h3" | "h4" | "h5" | "h6"));
+        elements.truncate(members.unwrap_or(elements.len()));
+    }
+    Enrichment {
+        signature: signature(&elements, name),
+        deprecated: deprecated(&elements),
+        since: since(&elements),
+    }
+}
+
+/// Text of an element on one line, without the permalink marks pages put
+/// after headings.
+fn text(el: ElementRef) -> String {
+    let text = el.text().collect::<Vec<_>>().join("");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    text.trim_end_matches(['¶', '§', '#']).trim().to_string()
+}
+
+fn has_class(el: ElementRef, wanted: &[&str]) -> bool {
+    el.value().classes().any(|c| wanted.contains(&c))
+}
+
+/// The first declaration element, else the first code block naming the
+/// entry's member.
+fn signature(elements: &[ElementRef], name: &str) -> Option<String> {
+    if let Some(el) = elements
+        .iter()
+        .find(|el| has_class(**el, SIGNATURE_CLASSES))
+    {
+        return Some(text(*el)).filter(|t| !t.is_empty());
+    }
+    let member = name.rsplit(['.', ':', '#']).next().unwrap_or(name);
+    elements
+        .iter()
+        .filter(|el| matches!(el.value().name(), "pre" | "code"))
+        .map(|el| text(*el))
+        .find(|t| !member.is_empty() && t.contains(member) && t.len() <= 300)
+}
+
+fn deprecated(elements: &[ElementRef]) -> Option<String> {
+    elements
+        .iter()
+        .find(|el| {
+            el.value()
+                .classes()
+                .any(|c| c.to_ascii_lowercase().contains("deprecated"))
+        })
+        .map(|el| text(*el))
+        .filter(|t| !t.is_empty())
+}
+
+/// rustdoc's `since` badge, Sphinx's `versionadded` note, or the version
+/// after a `Since:` label as Javadoc writes it.
+fn since(elements: &[ElementRef]) -> Option<String> {
+    let number = Regex::new(r"\d+(\.\d+)+").unwrap();
+    let version = |text: &str| number.find(text).map(|v| v.as_str().to_string());
+    let badge = elements.iter().find(|el| {
+        el.value()
+            .classes()
+            .any(|c| c == "since" || c == "versionadded" || c == "added")
+    });
+    if let Some(found) = badge.and_then(|el| {
+        let title = el.value().attr("title").unwrap_or_default();
+        version(&text(*el)).or_else(|| version(title))
+    }) {
+        return Some(found);
+    }
+    let label = elements
+        .iter()
+        .find(|el| text(**el).eq_ignore_ascii_case("Since:"))?;
+    let value = label.next_siblings().find_map(ElementRef::wrap)?;
+    version(&text(value))
+}
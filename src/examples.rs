@@ -0,0 +1,128 @@
+use ansi_term::{Colour, Style};
+use scraper::{ElementRef, Html, Node};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{archive, render, theme, toc};
+
+/// A code block of a page.
+pub struct Example {
+    /// As the page declares it, see [`render::code_language`].
+    pub language: Option<String>,
+    pub code: String,
+    /// `code` coloured by the highlighting markup the page ships with, the
+    /// classes rustdoc, highlight.js, Prism and Pygments put on their spans.
+    pub highlighted: String,
+}
+
+/// The code blocks of the section the entry at `path` points into: those
+/// after its anchor up to the next heading of the same or a higher level.
+/// When that section has none, every block after the anchor is taken, and
+/// without an anchor, or with `all`, every block of the page.
+pub fn near(path: &Path, all: bool) -> io::Result<Vec<Example>> {
+    archive::materialize(path)?;
+    let html = fs::read_to_string(toc::strip_fragment(path))?;
+    let doc = Html::parse_document(&html);
+    let fragment = path
+        .to_string_lossy()
+        .split_once('#')
+        .map(|(_, f)| f.to_string())
+        .filter(|_| !all);
+    let elements: Vec<ElementRef> = doc
+        .root_element()
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .collect();
+    let start = fragment
+        .and_then(|fragment| {
+            elements.iter().position(|el| {
+                let el = el.value();
+                el.id() == Some(fragment.as_str()) || el.attr("name") == Some(fragment.as_str())
+            })
+        })
+        .unwrap_or(0);
+    let blocks = |within_section: bool| {
+        let mut level = None;
+        let mut blocks = Vec::new();
+        for (i, el) in elements.iter().enumerate().skip(start) {
+            if let Some(l) = heading_level(el) {
+                match level {
+                    // The anchor's own heading, or the first one after it.
+                    None => level = Some(l),
+                    Some(at) if within_section && l <= at && i > start => break,
+                    Some(_) => {}
+                }
+            }
+            let nested = el
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|a| a.value().name() == "pre");
+            if el.value().name() == "pre" && !nested {
+                blocks.push(example(*el));
+            }
+        }
+        blocks
+    };
+    let near = if start > 0 { blocks(true) } else { Vec::new() };
+    Ok(if near.is_empty() { blocks(false) } else { near })
+}
+
+fn heading_level(el: &ElementRef) -> Option<usize> {
+    match el.value().name().as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some((level - b'0') as usize),
+        _ => None,
+    }
+}
+
+fn example(pre: ElementRef) -> Example {
+    let mut highlighted = String::new();
+    for node in pre.descendants() {
+        if let Node::Text(text) = node.value() {
+            // The innermost highlighting class around the text counts.
+            let style = node
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .take_while(|el| el.value().name() != "pre")
+                .find_map(|el| el.value().classes().find_map(class_style));
+            match style {
+                Some(style) => highlighted.push_str(&style.paint(&text[..]).to_string()),
+                None => highlighted.push_str(text),
+            }
+        }
+    }
+    Example {
+        language: render::code_language(pre),
+        code: pre.text().collect::<String>().trim_end().to_string(),
+        highlighted: highlighted.trim_end().to_string(),
+    }
+}
+
+fn class_style(class: &str) -> Option<Style> {
+    let class = class.strip_prefix("hljs-").unwrap_or(class);
+    let (element, style) = token_style(class)?;
+    Some(theme::ui_style(element, style))
+}
+
+/// The theme element and default style of a highlighting class.
+fn token_style(class: &str) -> Option<(&'static str, Style)> {
+    let (element, colour) = match class {
+        "kw" | "kw-2" | "keyword" | "self" | "bool-val" | "k" | "kd" | "kn" | "kr" | "kc" => {
+            ("keyword", Colour::Purple)
+        }
+        "string" | "char" | "s" | "s1" | "s2" | "sb" | "sc" | "sd" | "se" => {
+            ("string", Colour::Green)
+        }
+        "comment" | "doccomment" | "c" | "c1" | "cm" | "cp" => {
+            return Some(("comment", Style::new().dimmed()));
+        }
+        "number" | "mi" | "mf" | "mh" | "mo" => ("number", Colour::Yellow),
+        "fn" | "function" | "title" | "nf" | "fm" => ("function", Colour::Blue),
+        "type" | "struct" | "enum" | "trait" | "class-name" | "built_in" | "nc" | "kt" | "nb" => {
+            ("type", Colour::Cyan)
+        }
+        "macro" | "attr" | "attribute" | "meta" | "decorator" | "nd" => ("attribute", Colour::Red),
+        _ => return None,
+    };
+    Some((element, colour.normal()))
+}
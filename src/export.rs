@@ -0,0 +1,89 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::render::{self, Flavor};
+use crate::search::SearchResult;
+use crate::{archive, toc};
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Md,
+    Txt,
+    Html,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Md => "md",
+            ExportFormat::Txt => "txt",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// Distinct local pages behind `results`, in ranking order.
+pub fn pages(results: &[SearchResult]) -> Vec<PathBuf> {
+    let mut pages: Vec<PathBuf> = Vec::new();
+    for m in results {
+        let page = toc::strip_fragment(&m.path);
+        if page.starts_with("http:") || page.starts_with("https:") {
+            continue;
+        }
+        if !pages.contains(&page) {
+            pages.push(page);
+        }
+    }
+    pages
+}
+
+pub fn convert(page: &Path, format: ExportFormat) -> std::io::Result<String> {
+    archive::materialize(page)?;
+    let html = fs::read_to_string(page)?;
+    Ok(match format {
+        ExportFormat::Md => render::html_to(&html, Flavor::Markdown),
+        ExportFormat::Txt => render::html_to(&html, Flavor::Text),
+        ExportFormat::Html => html,
+    })
+}
+
+/// Writes each page to `out_dir` (mirroring its path under the docset's
+/// Documents dir) or, without one, concatenates them on stdout.
+pub fn export(
+    docset_path: &Path,
+    pages: &[PathBuf],
+    format: ExportFormat,
+    out_dir: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let docs_dir = docset_path.join("Contents/Resources/Documents");
+    let separator = match format {
+        ExportFormat::Md => "\n\n---\n\n",
+        ExportFormat::Txt | ExportFormat::Html => "\n\n",
+    };
+
+    for (i, page) in pages.iter().enumerate() {
+        let content = convert(page, format)?;
+        match out_dir {
+            Some(dir) => {
+                let relative = page.strip_prefix(&docs_dir).unwrap_or(page);
+                let target = dir.join(relative).with_extension(format.extension());
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, content)?;
+                println!("{}", target.display());
+            }
+            None => {
+                if i > 0 {
+                    print!("{}", separator);
+                }
+                println!("{}", content);
+            }
+        }
+    }
+    Ok(())
+}
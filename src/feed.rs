@@ -0,0 +1,174 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::net::Net;
+
+pub const USER_CONTRIB_FEED_URL: &str = "https://zealusercontributions.vercel.app/api/docsets";
+
+/// How long a downloaded feed is reused before it is fetched again.
+const FEED_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// One docset entry of a zealusercontributions-style JSON feed.
+#[derive(Deserialize, Debug, Clone)]
+pub struct FeedDocset {
+    pub name: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub revision: String,
+    #[serde(default)]
+    pub urls: Vec<String>,
+    /// Hex SHA-256 of the archive, when the feed publishes one.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+fn cache_path(feed_url: &str, extension: &str) -> Option<PathBuf> {
+    let key: String = Sha256::digest(feed_url.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    dirs::cache_dir().map(|d| {
+        d.join("zeal-cli")
+            .join("feeds")
+            .join(format!("{}.{}", key, extension))
+    })
+}
+
+fn is_fresh(path: &PathBuf) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < FEED_CACHE_TTL)
+}
+
+/// Fetches and parses a feed, reusing the cached copy while it is fresh.
+///
+/// A stale cache is still used, with a warning, when the feed cannot be
+/// fetched. Only bodies that parse are cached.
+fn load_cached<T>(
+    net: &Net,
+    feed_url: &str,
+    extension: &str,
+    refresh: bool,
+    parse: impl Fn(&str) -> Result<T, Box<dyn std::error::Error>>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let cache = cache_path(feed_url, extension);
+
+    if let Some(path) = cache.as_ref().filter(|p| !refresh && is_fresh(p)) {
+        if let Ok(body) = fs::read_to_string(path) {
+            return parse(&body);
+        }
+    }
+
+    let fetched = net
+        .agent_for(feed_url)
+        .and_then(|agent| Ok(agent.get(feed_url).call()?));
+    let body = match fetched {
+        Ok(resp) => resp.into_string()?,
+        Err(e) => match cache.as_ref().and_then(|p| fs::read_to_string(p).ok()) {
+            Some(body) => {
+                eprintln!(
+                    "Warning: using cached feed, fetching {} failed: {}",
+                    feed_url, e
+                );
+                return parse(&body);
+            }
+            None => return Err(e),
+        },
+    };
+
+    let parsed = parse(&body)?;
+    if let Some(path) = cache {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, body)?;
+    }
+    Ok(parsed)
+}
+
+/// Loads a user-contributed feed, reusing the cached copy while it is fresh.
+pub fn load_user_contrib(
+    net: &Net,
+    feed_url: &str,
+    refresh: bool,
+) -> Result<Vec<FeedDocset>, Box<dyn std::error::Error>> {
+    load_cached(net, feed_url, "json", refresh, |body| {
+        Ok(serde_json::from_str(body)?)
+    })
+}
+
+/// A Dash-style per-docset feed, as Zeal reads for docsets installed from a
+/// feed URL:
+///
+/// ```xml
+/// <entry>
+///   <version>1.80.0/Rev 2</version>
+///   <url>https://london.kapeli.com/feeds/Rust.tgz</url>
+///   <other-versions><version><name>1.79.0</name></version></other-versions>
+/// </entry>
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct XmlFeed {
+    pub version: String,
+    pub revision: String,
+    pub urls: Vec<String>,
+    /// Versions the feed also offers, newest first as Dash lists them.
+    pub other_versions: Vec<String>,
+}
+
+/// Parses a per-docset feed. `<version>` splits into version and revision
+/// at `/`, as Zeal does, so `1.80.0/Rev 2` has revision `2`.
+pub fn parse_xml(body: &str) -> Result<XmlFeed, Box<dyn std::error::Error>> {
+    let doc = roxmltree::Document::parse(body)?;
+    let entry = doc.root_element();
+    if entry.tag_name().name() != "entry" {
+        return Err(format!(
+            "Expected an <entry> feed, found <{}>",
+            entry.tag_name().name()
+        )
+        .into());
+    }
+    let text = |node: roxmltree::Node| node.text().unwrap_or_default().trim().to_string();
+    let mut feed = XmlFeed::default();
+    for child in entry.children().filter(|n| n.is_element()) {
+        match child.tag_name().name() {
+            "version" => {
+                let raw = text(child);
+                let (version, revision) = raw.split_once('/').unwrap_or((&raw, ""));
+                feed.version = version.trim().to_string();
+                feed.revision = revision.trim().trim_start_matches("Rev").trim().to_string();
+            }
+            "url" => feed.urls.push(text(child)),
+            "other-versions" => {
+                feed.other_versions = child
+                    .descendants()
+                    .filter(|n| n.has_tag_name("name"))
+                    .map(text)
+                    .collect();
+            }
+            _ => {}
+        }
+    }
+    if feed.version.is_empty() && feed.urls.is_empty() {
+        return Err("Feed has neither <version> nor <url>".into());
+    }
+    Ok(feed)
+}
+
+/// Loads a per-docset feed, reusing the cached copy while it is fresh.
+pub fn load_xml(
+    net: &Net,
+    feed_url: &str,
+    refresh: bool,
+) -> Result<XmlFeed, Box<dyn std::error::Error>> {
+    load_cached(net, feed_url, "xml", refresh, parse_xml)
+}
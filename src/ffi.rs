@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::search::{self, SearchOptions};
+use crate::{config, plugin, query, zeal_conf};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+type Error = Box<dyn std::error::Error>;
+
+/// Runs `f`, turning its result into a string for C or NULL and the error.
+fn respond(f: impl FnOnce() -> Result<String, Error>) -> *mut c_char {
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err("zeal-cli panicked".into()))
+        .and_then(|json| Ok(CString::new(json)?));
+    match result {
+        Ok(json) => {
+            LAST_ERROR.with(|e| *e.borrow_mut() = None);
+            json.into_raw()
+        }
+        Err(e) => {
+            let message = CString::new(e.to_string().replace('\0', " ")).unwrap_or_default();
+            LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// The user's config, read by the first search and kept for the life of
+/// the process.
+fn config() -> Result<&'static config::Config, Error> {
+    static CONFIG: OnceLock<config::Config> = OnceLock::new();
+    if let Some(config) = CONFIG.get() {
+        return Ok(config);
+    }
+    let config = config::load(None)?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// A string argument; NULL is `None`.
+///
+/// # Safety
+///
+/// `s` is NULL or a NUL-terminated string valid for the call.
+unsafe fn arg<'a>(s: *const c_char) -> Result<Option<&'a str>, Error> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(CStr::from_ptr(s).to_str()?))
+}
+
+/// The docsets directory given, else Zeal's.
+fn docsets_dir(dir: Option<&str>) -> Result<PathBuf, Error> {
+    dir.map(PathBuf::from)
+        .or_else(zeal_conf::docsets_path)
+        .or_else(zeal_conf::default_docsets_dir)
+        .ok_or_else(|| "no docsets directory given and Zeal's is unknown".into())
+}
+
+fn installed(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut docsets: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("{}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| search::is_docset_dir(path))
+        .collect();
+    docsets.sort();
+    Ok(docsets)
+}
+
+/// The names of the docsets in `docsets_dir`, or Zeal's directory when it
+/// is NULL, as a JSON array.
+///
+/// # Safety
+///
+/// `docsets_dir` is NULL or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn zeal_list_docsets(docsets_dir: *const c_char) -> *mut c_char {
+    respond(|| {
+        let dir = self::docsets_dir(arg(docsets_dir)?)?;
+        let names: Vec<String> = installed(&dir)?
+            .iter()
+            .map(|p| search::docset_name(p))
+            .collect();
+        Ok(serde_json::to_string(&names)?)
+    })
+}
+
+/// Searches the docset named `docset` in `docsets_dir`, or Zeal's directory
+/// when it is NULL, returning at most `limit` results, or all when it is
+/// 0, best first as a JSON array of the objects `--output jsonl` prints.
+/// `query` takes the operators `search` does, such as `t:Method`.
+///
+/// # Safety
+///
+/// `docsets_dir` is NULL or a NUL-terminated string; `docset` and `query`
+/// are NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn zeal_search(
+    docsets_dir: *const c_char,
+    docset: *const c_char,
+    query: *const c_char,
+    limit: usize,
+) -> *mut c_char {
+    respond(|| {
+        let dir = self::docsets_dir(arg(docsets_dir)?)?;
+        let docset = arg(docset)?.ok_or("no docset given")?;
+        let query = query::parse(arg(query)?.unwrap_or_default())?;
+        let docsets = installed(&dir)?;
+        let docset_path = search::find_named(&docsets, docset)?
+            .ok_or_else(|| format!("Docset '{}' not found", docset))?;
+        let plugins = &config()?.plugins;
+        let limit = (limit > 0).then_some(limit);
+        let opts = SearchOptions {
+            types: query.types,
+            limit: plugin::scoring_limit(plugins, limit),
+            filters: query.filters,
+            ..Default::default()
+        };
+        let entries = search::load_entries(docset_path)?;
+        let mut results = search::match_entries(&entries, &query.text, &opts);
+        let name = search::docset_name(docset_path);
+        for m in &mut results {
+            m.docset.clone_from(&name);
+        }
+        let results = plugin::transform(plugins, &query.text, results, limit)?;
+        Ok(serde_json::to_string(&results)?)
+    })
+}
+
+/// What made the last call on this thread return NULL, or NULL if it
+/// succeeded. Owned by the library: valid until the next call, not freed.
+#[no_mangle]
+pub extern "C" fn zeal_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(std::ptr::null(), |e| e.as_ptr()))
+}
+
+/// Frees a string returned by this library; NULL is ignored.
+///
+/// # Safety
+///
+/// `s` is NULL or a string returned by this library, not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn zeal_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
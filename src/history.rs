@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::listing;
+
+/// The `[history]` config table: a shell-style file of past search queries.
+///
+/// ```toml
+/// [history]
+/// enabled = true
+/// # Like HISTIGNORE: globs matched against the whole query
+/// ignore = ["ls*", "?"]
+/// # Like HISTCONTROL=ignorespace:ignoredups
+/// ignore_space = true
+/// ignore_dups = true
+/// ```
+///
+/// The file has bash's timestamped format, a `#<unix time>` line before each
+/// query, so history tools can import it as they are.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct History {
+    pub enabled: bool,
+    /// Where the file lives; `history` in the zeal-cli data directory by default.
+    pub file: Option<PathBuf>,
+    pub ignore: Vec<String>,
+    /// Leave out queries that start with a space.
+    pub ignore_space: bool,
+    /// Leave out a query repeating the one before it.
+    pub ignore_dups: bool,
+}
+
+/// One query of the history file.
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+pub struct Line {
+    /// Unix time it was searched, when the file says.
+    pub time: Option<u64>,
+    pub query: String,
+}
+
+impl History {
+    pub fn path(&self) -> Option<PathBuf> {
+        self.file
+            .clone()
+            .or_else(|| dirs::data_dir().map(|d| d.join("zeal-cli").join("history")))
+    }
+
+    fn ignores(&self, query: &str) -> bool {
+        query.trim().is_empty()
+            || (self.ignore_space && query.starts_with(' '))
+            || self.ignore.iter().any(|p| listing::glob_match(p, query))
+    }
+
+    /// Appends a query when history is enabled and its rules keep it.
+    /// Failures are only logged; history is never worth failing a search over.
+    pub fn record(&self, query: &str) {
+        if !self.enabled || self.ignores(query) {
+            return;
+        }
+        let Some(path) = self.path() else {
+            return;
+        };
+        // One query a line, whatever it was typed with.
+        let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+        if self.ignore_dups {
+            let last = read(&path).ok().and_then(|mut lines| lines.pop());
+            if last.is_some_and(|l| l.query == query) {
+                return;
+            }
+        }
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let append = || -> io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+            // One write, so concurrent searches don't interleave lines.
+            file.write_all(format!("#{}\n{}\n", time, query).as_bytes())
+        };
+        if let Err(e) = append() {
+            tracing::warn!("Could not write history to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Replaces a history file with `lines`, in the format [`read`] reads.
+pub fn write(path: &std::path::Path, lines: &[Line]) -> io::Result<()> {
+    let dir = path.parent().unwrap_or(std::path::Path::new("."));
+    fs::create_dir_all(dir)?;
+    // Written aside and renamed so a failed write keeps the old history.
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    for line in lines {
+        if let Some(time) = line.time {
+            writeln!(tmp, "#{}", time)?;
+        }
+        writeln!(tmp, "{}", line.query)?;
+    }
+    tmp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// The queries of a history file, oldest first. Lines without a timestamp,
+/// as in a plain bash history, are read too.
+pub fn read(path: &std::path::Path) -> io::Result<Vec<Line>> {
+    let body = match fs::read_to_string(path) {
+        Ok(body) => body,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut lines = Vec::new();
+    let mut time = None;
+    for line in body.lines() {
+        if let Some(stamp) = line.strip_prefix('#').and_then(|s| s.parse().ok()) {
+            time = Some(stamp);
+        } else if !line.is_empty() {
+            lines.push(Line {
+                time: time.take(),
+                query: line.to_string(),
+            });
+        }
+    }
+    Ok(lines)
+}
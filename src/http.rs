@@ -0,0 +1,608 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::open;
+use crate::serve::{Connection, Server};
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY: usize = 1 << 20;
+/// Largest request line or header accepted, in bytes.
+const MAX_HEADER_LINE: u64 = 8 << 10;
+/// Most headers accepted in a request.
+const MAX_HEADERS: usize = 100;
+/// How long a client may take to send a request, or stay idle between them.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// The variable holding the bearer token, when `--token-file` isn't given.
+const TOKEN_ENV: &str = "ZEAL_CLI_TOKEN";
+/// Smallest response body gzipped for clients that accept it, in bytes.
+const GZIP_MIN: usize = 1024;
+/// Where docset pages are served, as `/docs/<Name>.docset/<page>`.
+const PAGES: &str = "/docs/";
+
+/// Settings of `serve --http`.
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    pub bind: SocketAddr,
+    /// Bearer token every request must carry, when set.
+    pub token: Option<String>,
+    /// PEM certificate chain and private key to serve HTTPS with.
+    pub tls: Option<(PathBuf, PathBuf)>,
+    /// Gzip responses for clients that send `Accept-Encoding: gzip`.
+    pub gzip: bool,
+}
+
+/// The bearer token clients must send: the first line of `file`, else
+/// `$ZEAL_CLI_TOKEN`; never the command line, which other users can read.
+pub fn token(file: Option<&Path>) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let token = match file {
+        Some(file) => fs::read_to_string(file)
+            .map_err(|e| format!("Cannot read token file {}: {}", file.display(), e))?
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+        None => std::env::var(TOKEN_ENV).unwrap_or_default(),
+    };
+    if token.is_empty() && file.is_some() {
+        return Err("The token file is empty".into());
+    }
+    Ok(Some(token).filter(|t| !t.is_empty()))
+}
+
+/// Serves the JSON request API over HTTP, one thread per client.
+///
+/// Each request is a `POST /` whose body is a request as sent to
+/// [`Server::handle`], answered with its response as the body. Chunked
+/// search results come as JSON lines, each line sent as one transfer chunk
+/// as soon as it is ready.
+/// Completion sessions live as long as the client keeps its connection open.
+/// `status`, `invalidate` and `shutdown` are left to the `--socket` server.
+/// Docset pages can be read with `GET /docs/<Name>.docset/<page>`, the URLs
+/// `--path-style localhost` prints.
+pub fn run(server: Arc<Server>, opts: &HttpOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let tls = match &opts.tls {
+        Some((cert, key)) => Some(tls_config(cert, key)?),
+        None => None,
+    };
+    if opts.token.is_none() && !opts.bind.ip().is_loopback() {
+        return Err(format!(
+            "Refusing to serve {} to other hosts without a token; set {} or pass --token-file",
+            opts.bind, TOKEN_ENV
+        )
+        .into());
+    }
+    let listener = TcpListener::bind(opts.bind)?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let mut local = listener.local_addr()?;
+    if local.ip().is_unspecified() {
+        local.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+    let base = format!("{}://{}", scheme, local);
+    eprintln!("Listening on {}", base);
+    if let Err(e) = record_base(&base) {
+        tracing::warn!("Cannot record the server's address: {}", e);
+    }
+    for stream in listener.incoming() {
+        let stream = stream?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let (server, tls, token) = (server.clone(), tls.clone(), opts.token.clone());
+        let gzip = opts.gzip;
+        std::thread::spawn(move || {
+            let result = match tls {
+                Some(config) => rustls::ServerConnection::new(config)
+                    .map_err(std::io::Error::other)
+                    .and_then(|conn| {
+                        let stream = rustls::StreamOwned::new(conn, stream);
+                        serve_client(&server, stream, token.as_deref(), gzip)
+                    }),
+                None => serve_client(&server, stream, token.as_deref(), gzip),
+            };
+            match result {
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    tracing::debug!("Client timed out")
+                }
+                Err(e) => tracing::warn!("Client error: {}", e),
+                Ok(()) => {}
+            }
+        });
+    }
+    Ok(())
+}
+
+/// The file holding the base URL of the `serve --http` started last.
+fn base_file() -> Option<PathBuf> {
+    let dir = dirs::runtime_dir().or_else(dirs::cache_dir)?;
+    Some(dir.join("zeal-cli").join("http-base"))
+}
+
+fn record_base(base: &str) -> std::io::Result<()> {
+    let file = base_file().ok_or_else(|| std::io::Error::other("No runtime directory"))?;
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(file, base)
+}
+
+/// The base URL of the `serve --http` started last, while it still takes
+/// connections; looked for once per process.
+pub fn running_base() -> Option<&'static str> {
+    static BASE: OnceLock<Option<String>> = OnceLock::new();
+    BASE.get_or_init(|| {
+        let base = fs::read_to_string(base_file()?).ok()?.trim().to_string();
+        let addr: SocketAddr = base.split_once("://")?.1.parse().ok()?;
+        TcpStream::connect_timeout(&addr, Duration::from_millis(200)).ok()?;
+        Some(base)
+    })
+    .as_deref()
+}
+
+/// The URL the server at `base` serves an entry's page at, fragment kept;
+/// `None` for online entries and paths outside a docset.
+pub fn page_url(base: &str, path: &Path) -> Option<String> {
+    let url = open::entry_url(path);
+    let (docset, page) = url
+        .strip_prefix("file://")?
+        .split_once("/Contents/Resources/Documents/")?;
+    let docset = docset.rsplit('/').next()?;
+    Some(format!("{}{}{}/{}", base, PAGES, docset, page))
+}
+
+fn tls_config(
+    cert: &Path,
+    key: &Path,
+) -> Result<Arc<rustls::ServerConfig>, Box<dyn std::error::Error>> {
+    let chain = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Cannot read certificate {}: {}", cert.display(), e))?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .map_err(|e| format!("Cannot read private key {}: {}", key.display(), e))?;
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(chain, key)?;
+    Ok(Arc::new(config))
+}
+
+/// A parsed request: method, path, lowercased headers and body.
+struct Request {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Reads a line of the request head into `line`, refusing one longer than
+/// `MAX_HEADER_LINE`; 0 once the client hung up.
+fn read_head_line(reader: &mut impl BufRead, line: &mut String) -> std::io::Result<usize> {
+    line.clear();
+    let read = reader.take(MAX_HEADER_LINE).read_line(line)?;
+    if read as u64 == MAX_HEADER_LINE && !line.ends_with('\n') {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "Request header too large",
+        ));
+    }
+    Ok(read)
+}
+
+/// Reads the next request, `None` once the client hung up.
+fn read_request(reader: &mut impl BufRead) -> std::io::Result<Option<Request>> {
+    let mut line = String::new();
+    if read_head_line(reader, &mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "Malformed request line",
+        ));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+    let mut headers = HashMap::new();
+    for count in 0.. {
+        if read_head_line(reader, &mut line)? == 0 {
+            return Ok(None);
+        }
+        let Some((name, value)) = line.trim_end().split_once(':') else {
+            break;
+        };
+        if count == MAX_HEADERS {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                "Too many request headers",
+            ));
+        }
+        headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "Request body too large",
+        ));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(Request {
+        method,
+        path,
+        headers,
+        body,
+    }))
+}
+
+fn write_response(
+    writer: &mut impl Write,
+    status: &str,
+    extra_headers: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n{}",
+        status,
+        body.len(),
+        extra_headers,
+        body
+    )?;
+    writer.flush()
+}
+
+/// Answers with what [`Server::handle`] makes of the request's body. The
+/// lines of a chunked search are sent as the server produces them, so the
+/// client can read the first results while the rest are on their way.
+fn write_reply(
+    writer: &mut impl Write,
+    request: &Request,
+    server: &Server,
+    conn: &Connection,
+    gzip: bool,
+) -> std::io::Result<()> {
+    let gzip = gzip && accepts_gzip(request);
+    let body = String::from_utf8_lossy(&request.body);
+    let mut lines = Lines::Pending(writer);
+    let last = server.handle(&body, conn, &mut |line| lines.send(&line, gzip));
+    lines.finish(&last, gzip)
+}
+
+/// Where the lines of a response go: held back while it may be a single
+/// line, then the transfer chunks of a JSON-lines body.
+enum Lines<'a, W: Write> {
+    Pending(&'a mut W),
+    Plain(Chunked<&'a mut W>),
+    Gzip(Box<GzEncoder<Chunked<&'a mut W>>>),
+    /// Writing the head of the body failed.
+    Broken,
+}
+
+impl<W: Write> Lines<'_, W> {
+    /// Sends one line of a JSON-lines body, starting the body with the first.
+    fn send(&mut self, line: &str, gzip: bool) -> std::io::Result<()> {
+        *self = match std::mem::replace(self, Lines::Broken) {
+            Lines::Pending(writer) => {
+                let encoding = if gzip {
+                    "Content-Encoding: gzip\r\nVary: Accept-Encoding\r\n"
+                } else {
+                    ""
+                };
+                write!(
+                    writer,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\n{}\r\n",
+                    encoding
+                )?;
+                if gzip {
+                    Lines::Gzip(Box::new(GzEncoder::new(
+                        Chunked(writer),
+                        Compression::default(),
+                    )))
+                } else {
+                    Lines::Plain(Chunked(writer))
+                }
+            }
+            lines => lines,
+        };
+        let writer: &mut dyn Write = match self {
+            Lines::Plain(chunked) => chunked,
+            Lines::Gzip(encoder) => encoder.as_mut(),
+            Lines::Pending(_) | Lines::Broken => {
+                return Err(std::io::Error::other("The response was cut off"))
+            }
+        };
+        writer.write_all(format!("{}\n", line).as_bytes())?;
+        // For gzip a sync flush, so what was sent so far decompresses.
+        writer.flush()
+    }
+
+    /// Sends the last line: the whole body when it is the only one, gzipped
+    /// when it is big enough to gain from it, else the end of the JSON lines.
+    fn finish(mut self, line: &str, gzip: bool) -> std::io::Result<()> {
+        if let Lines::Pending(writer) = self {
+            if !gzip || line.len() < GZIP_MIN {
+                return write_response(writer, "200 OK", "", line);
+            }
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(line.as_bytes())?;
+            let compressed = encoder.finish()?;
+            write!(
+                writer,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nContent-Encoding: gzip\r\nVary: Accept-Encoding\r\n\r\n",
+                compressed.len()
+            )?;
+            writer.write_all(&compressed)?;
+            return writer.flush();
+        }
+        self.send(line, gzip)?;
+        let chunked = match self {
+            Lines::Plain(chunked) => chunked,
+            Lines::Gzip(encoder) => encoder.finish()?,
+            Lines::Pending(_) | Lines::Broken => unreachable!("send started the body"),
+        };
+        chunked.0.write_all(b"0\r\n\r\n")?;
+        chunked.0.flush()
+    }
+}
+
+/// Answers with the docset page `route` names, `<Name>.docset/<page>`
+/// percent-encoded, or 404 when there is no such page in a docsets
+/// directory.
+fn write_page(writer: &mut impl Write, server: &Server, route: &str) -> std::io::Result<()> {
+    let route = route.split(['?', '#']).next().unwrap_or_default();
+    let page = urlencoding::decode(route).ok().and_then(|route| {
+        let (docset, page) = route.split_once('/')?;
+        let docset = docset.strip_suffix(".docset").unwrap_or(docset);
+        server.page_file(docset, page).ok()
+    });
+    let Some((body, page)) = page.and_then(|page| Some((fs::read(&page).ok()?, page))) else {
+        return write_response(writer, "404 Not Found", "", r#"{"error":"Not found"}"#);
+    };
+    write!(
+        writer,
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+        content_type(&page),
+        body.len()
+    )?;
+    writer.write_all(&body)?;
+    writer.flush()
+}
+
+fn content_type(page: &Path) -> &'static str {
+    let extension = page
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css",
+        "js" => "text/javascript",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Whether the client lists gzip in `Accept-Encoding`, and not as `q=0`.
+fn accepts_gzip(request: &Request) -> bool {
+    let Some(accepted) = request.headers.get("accept-encoding") else {
+        return false;
+    };
+    accepted.split(',').any(|coding| {
+        let mut parts = coding.split(';').map(str::trim);
+        parts.next().is_some_and(|c| c.eq_ignore_ascii_case("gzip"))
+            && !parts.any(|p| p.replace(' ', "") == "q=0")
+    })
+}
+
+/// Sends everything written to it as chunks of a chunked transfer encoding.
+struct Chunked<W: Write>(W);
+
+impl<W: Write> Write for Chunked<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // An empty chunk would end the body.
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        write!(self.0, "{:x}\r\n", buf.len())?;
+        self.0.write_all(buf)?;
+        self.0.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Whether `request` carries `Authorization: Bearer <token>`, compared in
+/// constant time so the token can't be guessed byte by byte.
+fn authorized(request: &Request, token: &str) -> bool {
+    let Some(given) = request
+        .headers
+        .get("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn serve_client(
+    server: &Server,
+    stream: impl Read + Write,
+    token: Option<&str>,
+    gzip: bool,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let conn = Connection::default();
+    while let Some(request) = read_request(&mut reader)? {
+        let writer = reader.get_mut();
+        if token.is_some_and(|token| !authorized(&request, token)) {
+            let body = r#"{"error":"Unauthorized"}"#;
+            write_response(
+                writer,
+                "401 Unauthorized",
+                "WWW-Authenticate: Bearer\r\n",
+                body,
+            )?;
+        } else if let Some(route) = request.path.strip_prefix(PAGES) {
+            if request.method == "GET" {
+                write_page(writer, server, route)?;
+            } else {
+                let body = r#"{"error":"Method not allowed"}"#;
+                write_response(writer, "405 Method Not Allowed", "Allow: GET\r\n", body)?;
+            }
+        } else if request.path != "/" {
+            write_response(writer, "404 Not Found", "", r#"{"error":"Not found"}"#)?;
+        } else if request.method != "POST" {
+            let body = r#"{"error":"Method not allowed"}"#;
+            write_response(writer, "405 Method Not Allowed", "Allow: POST\r\n", body)?;
+        } else {
+            let _busy = server.busy();
+            write_reply(writer, &request, server, &conn, gzip)?;
+        }
+        let close = request
+            .headers
+            .get("connection")
+            .is_some_and(|c| c.eq_ignore_ascii_case("close"));
+        if close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &[u8]) -> std::io::Result<Option<Request>> {
+        read_request(&mut BufReader::new(raw))
+    }
+
+    fn with_header(name: &str, value: &str) -> Request {
+        Request {
+            method: "POST".into(),
+            path: "/".into(),
+            headers: HashMap::from([(name.into(), value.into())]),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reads_a_request_with_its_body() {
+        let request =
+            parse(b"POST / HTTP/1.1\r\nContent-Length: 4\r\nX-Foo:  bar \r\n\r\nbodyrest")
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/")
+        );
+        assert_eq!(request.headers["x-foo"], "bar");
+        assert_eq!(request.body, b"body");
+        assert!(parse(b"").unwrap().is_none());
+    }
+
+    #[test]
+    fn refuses_oversized_requests() {
+        let long = format!(
+            "GET /{} HTTP/1.1\r\n\r\n",
+            "a".repeat(MAX_HEADER_LINE as usize)
+        );
+        assert!(parse(long.as_bytes()).is_err());
+
+        let mut many = String::from("GET / HTTP/1.1\r\n");
+        for i in 0..MAX_HEADERS {
+            many += &format!("X-{}: 1\r\n", i);
+        }
+        assert!(parse(format!("{}\r\n", many).as_bytes()).is_ok());
+        assert!(parse(format!("{}X-Last: 1\r\n\r\n", many).as_bytes()).is_err());
+
+        let body = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY + 1
+        );
+        assert!(parse(body.as_bytes()).is_err());
+        assert!(parse(b"nonsense\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn checks_the_bearer_token() {
+        assert!(authorized(
+            &with_header("authorization", "Bearer secret"),
+            "secret"
+        ));
+        assert!(!authorized(
+            &with_header("authorization", "Bearer secreT"),
+            "secret"
+        ));
+        assert!(!authorized(
+            &with_header("authorization", "Bearer secret2"),
+            "secret"
+        ));
+        assert!(!authorized(
+            &with_header("authorization", "Basic secret"),
+            "secret"
+        ));
+        assert!(!authorized(&with_header("accept", "*/*"), "secret"));
+    }
+
+    #[test]
+    fn accepts_gzip_unless_refused() {
+        let accepts = |value| accepts_gzip(&with_header("accept-encoding", value));
+        assert!(accepts("br, GZIP;q=0.5"));
+        assert!(!accepts("gzip; q=0"));
+        assert!(!accepts("deflate"));
+        assert!(!accepts_gzip(&with_header("accept", "*/*")));
+    }
+
+    #[test]
+    fn reads_the_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("token");
+        fs::write(&file, "  secret \nignored\n").unwrap();
+        assert_eq!(token(Some(&file)).unwrap().as_deref(), Some("secret"));
+        fs::write(&file, "\n").unwrap();
+        assert!(token(Some(&file)).is_err());
+        assert!(token(Some(&dir.path().join("missing"))).is_err());
+    }
+
+    #[test]
+    fn refuses_other_hosts_without_a_token() {
+        let server = Arc::new(Server::new(Vec::new(), Default::default()));
+        let opts = HttpOptions {
+            bind: "0.0.0.0:0".parse().unwrap(),
+            token: None,
+            tls: None,
+            gzip: false,
+        };
+        let error = run(server, &opts).unwrap_err().to_string();
+        assert!(error.starts_with("Refusing to serve"), "{}", error);
+    }
+}
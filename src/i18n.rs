@@ -0,0 +1,123 @@
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use icu_collator::options::CollatorOptions;
+use icu_collator::{Collator, CollatorBorrowed};
+use std::cmp::Ordering;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+/// Environment variable choosing the language, below `--lang`.
+const LANG_ENV: &str = "ZEAL_CLI_LANG";
+
+/// Message catalogs compiled into the binary. English is the fallback for
+/// languages and messages without a translation.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("locales/en.ftl")),
+    ("ja", include_str!("locales/ja.ftl")),
+];
+
+struct Locale {
+    messages: FluentBundle<FluentResource>,
+    fallback: FluentBundle<FluentResource>,
+    /// Absent when no collation data matches; names then sort by code point.
+    collator: Option<CollatorBorrowed<'static>>,
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// The language to use: `--lang`, `$ZEAL_CLI_LANG`, then the POSIX locale
+/// variables, as a BCP 47 tag (`ja_JP.UTF-8` becomes `ja-JP`).
+fn requested(lang: Option<&str>) -> String {
+    let from_env = || {
+        [LANG_ENV, "LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+    };
+    let raw = lang
+        .map(str::to_string)
+        .or_else(from_env)
+        .unwrap_or_default();
+    let tag = raw
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .replace('_', "-");
+    match tag.as_str() {
+        "" | "C" | "POSIX" => "en".to_string(),
+        _ => tag,
+    }
+}
+
+fn bundle(lang: &LanguageIdentifier, source: &str) -> FluentBundle<FluentResource> {
+    let mut bundle = FluentBundle::new_concurrent(vec![lang.clone()]);
+    // Isolation marks around arguments show up as junk in terminals.
+    bundle.set_use_isolating(false);
+    let resource =
+        FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, _)| resource);
+    let _ = bundle.add_resource(resource);
+    bundle
+}
+
+/// Chooses the language for messages and name collation. Later calls have
+/// no effect.
+pub fn init(lang: Option<&str>) {
+    LOCALE.get_or_init(|| {
+        let tag = requested(lang);
+        let id: LanguageIdentifier = tag.parse().unwrap_or_default();
+        let catalog = |language: &str| {
+            CATALOGS
+                .iter()
+                .find(|(l, _)| *l == language)
+                .map(|&(_, source)| source)
+        };
+        let english = catalog("en").unwrap_or_default();
+        let source = catalog(id.language.as_str()).unwrap_or(english);
+        let collator = icu_locale_core::Locale::try_from_str(&tag)
+            .ok()
+            .and_then(|locale| {
+                Collator::try_new((&locale).into(), CollatorOptions::default()).ok()
+            });
+        Locale {
+            messages: bundle(&id, source),
+            fallback: bundle(&"en".parse().unwrap_or_default(), english),
+            collator,
+        }
+    });
+}
+
+fn locale() -> &'static Locale {
+    init(None);
+    LOCALE.get().expect("initialized above")
+}
+
+/// The message `id` in the chosen language, with `args` filled in.
+pub fn message(id: &str, args: &[(&str, FluentValue)]) -> String {
+    let locale = locale();
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.clone());
+    }
+    for bundle in [&locale.messages, &locale.fallback] {
+        if let Some(pattern) = bundle.get_message(id).and_then(|m| m.value()) {
+            let mut errors = Vec::new();
+            return bundle
+                .format_pattern(pattern, Some(&fluent_args), &mut errors)
+                .into_owned();
+        }
+    }
+    id.to_string()
+}
+
+/// Languages with a message catalog.
+pub fn languages() -> Vec<&'static str> {
+    CATALOGS.iter().map(|&(lang, _)| lang).collect()
+}
+
+/// Compares names the way the chosen language sorts them.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match &locale().collator {
+        Some(collator) => collator.compare(a, b),
+        None => a.cmp(b),
+    }
+}
@@ -0,0 +1,467 @@
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+use crate::download::{self, DownloadOptions};
+use crate::feed::{self, FeedDocset};
+use crate::net::Net;
+use crate::progress;
+use crate::search;
+use crate::verify;
+use crate::versions;
+
+/// Zeal's per-docset `meta.json`, written next to `Contents/` on install.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct DocsetMeta {
+    pub name: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub revision: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<String>,
+    /// Hex SHA-256 of the archive it was installed from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+pub fn read_meta(docset_path: &Path) -> Option<DocsetMeta> {
+    let body = fs::read_to_string(docset_path.join("meta.json")).ok()?;
+    serde_json::from_str(&body).ok()
+}
+
+/// The installed version as `meta.json` records it, `1.80.0 (rev 2)`;
+/// `None` for docsets installed by hand, which have none.
+pub fn installed_version(docset_path: &Path) -> Option<String> {
+    read_meta(docset_path)
+        .filter(|meta| !meta.version.is_empty())
+        .map(|meta| describe_version(&meta.version, &meta.revision))
+}
+
+/// Finds the installed `<name>.docset` directory, ignoring case and also
+/// matching docsets by the name in their metadata; see [`search::is_named`].
+/// Symlinked docset directories are followed. Fails when `name` is
+/// ambiguous.
+pub fn find_installed(
+    docsets_dir: &Path,
+    name: &str,
+) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+    let exact = docsets_dir.join(format!("{}.docset", name));
+    if exact.is_dir() {
+        return Ok(Some(exact));
+    }
+    let Ok(dir) = fs::read_dir(docsets_dir) else {
+        return Ok(None);
+    };
+    let docsets: Vec<PathBuf> = dir
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| search::is_docset_dir(p))
+        .chain(archive::docsets(docsets_dir))
+        .collect();
+    Ok(search::find_named(&docsets, name)?.cloned())
+}
+
+/// Downloads a feed docset archive and unpacks it as `<name>.docset` in `docsets_dir`,
+/// replacing any previous installation.
+///
+/// The archive is downloaded to a resumable partial file first and checked
+/// against the feed checksum, if any, before anything is extracted.
+pub fn install_docset(
+    docset: &FeedDocset,
+    feed_url: &str,
+    docsets_dir: &Path,
+    opts: &DownloadOptions,
+    net: &Net,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if docset.urls.is_empty() {
+        return Err(format!("Feed entry '{}' has no download URL", docset.name).into());
+    }
+    let mut candidates: Vec<String> = Vec::new();
+    for url in docset.urls.iter().flat_map(|u| download::mirrors(u)) {
+        if !candidates.contains(&url) {
+            candidates.push(url);
+        }
+    }
+    let urls = download::rank_by_latency(net, candidates);
+
+    let archive_path = download::partial_path(&docset.urls[0], &docset.version);
+    download::download(net, &urls, &archive_path, opts)?;
+    if opts.verify && docset.sha256.is_none() {
+        return Err(format!(
+            "The feed publishes no checksum for '{}' to verify it against",
+            docset.name
+        )
+        .into());
+    }
+    if let Some(expected) = &docset.sha256 {
+        if let Err(e) = download::verify_sha256(&archive_path, expected) {
+            // A corrupt partial file would otherwise be resumed forever.
+            fs::remove_file(&archive_path)?;
+            return Err(e);
+        }
+    }
+
+    let meta = DocsetMeta {
+        name: docset.name.clone(),
+        title: docset.title.clone(),
+        version: docset.version.clone(),
+        revision: docset.revision.clone(),
+        feed_url: Some(feed_url.to_string()),
+        sha256: Some(download::sha256_hex(fs::File::open(&archive_path)?)?),
+    };
+    // Keep the archive around so the docset can be rolled back later.
+    let spinner = progress::spinner(opts.progress, format!("Unpacking {}", docset.name));
+    let stored = versions::store(&archive_path, &meta);
+    let installed = stored.and_then(|stored| extract(&stored, &meta, docsets_dir));
+    spinner.finish_and_clear();
+    let installed = installed?;
+    if opts.verify {
+        let report = verify::verify(&installed, Some(std::slice::from_ref(docset)))?;
+        if !report.is_clean() {
+            report.print(&docset.name);
+            return Err(format!("'{}' failed verification", docset.name).into());
+        }
+    }
+    Ok(installed)
+}
+
+/// Lock file guarding a docsets directory against concurrent installs.
+const LOCK_FILE: &str = ".zeal-cli.lock";
+
+/// Takes the install lock of `docsets_dir`, waiting for other zeal-cli
+/// processes to finish theirs. The OS lets go of it when the returned file is
+/// closed, including when a process dies mid-install.
+pub(crate) fn lock(docsets_dir: &Path) -> std::io::Result<fs::File> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(docsets_dir.join(LOCK_FILE))?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(fs::TryLockError::WouldBlock) => {
+            eprintln!(
+                "Waiting for another install in {}...",
+                docsets_dir.display()
+            );
+            file.lock()?;
+        }
+        Err(fs::TryLockError::Error(e)) => return Err(e),
+    }
+    Ok(file)
+}
+
+/// Staging directories that installs in `docsets_dir` left behind, as
+/// happens when zeal-cli is killed mid-extraction. Ones of installs still
+/// running are listed too unless the install lock is held.
+pub fn abandoned(docsets_dir: &Path) -> Vec<PathBuf> {
+    let staging = |path: &PathBuf| {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        // `.<name>.<random>`, as `tempfile` names them.
+        let Some((docset, random)) = name.strip_prefix('.').and_then(|n| n.rsplit_once('.')) else {
+            return false;
+        };
+        !docset.is_empty()
+            && random.len() == 6
+            && random.chars().all(|c| c.is_ascii_alphanumeric())
+            && path.is_dir()
+    };
+    let Ok(dir) = fs::read_dir(docsets_dir) else {
+        return Vec::new();
+    };
+    dir.filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(staging)
+        .collect()
+}
+
+/// Unpacks a docset archive as `<name>.docset` in `docsets_dir`, replacing
+/// any previous installation, and records `meta` in its `meta.json`.
+///
+/// The archive is unpacked into a hidden sibling directory and renamed into
+/// place once complete, so searches never see a half-extracted docset.
+pub fn extract(
+    archive_path: &Path,
+    meta: &DocsetMeta,
+    docsets_dir: &Path,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    fs::create_dir_all(docsets_dir)?;
+    let _lock = lock(docsets_dir)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(archive_path)?));
+    // Removed on drop, along with whatever an error leaves in it.
+    let staging = tempfile::Builder::new()
+        .prefix(&format!(".{}.", meta.name))
+        .tempdir_in(docsets_dir)?;
+
+    // Archives contain one `<Something>.docset` directory, though not
+    // necessarily as the first member or at the top level.
+    let mut docset: Option<PathBuf> = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if docset.is_none() {
+            docset = archive::docset_root(&entry.path()?).map(|root| staging.path().join(root));
+        }
+        entry.unpack_in(staging.path())?;
+    }
+    let unpacked =
+        docset.ok_or_else(|| format!("Archive for '{}' holds no .docset directory", meta.name))?;
+    fs::write(
+        unpacked.join("meta.json"),
+        serde_json::to_string_pretty(meta)?,
+    )?;
+    verify::record(archive_path, &unpacked)?;
+
+    let target = docsets_dir.join(format!("{}.docset", meta.name));
+    replace(&unpacked, &target, &staging.path().join("previous"))?;
+    Ok(target)
+}
+
+/// Renames `unpacked` to `target`, moving anything already there to
+/// `previous` first: directories can't be renamed over one another on every
+/// platform. The previous installation is put back if the rename fails.
+pub(crate) fn replace(unpacked: &Path, target: &Path, previous: &Path) -> std::io::Result<()> {
+    let replacing = target.exists();
+    if replacing {
+        fs::rename(target, previous)?;
+    }
+    if let Err(e) = fs::rename(unpacked, target) {
+        if replacing {
+            let _ = fs::rename(previous, target);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Whether the installed copy differs from what the feed currently offers.
+pub fn is_outdated(meta: &DocsetMeta, docset: &FeedDocset) -> bool {
+    meta.version != docset.version || meta.revision != docset.revision
+}
+
+fn find<'a>(feed: &'a [FeedDocset], name: &str) -> Option<&'a FeedDocset> {
+    feed.iter().find(|d| d.name.eq_ignore_ascii_case(name))
+}
+
+pub fn install(
+    names: &[String],
+    feed: &[FeedDocset],
+    feed_url: &str,
+    docsets_dir: &Path,
+    opts: &DownloadOptions,
+    net: &Net,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for name in names {
+        let docset = find(feed, name).ok_or_else(|| format!("No docset '{}' in feed", name))?;
+        eprintln!("Installing {} {}...", docset.name, docset.version);
+        let path = install_docset(docset, feed_url, docsets_dir, opts, net)?;
+        eprintln!("Installed {} to {}", docset.name, path.display());
+    }
+    Ok(())
+}
+
+/// Reinstalls docsets that came from `feed_url` and have a newer feed entry.
+/// With `names` empty, every docset installed from that feed is considered.
+pub fn update(
+    names: &[String],
+    feed: &[FeedDocset],
+    feed_url: &str,
+    docsets_dir: &Path,
+    opts: &DownloadOptions,
+    net: &Net,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut updated = 0;
+    for entry in fs::read_dir(docsets_dir)? {
+        let path = entry?.path();
+        let Some(meta) = read_meta(&path) else {
+            continue;
+        };
+        if meta.feed_url.as_deref() != Some(feed_url) {
+            continue;
+        }
+        if !names.is_empty() && !names.iter().any(|n| n.eq_ignore_ascii_case(&meta.name)) {
+            continue;
+        }
+        if let Some(pinned) = versions::load(&meta.name).pinned {
+            eprintln!("Skipping {}: pinned to version {}", meta.name, pinned);
+            continue;
+        }
+        let Some(docset) = find(feed, &meta.name) else {
+            eprintln!("Warning: '{}' is no longer listed in the feed", meta.name);
+            continue;
+        };
+        if !is_outdated(&meta, docset) {
+            continue;
+        }
+        eprintln!(
+            "Updating {} {} -> {}...",
+            meta.name, meta.version, docset.version
+        );
+        install_docset(docset, feed_url, docsets_dir, opts, net)?;
+        updated += 1;
+    }
+    if updated == 0 {
+        eprintln!("All docsets are up to date.");
+    }
+    Ok(())
+}
+
+/// Per-docset feeds of the docsets Zeal installs from its own repository,
+/// for installed docsets that didn't come from a feed URL.
+pub const KAPELI_FEEDS_URL: &str = "https://raw.githubusercontent.com/Kapeli/feeds/master";
+
+pub(crate) fn describe_version(version: &str, revision: &str) -> String {
+    if revision.is_empty() || revision == "0" {
+        version.to_string()
+    } else {
+        format!("{} (rev {})", version, revision)
+    }
+}
+
+fn numeric_version(version: &str) -> Option<Vec<u64>> {
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+/// How far `installed` is behind what a feed offers, `None` when it isn't.
+///
+/// Counts releases when the feed lists its older versions, else names the
+/// kind of version bump.
+fn behind(meta: &DocsetMeta, version: &str, revision: &str, others: &[String]) -> Option<String> {
+    if meta.version == version {
+        return (meta.revision != revision).then(|| "new revision".to_string());
+    }
+    let (installed, available) = (numeric_version(&meta.version), numeric_version(version));
+    if let (Some(installed), Some(available)) = (&installed, &available) {
+        if installed > available {
+            return None;
+        }
+    }
+    let releases = others
+        .iter()
+        .filter(|v| v.as_str() != version)
+        .position(|v| *v == meta.version)
+        .map(|n| n + 1);
+    if let Some(n) = releases {
+        return Some(format!(
+            "{} release{} behind",
+            n,
+            if n == 1 { "" } else { "s" }
+        ));
+    }
+    Some(match (installed, available) {
+        (Some(installed), Some(available)) => {
+            let level = installed
+                .iter()
+                .zip(&available)
+                .position(|(a, b)| a != b)
+                .unwrap_or(installed.len().min(available.len()));
+            match level {
+                0 => "major update",
+                1 => "minor update",
+                _ => "patch update",
+            }
+            .to_string()
+        }
+        _ => "new version".to_string(),
+    })
+}
+
+/// Reports which installed docsets their feed has a newer version of,
+/// downloading only feeds, never archives. Returns how many are outdated.
+///
+/// Docsets installed from a per-docset XML feed are checked against it,
+/// those from the user-contributed JSON feed against `user_feed_url`, and
+/// the rest against Kapeli's feed of the same name.
+pub fn check(
+    names: &[String],
+    user_feed_url: &str,
+    docsets_dir: &Path,
+    net: &Net,
+    refresh: bool,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut user_feed: Option<Vec<FeedDocset>> = None;
+    let mut rows: Vec<[String; 4]> = Vec::new();
+    let mut manual = Vec::new();
+    let mut paths: Vec<PathBuf> = fs::read_dir(docsets_dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let Some(meta) = read_meta(&path) else {
+            if search::is_docset_dir(&path)
+                && (names.is_empty() || names.iter().any(|n| search::is_named(&path, n)))
+            {
+                manual.push(search::docset_name(&path));
+            }
+            continue;
+        };
+        if !names.is_empty() && !names.iter().any(|n| n.eq_ignore_ascii_case(&meta.name)) {
+            continue;
+        }
+        let available = match meta.feed_url.as_deref() {
+            Some(url) if url == user_feed_url => {
+                if user_feed.is_none() {
+                    user_feed = Some(feed::load_user_contrib(net, url, refresh)?);
+                }
+                user_feed
+                    .as_deref()
+                    .and_then(|f| find(f, &meta.name))
+                    .map(|d| (d.version.clone(), d.revision.clone(), Vec::new()))
+                    .ok_or_else(|| format!("'{}' is no longer listed in the feed", meta.name))
+            }
+            url => {
+                let url = url.map_or_else(
+                    || format!("{}/{}.xml", KAPELI_FEEDS_URL, meta.name),
+                    str::to_string,
+                );
+                feed::load_xml(net, &url, refresh)
+                    .map(|f| (f.version, f.revision, f.other_versions))
+                    .map_err(|e| format!("Cannot check {}: {}", meta.name, e))
+            }
+        };
+        let (version, revision, others) = match available {
+            Ok(available) => available,
+            Err(e) => {
+                eprintln!("Warning: {}", e);
+                continue;
+            }
+        };
+        let Some(behind) = behind(&meta, &version, &revision, &others) else {
+            continue;
+        };
+        let pinned = versions::load(&meta.name)
+            .pinned
+            .map(|v| format!(", pinned to {}", v))
+            .unwrap_or_default();
+        rows.push([
+            meta.name.clone(),
+            describe_version(&meta.version, &meta.revision),
+            describe_version(&version, &revision),
+            format!("{}{}", behind, pinned),
+        ]);
+    }
+
+    if !manual.is_empty() {
+        eprintln!(
+            "Not checked, installed without meta.json: {}",
+            manual.join(", ")
+        );
+    }
+    if rows.is_empty() {
+        eprintln!("All docsets are up to date.");
+        return Ok(0);
+    }
+    let width = |i: usize| rows.iter().map(|r| r[i].len()).max().unwrap_or(0);
+    let (name_w, installed_w, available_w) = (width(0), width(1), width(2));
+    for [name, installed, available, behind] in &rows {
+        println!(
+            "{:name_w$}  {:installed_w$} -> {:available_w$}  {}",
+            name, installed, available, behind
+        );
+    }
+    Ok(rows.len())
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{db, slow_fs};
+
+const INVENTORY_VERSION: u32 = 1;
+
+/// The docsets found in the docset roots, kept between runs so commands
+/// needn't list every root and open every archive each time.
+#[derive(Serialize, Deserialize, Default)]
+struct Inventory {
+    version: u32,
+    /// The roots scanned with their modification times, which change when
+    /// a docset is added, removed or renamed in them.
+    roots: Vec<(PathBuf, Option<u128>)>,
+    docsets: Vec<PathBuf>,
+    /// What was read from each docset's index, by docset path.
+    #[serde(default)]
+    details: BTreeMap<PathBuf, Details>,
+}
+
+/// What a docset's index holds.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Details {
+    /// `dash` for a `searchIndex` table, `zdash` for the Core Data tables of
+    /// Dash's newer docsets, else `unknown`.
+    pub schema: String,
+    pub entries: u64,
+    /// Modification time of the index these were read from.
+    index_mtime: Option<u128>,
+}
+
+fn inventory_path() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("zeal-cli")
+        .join("inventory.json")
+}
+
+fn mtime(path: &Path) -> Option<u128> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(
+        modified
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?
+            .as_nanos(),
+    )
+}
+
+fn load() -> Option<Inventory> {
+    let body = fs::read(inventory_path()).ok()?;
+    serde_json::from_slice::<Inventory>(&body)
+        .ok()
+        .filter(|inventory| inventory.version == INVENTORY_VERSION)
+}
+
+/// Failures are only logged: the inventory is rebuilt on the next run.
+fn save(inventory: &Inventory) {
+    let path = inventory_path();
+    let write = || -> Result<(), Box<dyn std::error::Error>> {
+        let dir = path.parent().ok_or("no cache directory")?;
+        fs::create_dir_all(dir)?;
+        // Written aside and renamed so concurrent runs never read half of it.
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        serde_json::to_writer(&mut tmp, inventory)?;
+        tmp.persist(&path).map_err(|e| e.error)?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        tracing::warn!("Could not write {}: {}", path.display(), e);
+    }
+}
+
+/// The installed docsets of `roots`: the inventory's when none of the roots
+/// changed since it was taken and its docsets are all still there, else
+/// what `scan` finds, which is kept for next time. `refresh` always scans.
+pub fn installed(
+    roots: &[PathBuf],
+    refresh: bool,
+    scan: impl FnOnce() -> std::io::Result<Vec<PathBuf>>,
+) -> std::io::Result<Vec<PathBuf>> {
+    let stamps: Vec<(PathBuf, Option<u128>)> =
+        roots.iter().map(|r| (r.clone(), mtime(r))).collect();
+    let previous = load();
+    if let Some(inventory) = previous.as_ref().filter(|_| !refresh) {
+        // The roots' modification times cover docsets added or removed; a
+        // slow mount is spared checking each docset besides.
+        let present = slow_fs::enabled() || inventory.docsets.iter().all(|p| p.exists());
+        if inventory.roots == stamps && present {
+            tracing::debug!(
+                docsets = inventory.docsets.len(),
+                "docset inventory is current"
+            );
+            return Ok(inventory.docsets.clone());
+        }
+    }
+    let docsets = scan()?;
+    let mut details = previous.map(|i| i.details).unwrap_or_default();
+    details.retain(|path, _| docsets.contains(path));
+    save(&Inventory {
+        version: INVENTORY_VERSION,
+        roots: stamps,
+        docsets: docsets.clone(),
+        details,
+    });
+    Ok(docsets)
+}
+
+/// The index details of each docset, read again only for indexes written
+/// since the inventory last had them.
+pub fn details(docsets: &[PathBuf]) -> Vec<Details> {
+    let mut inventory = load().unwrap_or_else(|| Inventory {
+        version: INVENTORY_VERSION,
+        ..Default::default()
+    });
+    let mut changed = false;
+    let found = docsets
+        .iter()
+        .map(|docset| {
+            let index_mtime = mtime(&docset.join("Contents/Resources/docSet.dsidx"));
+            if let Some(details) = inventory
+                .details
+                .get(docset)
+                .filter(|d| index_mtime.is_some() && d.index_mtime == index_mtime)
+            {
+                return details.clone();
+            }
+            let details = Details {
+                index_mtime,
+                ..read_details(docset)
+            };
+            inventory.details.insert(docset.clone(), details.clone());
+            changed = true;
+            details
+        })
+        .collect();
+    if changed {
+        save(&inventory);
+    }
+    found
+}
+
+fn read_details(docset: &Path) -> Details {
+    let read = db::read(docset, |conn| {
+        let has = |table: &str| -> rusqlite::Result<bool> {
+            conn.query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )
+        };
+        let (schema, count_sql) = if has("searchIndex")? {
+            ("dash", "SELECT COUNT(*) FROM searchIndex")
+        } else if has("ZTOKEN")? {
+            ("zdash", "SELECT COUNT(*) FROM ZTOKEN")
+        } else {
+            return Ok(("unknown", 0));
+        };
+        Ok((schema, conn.query_row(count_sql, [], |row| row.get(0))?))
+    });
+    let (schema, entries) = read.unwrap_or(("unknown", 0));
+    Details {
+        schema: schema.to_string(),
+        entries,
+        index_mtime: None,
+    }
+}
@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::{search, zeal_conf};
+
+/// Keywords a docset answers to on its own, the way Zeal derives them: the
+/// `DocSetPlatformFamily` and `DashDocSetKeyword` of its `Info.plist` and the
+/// `extra.keywords` of its `meta.json`.
+pub fn docset_keywords(docset_path: &Path) -> Vec<String> {
+    let mut keywords = Vec::new();
+    if let Some(info) = search::info_plist(docset_path) {
+        for key in ["DocSetPlatformFamily", "DashDocSetKeyword"] {
+            if let Some(k) = info.get(key).and_then(|v| v.as_string()) {
+                keywords.push(k.to_string());
+            }
+        }
+    }
+    if let Some(meta) = fs::read_to_string(docset_path.join("meta.json"))
+        .ok()
+        .and_then(|body| serde_json::from_str::<serde_json::Value>(&body).ok())
+    {
+        if let Some(extra) = meta["extra"]["keywords"].as_array() {
+            keywords.extend(extra.iter().filter_map(|k| k.as_str()).map(str::to_string));
+        }
+    }
+    keywords
+}
+
+/// Splits a `kw:query` or `kw1,kw2:query` search argument.
+pub fn split(arg: &str) -> Option<(Vec<&str>, &str)> {
+    let (keywords, query) = arg.split_once(':')?;
+    // `std::vec` is a query, not a keyword.
+    if keywords.is_empty() || query.starts_with(':') {
+        return None;
+    }
+    Some((keywords.split(',').map(str::trim).collect(), query))
+}
+
+/// Installed docsets answering to `keyword`: those listed for it under
+/// `[keywords]` in the config, else under `[keywords]` in Zeal's settings,
+/// otherwise those declaring it themselves.
+pub fn resolve(keyword: &str, config: &Config, installed: &[PathBuf]) -> Vec<PathBuf> {
+    let mut configured: Vec<String> = config
+        .keywords
+        .iter()
+        .filter(|(k, _)| k.eq_ignore_ascii_case(keyword))
+        .flat_map(|(_, names)| names.as_slice())
+        .cloned()
+        .collect();
+    if configured.is_empty() {
+        configured = zeal_conf::keyword_docsets(keyword);
+    }
+    if !configured.is_empty() {
+        return installed
+            .iter()
+            .filter(|p| {
+                let name = search::docset_name(p);
+                configured.iter().any(|n| n.eq_ignore_ascii_case(&name))
+            })
+            .cloned()
+            .collect();
+    }
+    installed
+        .iter()
+        .filter(|p| {
+            docset_keywords(p)
+                .iter()
+                .any(|k| k.eq_ignore_ascii_case(keyword))
+        })
+        .cloned()
+        .collect()
+}
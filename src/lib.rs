@@ -0,0 +1,46 @@
+//! The search engine over a C ABI, for tools that would rather not run
+//! zeal-cli as a subprocess: Python through `ctypes`, Lua through LuaJIT's
+//! FFI in Neovim, or C. `include/zeal.h` declares these functions.
+//!
+//! Strings are UTF-8 and NUL-terminated both ways. Functions return JSON as
+//! a string the caller frees with `zeal_string_free`, or NULL on failure,
+//! after which `zeal_last_error` says what went wrong.
+//!
+//! Built as a `cdylib` with `--features ffi`; without it the library is
+//! empty and only the zeal-cli binary is built.
+#![cfg(feature = "ffi")]
+// The modules are the binary's; the ABI uses only part of them.
+#![allow(dead_code)]
+
+mod archive;
+mod cache;
+mod config;
+mod db;
+mod download;
+mod enrich;
+mod feed;
+mod ffi;
+mod history;
+mod i18n;
+mod install;
+mod inventory;
+mod listing;
+mod net;
+mod open;
+mod platform;
+mod plugin;
+mod preview;
+mod progress;
+mod query;
+mod ranking;
+mod related;
+mod render;
+mod search;
+mod slow_fs;
+mod theme;
+mod toc;
+mod types;
+mod usage;
+mod verify;
+mod versions;
+mod zeal_conf;
@@ -0,0 +1,131 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::search;
+
+/// `zeal://Rust/Vec::push`: a docset and an entry name, percent-encoded
+/// where need be, ending at whitespace or the Markdown or HTML around it.
+/// A `.`, `,`, `;` or `:` ending the sentence after the link isn't part of
+/// the name; `!` and `?` are, as in `println!` or Ruby's `empty?`.
+const ZEAL_LINK: &str = r#"zeal://(?P<docset>[^/\s]+)/(?P<name>[^\s)\]>"'`]*[^\s)\]>"'`.,;:])"#;
+
+/// A reference to a docset entry found in a file.
+pub struct Link {
+    pub file: PathBuf,
+    /// 1-based.
+    pub line: usize,
+    /// The reference as written.
+    pub text: String,
+    pub docset: String,
+    pub name: String,
+}
+
+/// The patterns links are found with: `zeal://` links, then the config's
+/// `link_patterns`, each of which must capture `docset` and `name`.
+pub fn patterns(configured: &[Regex]) -> Result<Vec<Regex>, Box<dyn std::error::Error>> {
+    let mut patterns = vec![Regex::new(ZEAL_LINK).unwrap()];
+    for pattern in configured {
+        let names: Vec<_> = pattern.capture_names().flatten().collect();
+        if !names.contains(&"docset") || !names.contains(&"name") {
+            return Err(format!(
+                "link pattern '{}' must capture both `docset` and `name`",
+                pattern
+            )
+            .into());
+        }
+        patterns.push(pattern.clone());
+    }
+    Ok(patterns)
+}
+
+/// The links in `path`, a file or a directory searched recursively,
+/// skipping hidden entries such as `.git`, and how many files were read.
+/// Files that aren't UTF-8 text are passed over.
+pub fn scan(path: &Path, patterns: &[Regex]) -> io::Result<(Vec<Link>, usize)> {
+    let mut files = Vec::new();
+    collect_files(path, &mut files)?;
+    let mut links = Vec::new();
+    let mut read = 0;
+    for file in files {
+        let Ok(text) = fs::read_to_string(&file) else {
+            continue;
+        };
+        read += 1;
+        for (i, line) in text.lines().enumerate() {
+            for pattern in patterns {
+                for caps in pattern.captures_iter(line) {
+                    let decode = |group: &str| {
+                        let raw = caps.name(group).map_or("", |m| m.as_str());
+                        urlencoding::decode(raw)
+                            .map_or_else(|_| raw.to_string(), |d| d.into_owned())
+                    };
+                    links.push(Link {
+                        file: file.clone(),
+                        line: i + 1,
+                        text: caps[0].to_string(),
+                        docset: decode("docset"),
+                        name: decode("name"),
+                    });
+                }
+            }
+        }
+    }
+    Ok((links, read))
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| !n.starts_with('.'))
+        })
+        .collect();
+    entries.sort();
+    for entry in entries {
+        collect_files(&entry, files)?;
+    }
+    Ok(())
+}
+
+/// The links whose target doesn't exist, with why. `resolve` finds an
+/// installed docset by name; each docset and name is only looked up once.
+pub fn dangling(
+    links: &[Link],
+    resolve: impl Fn(&str) -> Result<Option<PathBuf>, Box<dyn std::error::Error>>,
+    ignore_case: bool,
+) -> Result<Vec<(&Link, String)>, Box<dyn std::error::Error>> {
+    let mut docsets: HashMap<&str, Option<PathBuf>> = HashMap::new();
+    let mut found: HashMap<(&str, &str), bool> = HashMap::new();
+    let mut dangling = Vec::new();
+    for link in links {
+        if !docsets.contains_key(link.docset.as_str()) {
+            docsets.insert(&link.docset, resolve(&link.docset)?);
+        }
+        let Some(docset_path) = &docsets[link.docset.as_str()] else {
+            dangling.push((link, format!("docset '{}' is not installed", link.docset)));
+            continue;
+        };
+        let key = (link.docset.as_str(), link.name.as_str());
+        let exists = match found.get(&key) {
+            Some(exists) => *exists,
+            None => {
+                let exists = search::has_entry(docset_path, &link.name, ignore_case)?;
+                found.insert(key, exists);
+                exists
+            }
+        };
+        if !exists {
+            dangling.push((link, format!("no entry '{}' in {}", link.name, link.docset)));
+        }
+    }
+    Ok(dangling)
+}
@@ -0,0 +1,142 @@
+use clap::ValueEnum;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::ranking::Ranking;
+use crate::{i18n, install, inventory, search, versions};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    /// Alphabetically
+    #[default]
+    Name,
+    /// Largest on disk first
+    Size,
+    /// Most index entries first
+    Entries,
+    /// Most recently installed or updated first
+    Updated,
+    /// In the order results of equal score are shown in, see `[ranking]
+    /// priority`
+    Priority,
+}
+
+/// Keeps the docsets whose name matches `pattern`: a case-insensitive glob
+/// when it contains `*` or `?`, a fuzzy match otherwise.
+pub fn filter(docsets: Vec<PathBuf>, pattern: &str) -> Vec<PathBuf> {
+    let matcher = SkimMatcherV2::default();
+    let is_glob = pattern.contains(['*', '?']);
+    docsets
+        .into_iter()
+        .filter(|p| {
+            let name = search::docset_name(p);
+            if is_glob {
+                glob_match(&pattern.to_lowercase(), &name.to_lowercase())
+            } else {
+                matcher.fuzzy_match(&name, pattern).is_some()
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let (p, t): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+    // Classic two-pointer matcher, backtracking to the last `*`.
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+pub fn sort(docsets: &mut [PathBuf], key: SortKey, ranking: &Ranking) {
+    match key {
+        SortKey::Name => {
+            docsets.sort_by(|a, b| i18n::compare(&search::docset_name(a), &search::docset_name(b)))
+        }
+        SortKey::Size => docsets.sort_by_cached_key(|p| std::cmp::Reverse(dir_size(p))),
+        SortKey::Entries => {
+            let entries: HashMap<PathBuf, u64> = docsets
+                .iter()
+                .cloned()
+                .zip(inventory::details(docsets).into_iter().map(|d| d.entries))
+                .collect();
+            docsets.sort_by_cached_key(|p| std::cmp::Reverse(entries[p]));
+        }
+        SortKey::Updated => docsets.sort_by_cached_key(|p| std::cmp::Reverse(updated(p))),
+        SortKey::Priority => ranking.sort(docsets),
+    }
+}
+
+/// Total size of the files under `path`, not following symlinks.
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    fs::read_dir(path)
+        .map(|dir| {
+            dir.filter_map(|e| e.ok())
+                .map(|e| dir_size(&e.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Prints what is known of an installed docset: what its `meta.json`
+/// records, which docsets installed by hand lack, its index and location.
+pub fn info(docset_path: &Path) {
+    let meta = install::read_meta(docset_path);
+    let details = inventory::details(std::slice::from_ref(&docset_path.to_path_buf()));
+    let details = details.first().cloned().unwrap_or_default();
+    let name = search::docset_name(docset_path);
+    println!("Name:      {}", name);
+    match &meta {
+        Some(meta) => {
+            if !meta.title.is_empty() {
+                println!("Title:     {}", meta.title);
+            }
+            let version = install::installed_version(docset_path);
+            println!("Version:   {}", version.as_deref().unwrap_or("unknown"));
+            let feed = meta.feed_url.as_deref().unwrap_or("Kapeli's feed");
+            println!("Feed:      {}", feed);
+            if let Some(pinned) = versions::load(&meta.name).pinned {
+                println!("Pinned:    {}", pinned);
+            }
+            if let Some(sha256) = &meta.sha256 {
+                println!("Checksum:  {}", sha256);
+            }
+        }
+        None => println!("Version:   unknown, installed without meta.json"),
+    }
+    println!("Entries:   {}", details.entries);
+    println!("Schema:    {}", details.schema);
+    println!("Path:      {}", docset_path.display());
+}
+
+/// When the docset's index was last written.
+pub fn updated(docset_path: &Path) -> SystemTime {
+    fs::metadata(docset_path.join("Contents/Resources/docSet.dsidx"))
+        .or_else(|_| fs::metadata(docset_path))
+        .and_then(|m| m.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
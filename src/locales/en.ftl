@@ -0,0 +1,21 @@
+no-command = No command provided.
+no-docsets = No docsets found.
+no-docsets-to-search = No docsets found to search
+docset-not-found = Docset '{ $docset }' not found in { $dirs }
+default-docset-missing = Default docset '{ $docset }' is not installed
+search-label-one = docset '{ $docset }'
+search-label-many = { $count } docsets
+no-results = No results found for '{ $query }' in { $label }
+did-you-mean = Did you mean: { $names }?
+search-error = Error searching { $label }: { $error }
+cheat-not-found = Cheat sheet '{ $name }' not found
+cheat-no-entries = No entries found for '{ $query }' in cheat sheet '{ $name }'
+no-entry = No entry found for '{ $query }' in docset '{ $docset }'
+no-related-entry = No related entry { $number } for '{ $name }'
+related-header = Related:
+no-page = No page found for '{ $page }' in docset '{ $docset }'
+no-sections = No sections found in { $page }
+cache-cleared = Removed { $count } cached result { $count ->
+    [one] set
+   *[other] sets
+}
@@ -0,0 +1,18 @@
+no-command = コマンドが指定されていません。
+no-docsets = ドキュメントセットが見つかりません。
+no-docsets-to-search = 検索するドキュメントセットが見つかりません
+docset-not-found = ドキュメントセット「{ $docset }」が { $dirs } に見つかりません
+default-docset-missing = 既定のドキュメントセット「{ $docset }」はインストールされていません
+search-label-one = ドキュメントセット「{ $docset }」
+search-label-many = { $count } 個のドキュメントセット
+no-results = { $label } に「{ $query }」の結果はありません
+did-you-mean = もしかして: { $names }
+search-error = { $label } の検索中にエラーが発生しました: { $error }
+cheat-not-found = チートシート「{ $name }」が見つかりません
+cheat-no-entries = チートシート「{ $name }」に「{ $query }」の項目はありません
+no-entry = ドキュメントセット「{ $docset }」に「{ $query }」の項目はありません
+no-related-entry = 「{ $name }」に関連する項目 { $number } はありません
+related-header = 関連項目:
+no-page = ドキュメントセット「{ $docset }」に「{ $page }」のページはありません
+no-sections = { $page } にセクションはありません
+cache-cleared = キャッシュされた検索結果を { $count } 件削除しました
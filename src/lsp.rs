@@ -0,0 +1,434 @@
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::query::{self, Query};
+use crate::render::{self, Flavor};
+use crate::search::{self, MatchMode, Narrowed, SearchOptions, SearchResult};
+use crate::serve::Server;
+use crate::{keywords, open, plugin, preview, project, ranking, toc, types};
+
+/// Most results `workspace/symbol` returns; clients filter further as the
+/// user types.
+const SYMBOL_LIMIT: usize = 100;
+/// Longest hover text, in bytes; the full page is one click away.
+const HOVER_LIMIT: usize = 6000;
+
+/// JSON-RPC error codes.
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// Minimal language server: `textDocument/hover` shows the documentation of
+/// the symbol under the cursor and `workspace/symbol` searches all docsets.
+///
+/// Hover looks in the docsets matching the document's language ID, as a
+/// keyword (see [`keywords::resolve`]), falling back to the docsets of the
+/// workspace's project and then to every installed docset.
+struct Lsp<'a> {
+    server: &'a Server,
+    /// Open documents' text by URI, kept in sync with full-text changes.
+    documents: HashMap<String, (String, String)>,
+    root: Option<PathBuf>,
+    /// Each docset's matches of the last `workspace/symbol` query, which
+    /// the next one narrows as the user types on.
+    narrowed: HashMap<PathBuf, Option<Narrowed>>,
+    shutdown: bool,
+}
+
+/// Serves LSP on stdin/stdout until the client sends `exit`.
+pub fn run_stdio(server: &Server) -> std::io::Result<()> {
+    let stdin = std::io::stdin();
+    let mut reader = stdin.lock();
+    let mut writer = std::io::stdout().lock();
+    let mut lsp = Lsp {
+        server,
+        documents: HashMap::new(),
+        root: None,
+        narrowed: HashMap::new(),
+        shutdown: false,
+    };
+    while let Some(message) = read_message(&mut reader)? {
+        let message: Value = match serde_json::from_str(&message) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = json!({ "code": -32700, "message": e.to_string() });
+                write_message(
+                    &mut writer,
+                    &json!({ "jsonrpc": "2.0", "id": null, "error": error }),
+                )?;
+                continue;
+            }
+        };
+        let method = message["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            break;
+        }
+        let result = lsp.dispatch(method, &message["params"]);
+        // Notifications carry no id and get no response.
+        let Some(id) = message.get("id") else {
+            continue;
+        };
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, e)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": e },
+            }),
+        };
+        write_message(&mut writer, &response)?;
+    }
+    Ok(())
+}
+
+/// Reads one `Content-Length` framed message, `None` at end of input.
+fn read_message(reader: &mut impl BufRead) -> std::io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "Missing Content-Length")
+    })?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(String::from_utf8_lossy(&body).into_owned()))
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+impl Lsp<'_> {
+    fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if self.shutdown && method != "exit" {
+            return Err((-32600, "Server is shutting down".to_string()));
+        }
+        match method {
+            "initialize" => {
+                self.root = params["rootUri"]
+                    .as_str()
+                    .and_then(uri_to_path)
+                    .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+                Ok(json!({
+                    "capabilities": {
+                        // Full sync: every change resends the whole document.
+                        "textDocumentSync": 1,
+                        "hoverProvider": true,
+                        "workspaceSymbolProvider": true,
+                    },
+                    "serverInfo": { "name": "zeal-cli", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/didOpen" => {
+                let doc = &params["textDocument"];
+                if let (Some(uri), Some(text)) = (doc["uri"].as_str(), doc["text"].as_str()) {
+                    let language = doc["languageId"].as_str().unwrap_or_default();
+                    self.documents
+                        .insert(uri.to_string(), (language.to_string(), text.to_string()));
+                }
+                Ok(Value::Null)
+            }
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let (Some((_, current)), Some(text)) = (self.documents.get_mut(uri), text) {
+                    *current = text.to_string();
+                }
+                Ok(Value::Null)
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = params["textDocument"]["uri"].as_str() {
+                    self.documents.remove(uri);
+                }
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => self.hover(params),
+            "workspace/symbol" => self.symbols(params["query"].as_str().unwrap_or_default()),
+            // Other notifications (`initialized`, `$/cancelRequest`, ...) need
+            // no action; other requests aren't supported.
+            _ if method.starts_with("$/") || method == "initialized" => Ok(Value::Null),
+            other => Err((-32601, format!("Unknown method '{}'", other))),
+        }
+    }
+
+    /// Docsets to look a symbol up in for a document of `language`.
+    fn docsets_for(&self, language: &str) -> Vec<PathBuf> {
+        let installed = self.server.installed();
+        let by_language = keywords::resolve(language, self.server.config(), &installed);
+        if !by_language.is_empty() {
+            return by_language;
+        }
+        let by_project = self
+            .root
+            .as_deref()
+            .map(|root| project::detect(root, &installed))
+            .unwrap_or_default();
+        if !by_project.is_empty() {
+            return by_project;
+        }
+        installed
+    }
+
+    fn search(
+        &self,
+        docsets: &[PathBuf],
+        query: &str,
+        mode: MatchMode,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, (i64, String)> {
+        let query = Query {
+            text: query.to_string(),
+            ..Default::default()
+        };
+        self.search_narrowing(docsets, &query, mode, limit, None)
+    }
+
+    /// [`Lsp::search`], narrowing each docset's matches of the previous
+    /// query in `narrowed` when given, see [`search::match_narrowing`].
+    fn search_narrowing(
+        &self,
+        docsets: &[PathBuf],
+        query: &Query,
+        mode: MatchMode,
+        limit: usize,
+        mut narrowed: Option<&mut HashMap<PathBuf, Option<Narrowed>>>,
+    ) -> Result<Vec<SearchResult>, (i64, String)> {
+        let config = self.server.config();
+        let mut per_docset = Vec::new();
+        for docset_path in docsets {
+            let name = search::docset_name(docset_path);
+            let opts = SearchOptions {
+                synonyms: config.expand_query(&name, &query.text),
+                types: query.types.clone(),
+                ignore: config.ignore_for(&name),
+                boosts: config.ranking.boosts,
+                mode,
+                limit: plugin::scoring_limit(&config.plugins, Some(limit)),
+                filters: query.filters.clone(),
+                ..Default::default()
+            };
+            let entries = match self.server.index(docset_path) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    tracing::warn!("Skipping {}: {}", name, e);
+                    continue;
+                }
+            };
+            let mut found = match narrowed.as_deref_mut() {
+                Some(narrowed) => {
+                    let last = narrowed.entry(docset_path.clone()).or_default();
+                    search::match_narrowing(&entries, &query.text, &opts, last)
+                }
+                None => search::match_entries(&entries, &query.text, &opts),
+            };
+            for m in &mut found {
+                m.docset = name.clone();
+            }
+            per_docset.push((name, found));
+        }
+        let matches = ranking::merge(
+            per_docset,
+            &query.text,
+            config.ranking.strategy,
+            &config.ranking,
+        );
+        // A failing transform fails the search, as it does on the command line.
+        plugin::transform(&config.plugins, &query.text, matches, Some(limit))
+            .map_err(|e| (INTERNAL_ERROR, e.to_string()))
+    }
+
+    fn hover(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let Some((language, text)) = self.documents.get(uri) else {
+            return Ok(Value::Null);
+        };
+        let position = &params["position"];
+        let line = position["line"].as_u64().unwrap_or(0) as usize;
+        let character = position["character"].as_u64().unwrap_or(0) as usize;
+        let Some((symbol, range)) = symbol_at(text, line, character) else {
+            return Ok(Value::Null);
+        };
+
+        let docsets = self.docsets_for(language);
+        // Prefer an exact hit on the qualified name, then on its last
+        // segment (`new` of `Vec::new`), then on its qualifiers (`Vec`),
+        // then the best fuzzy match.
+        let mut candidates = vec![symbol.as_str()];
+        candidates.extend(symbol.rsplit(['.', ':']).next());
+        let mut qualifier = symbol.as_str();
+        while let Some(i) = qualifier.rfind(['.', ':']) {
+            qualifier = qualifier[..i].trim_end_matches([':', '.']);
+            candidates.push(qualifier);
+        }
+        let mut found = None;
+        for q in candidates.iter().filter(|q| !q.is_empty()) {
+            found = self.search(&docsets, q, MatchMode::Exact, 1)?.pop();
+            if found.is_some() {
+                break;
+            }
+        }
+        if found.is_none() {
+            found = self.search(&docsets, &symbol, MatchMode::Fuzzy, 1)?.pop();
+        }
+        let Some(found) = found else {
+            return Ok(Value::Null);
+        };
+        tracing::debug!(symbol, name = found.name, docset = found.docset, "hover");
+
+        let mut value = format!("**{}** ({}, {})", found.name, found.type_, found.docset);
+        if let Some(doc) = excerpt(&found.path) {
+            value.push_str("\n\n");
+            value.push_str(&doc);
+        }
+        value.push_str(&format!(
+            "\n\n[Open documentation]({})",
+            open::entry_url(&found.path)
+        ));
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": value },
+            "range": range,
+        }))
+    }
+
+    fn symbols(&mut self, query: &str) -> Result<Value, (i64, String)> {
+        let query = query::parse(query).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+        let mut narrowed = std::mem::take(&mut self.narrowed);
+        let installed = self.server.installed();
+        // Docsets no longer installed needn't keep their matches.
+        narrowed.retain(|path, _| installed.contains(path));
+        let matches = self.search_narrowing(
+            &installed,
+            &query,
+            MatchMode::Fuzzy,
+            SYMBOL_LIMIT,
+            Some(&mut narrowed),
+        );
+        self.narrowed = narrowed;
+        Ok(Value::Array(
+            matches?
+                .iter()
+                .map(|m| {
+                    let zero = json!({ "line": 0, "character": 0 });
+                    json!({
+                        "name": m.name,
+                        "kind": symbol_kind(&m.type_),
+                        "location": {
+                            "uri": open::entry_url(&toc::strip_fragment(&m.path)),
+                            "range": { "start": zero, "end": zero },
+                        },
+                        "containerName": m.docset,
+                    })
+                })
+                .collect(),
+        ))
+    }
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    Some(PathBuf::from(urlencoding::decode(path).ok()?.into_owned()))
+}
+
+/// The identifier under the cursor, with `.` and `::` qualifiers, and its
+/// LSP range. `character` counts UTF-16 code units, as LSP does.
+fn symbol_at(text: &str, line: usize, character: usize) -> Option<(String, Value)> {
+    let chars: Vec<char> = text.lines().nth(line)?.chars().collect();
+    let mut units = 0;
+    let cursor = chars
+        .iter()
+        .position(|c| {
+            units += c.len_utf16();
+            units > character
+        })
+        .unwrap_or(chars.len());
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let is_part = |i: usize| {
+        chars.get(i).is_some_and(|&c| {
+            is_word(c)
+                // Qualifier separators count only between word characters.
+                || ((c == '.' || c == ':')
+                    && i > 0
+                    && chars.get(i + 1).is_some_and(|&n| is_word(n) || n == ':'))
+        })
+    };
+    if !chars.get(cursor).is_some_and(|&c| is_word(c)) {
+        return None;
+    }
+    let mut start = cursor;
+    while start > 0 && is_part(start - 1) {
+        start -= 1;
+    }
+    let mut end = cursor;
+    while end < chars.len() && is_word(chars[end]) {
+        end += 1;
+    }
+    let symbol: String = chars[start..end].iter().collect();
+    let symbol = symbol.trim_start_matches([':', '.']).to_string();
+    let utf16 = |n: usize| chars[..n].iter().map(|c| c.len_utf16()).sum::<usize>();
+    let range = json!({
+        "start": { "line": line, "character": utf16(start) },
+        "end": { "line": line, "character": utf16(end) },
+    });
+    Some((symbol, range))
+}
+
+/// The documentation an entry points at, as Markdown: the element its
+/// `#fragment` names, widened until it says something, or the whole page.
+fn excerpt(path: &Path) -> Option<String> {
+    let mut text = render::html_to(&preview::region(path).ok()?, Flavor::Markdown);
+    let text_len = text.trim_end().len();
+    text.truncate(text_len);
+    if text.len() > HOVER_LIMIT {
+        let mut cut = HOVER_LIMIT;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        text.truncate(cut);
+        text.push_str("\n\n…");
+    }
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// LSP `SymbolKind` for a docset entry type.
+fn symbol_kind(type_: &str) -> u8 {
+    match types::normalize(type_) {
+        "Module" | "Package" | "Library" => 2,
+        "Namespace" => 3,
+        "Class" | "Struct" | "Type" | "Union" | "Trait" | "Protocol" => 5,
+        "Method" | "Constructor" => 6,
+        "Property" | "Attribute" => 7,
+        "Field" => 8,
+        "Enum" => 10,
+        "Interface" => 11,
+        "Function" | "Macro" | "Procedure" => 12,
+        "Variable" | "Global" => 13,
+        "Constant" => 14,
+        "Value" | "Option" => 22,
+        "Event" => 24,
+        "Operator" => 25,
+        _ => 12,
+    }
+}
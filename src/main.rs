@@ -1,183 +1,2547 @@
 use ansi_term::Colour;
-use clap::{Parser, Subcommand};
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
-use rusqlite::{Connection, Result};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use rusqlite::Result;
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::PathBuf;
+use std::time::Instant;
+
+mod anchors;
+mod archive;
+mod bench;
+mod bookmarks;
+mod breadcrumbs;
+mod bundle;
+mod cache;
+mod capabilities;
+mod cheat;
+mod chooser;
+mod clipboard;
+mod config;
+mod db;
+mod diff;
+mod download;
+mod du;
+mod enrich;
+mod examples;
+mod export;
+mod feed;
+mod history;
+mod http;
+mod i18n;
+mod install;
+mod inventory;
+mod keywords;
+mod links;
+mod listing;
+mod logging;
+mod lsp;
+mod menu;
+mod namespaces;
+mod net;
+mod notes;
+mod open;
+mod output;
+mod platform;
+mod plugin;
+mod preview;
+mod progress;
+mod project;
+mod query;
+mod ranking;
+mod related;
+mod render;
+mod save;
+mod search;
+mod self_update;
+mod serve;
+mod slow_fs;
+mod sql;
+mod state;
+mod suggest;
+mod symbols;
+mod tags;
+mod theme;
+mod toc;
+mod types;
+mod usage;
+mod verify;
+mod versions;
+mod wizard;
+mod zeal_conf;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Cli {
+    /// Docsets directory [default: $ZEAL_CLI_DOCSET_DIR, Zeal's setting, or Zeal's default]
     #[arg(long, global = true, value_name = "DIR")]
     pub docset_dir: Option<std::path::PathBuf>,
+    /// Config file to use instead of the default one
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<std::path::PathBuf>,
+    /// Use the `docsets` directory beside the zeal-cli executable, as when
+    /// running from a USB stick; `--docset-dir` still takes precedence
+    #[arg(long, global = true)]
+    pub portable: bool,
     #[arg(long, default_value_t = false)]
     pub icons: bool,
+    /// Format of search results
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub output: output::OutputFormat,
+    /// How search output prints paths and what it links pages to
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub path_style: output::PathStyle,
+    /// Keep plain search lines whole instead of eliding the middle of paths
+    /// to fit the terminal's width
+    #[arg(long, global = true)]
+    pub no_truncate: bool,
+    /// Proxy for network operations, overriding HTTP(S)_PROXY
+    #[arg(long, global = true, value_name = "URL")]
+    pub proxy: Option<String>,
+    /// Never touch the network; commands that need it fail immediately
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Print only data rows, without notes such as "No results found"
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+    /// Log more to stderr: -v for info, -vv for debug with timings, -vvv for trace
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Log level, overriding -v and RUST_LOG
+    #[arg(long, global = true, value_enum, value_name = "LEVEL")]
+    pub log_level: Option<logging::LogLevel>,
+    /// Language of messages and name sorting, e.g. `ja` [default: $ZEAL_CLI_LANG, else the locale]
+    #[arg(long, global = true, value_name = "LANG")]
+    pub lang: Option<String>,
+    /// Log as JSON lines instead of text
+    #[arg(long, global = true)]
+    pub log_json: bool,
+    /// For docsets on a network mount: query local copies of the indexes
+    /// and keep file system lookups to a minimum
+    #[arg(long, global = true)]
+    pub slow_fs: bool,
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+#[derive(Args, Debug)]
+pub struct FeedArgs {
+    /// User-contributed feed to use instead of the default one
+    #[arg(long, value_name = "URL")]
+    pub feed_url: Option<String>,
+    /// Ignore the cached feed and fetch it again
+    #[arg(long)]
+    pub refresh: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    /// Copy a field of the top result to the clipboard
+    #[arg(long, value_enum, value_name = "FIELD")]
+    pub copy: Option<clipboard::CopyField>,
+    /// How entry names are compared with the query
+    #[arg(long, value_enum, default_value_t)]
+    pub match_mode: search::MatchMode,
+    /// Fuzzy matching algorithm; only skim's so far, nucleo's isn't
+    /// available yet
+    #[arg(long, value_enum, default_value_t)]
+    pub matcher: search::Matcher,
+    /// Which entries are scored when `index build --symbols` has indexed
+    /// the docset
+    #[arg(long, value_enum, default_value_t)]
+    pub prefilter: search::Prefilter,
+    /// Reuse results of identical earlier searches from the on-disk cache
+    #[arg(long)]
+    pub cache: bool,
+    /// Only show entries of these types, e.g. `function,class`; docset-specific
+    /// spellings such as `func` or `clm` count as their Dash type
+    #[arg(long = "type", value_name = "TYPE", value_delimiter = ',')]
+    pub types: Vec<String>,
+    /// Match entries matching any of the query terms rather than all of them
+    #[arg(long)]
+    pub any: bool,
+    /// Fields the query terms are matched against; every space separated
+    /// term must match one of them
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "name")]
+    pub fields: Vec<search::MatchField>,
+    /// Match the query against entry names, document paths or both, e.g.
+    /// `--search-in path tokio/sync/`; short for `--fields`
+    #[arg(long, value_enum, value_name = "WHERE", conflicts_with = "fields")]
+    pub search_in: Option<search::SearchIn>,
+    /// Also search the anchors pages mark their sections with, such as the
+    /// methods on a class's page, as entries of their own; every page of a
+    /// docset is read once to find them
+    #[arg(long)]
+    pub include_anchors: bool,
+    /// Show at most this many results; scoring then keeps only the best N.
+    /// 0 shows every result, overriding the config's `[limits]`
+    #[arg(long, short = 'n', value_name = "N")]
+    pub limit: Option<usize>,
+    /// Give up fetching and scoring after this many milliseconds and show
+    /// the best results found by then
+    #[arg(long, value_name = "MS")]
+    pub timeout: Option<u64>,
+    /// How results of several docsets are combined [default: from config, else global-score]
+    #[arg(long, value_enum, value_name = "STRATEGY")]
+    pub merge: Option<ranking::MergeStrategy>,
+    /// Add a column with each result's container, e.g. `Vec` for `Vec::push`
+    #[arg(long)]
+    pub container: bool,
+    /// Print these columns, aligned, in this order instead of the tab
+    /// separated fields, e.g. `name,type,anchor`
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "COLUMN")]
+    pub columns: Vec<output::Column>,
+    /// Print results in groups, headed by the shared value
+    #[arg(long, value_enum, value_name = "FIELD")]
+    pub group_by: Option<output::GroupBy>,
+    /// Only show entries on this page or below this directory of the
+    /// docset, e.g. `book/`; `*` and `**` glob within and across directories
+    #[arg(long, value_name = "PAGE", value_parser = search::Within::parse)]
+    pub within: Option<search::Within>,
+    /// Print results with this plugin from the config instead of --output
+    #[arg(long, value_name = "PLUGIN", conflicts_with = "count")]
+    pub render: Option<String>,
+    /// Open the Nth result (default 1) in the browser instead of printing results
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        conflicts_with_all = ["count", "render"]
+    )]
+    pub open: Option<usize>,
+    /// Add a column with your note on each result, see `note add`
+    #[arg(long)]
+    pub notes: bool,
+    /// Add a column with where guides and sections sit, `Book › Ownership`,
+    /// read from their pages' directories and headings
+    #[arg(long)]
+    pub breadcrumbs: bool,
+    /// Add columns with the signature, deprecation notice and first version
+    /// each result's page declares; what is read is kept per docset
+    #[arg(long)]
+    pub enrich: bool,
+    /// Print only the number of matches
+    #[arg(long)]
+    pub count: bool,
+    /// Print how many entries each stage handled and how long it took to stderr
+    #[arg(long)]
+    pub timing: bool,
+}
+
+impl FeedArgs {
+    fn url(&self) -> &str {
+        self.feed_url
+            .as_deref()
+            .unwrap_or(feed::USER_CONTRIB_FEED_URL)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct DownloadArgs {
+    /// How many times to retry a failed download, rotating through mirrors
+    #[arg(long, default_value_t = 3)]
+    pub retries: u32,
+    /// Don't show a download progress bar
+    #[arg(long)]
+    pub no_progress: bool,
+    /// Only install archives the feed publishes a checksum for, and check
+    /// the installed files against them afterwards
+    #[arg(long)]
+    pub verify: bool,
+}
+
+impl DownloadArgs {
+    fn options(&self) -> download::DownloadOptions {
+        download::DownloadOptions {
+            retries: self.retries,
+            progress: !self.no_progress,
+            verify: self.verify,
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Commands {
-    ListDocsets,
-    Search { docset: String, query: Vec<String> },
-}
-
-fn type_icon(type_: &str) -> String {
-    match type_.to_lowercase().as_str() {
-        "guide" => Colour::Green.paint("󰗚").to_string(),
-        "section" => Colour::Yellow.paint("§").to_string(),
-        "function" => Colour::Cyan.paint("ƒ").to_string(),
-        "method" => Colour::Blue.paint("m").to_string(),
-        "class" => Colour::Purple.paint("🅒").to_string(),
-        "struct" | "_struct" => Colour::Red.paint("🅢").to_string(),
-        "enum" => Colour::Purple.paint("🄴").to_string(),
-        "constant" => Colour::Blue.paint("𝑪").to_string(),
-        "property" => Colour::Yellow.paint("").to_string(),
-        "macro" => Colour::Cyan.paint("μ").to_string(),
-        "interface" => Colour::Purple.paint("🄸").to_string(),
-        "typedef" | "type" => Colour::Cyan.paint("𝙏").to_string(),
-        "attribute" => Colour::Yellow.paint("󰓹").to_string(),
-        "event" => Colour::Cyan.paint("").to_string(),
-        "variable" => Colour::Blue.paint("𝚟").to_string(),
-        "module" => Colour::Yellow.paint("󰏖").to_string(),
-        "constructor" => Colour::Red.paint("").to_string(),
-        other => other.to_string(),
+    /// List installed docsets
+    ListDocsets {
+        /// Print the docsets directories that are read instead, in priority order
+        #[arg(long)]
+        roots: bool,
+        /// Only list docsets whose name matches: a glob with `*`/`?`, else fuzzy
+        #[arg(long, value_name = "PATTERN")]
+        filter: Option<String>,
+        /// Order of the listing
+        #[arg(long, value_enum, default_value_t)]
+        sort: listing::SortKey,
+        /// Also print each docset's version (`manual` for those installed
+        /// without meta.json), entry count, index schema and path
+        #[arg(long, short = 'l')]
+        long: bool,
+        /// Scan the docsets directories again instead of trusting the
+        /// inventory kept from earlier runs
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Show an installed docset's version, feed, index and location
+    Info { docset: String },
+    /// Search docsets: `search [DOCSET] QUERY...`
+    Search {
+        /// Optional docset (a name, `@group` for every docset of a configured
+        /// group, or `keyword:query`) followed by the query. Without a docset,
+        /// the configured default docsets, those of the current project, or
+        /// else all docsets are searched. Query terms may also be operators:
+        /// `type:Method`, `^Vec` for names starting with `Vec`, or
+        /// `re:PATTERN` for names matching a regex
+        #[arg(required = true, value_name = "[DOCSET] QUERY")]
+        words: Vec<String>,
+        /// Docset to search, for queries starting with a docset's name
+        #[arg(long, short = 'd')]
+        docset: Option<String>,
+        /// How many "did you mean" suggestions to show when nothing matches
+        #[arg(long, value_name = "N", default_value_t = 3)]
+        suggest: usize,
+        #[command(flatten)]
+        args: SearchArgs,
+    },
+    /// Show a Dash cheat sheet, or its entries matching a query
+    Cheat { name: String, query: Vec<String> },
+    /// List the section anchors of the best matching page
+    Toc { docset: String, page: Vec<String> },
+    /// Print the code examples near the best match, coloured in terminals
+    Examples {
+        docset: String,
+        query: Vec<String>,
+        /// Every code block of the page, not only those of the match's section
+        #[arg(long)]
+        all: bool,
+        /// Print at most this many examples
+        #[arg(long, short = 'n', value_name = "N")]
+        limit: Option<usize>,
+    },
+    /// Show the part of a page the best match points at, drawing its images
+    /// in terminals that support kitty or iTerm2 inline images
+    Preview {
+        #[arg(required_unless_present = "path")]
+        docset: Option<String>,
+        query: Vec<String>,
+        /// Entry path from search output, e.g. for `fzf --preview`
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["docset", "query"])]
+        path: Option<PathBuf>,
+        /// How images are drawn
+        #[arg(long, value_enum, default_value_t)]
+        images: preview::ImageProtocol,
+        /// Widest an image may be drawn, in terminal columns
+        #[arg(long, value_name = "N")]
+        image_columns: Option<u32>,
+        /// How many related entries to list after the preview
+        #[arg(long, value_name = "N", default_value_t = 10)]
+        related_limit: usize,
+        /// Show the related entry listed with this number instead
+        #[arg(long, value_name = "N")]
+        related: Option<usize>,
+        /// Open the entry in the browser instead of previewing it
+        #[arg(long)]
+        open: bool,
+    },
+    /// List docsets available from the user-contributed feed
+    Available {
+        #[command(flatten)]
+        feed: FeedArgs,
+    },
+    /// Install docsets from the user-contributed feed
+    Install {
+        #[arg(required = true)]
+        names: Vec<String>,
+        #[command(flatten)]
+        feed: FeedArgs,
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+    /// Update docsets installed from the user-contributed feed
+    Update {
+        names: Vec<String>,
+        /// Only report which docsets are outdated, downloading nothing but feeds
+        #[arg(long)]
+        check: bool,
+        #[command(flatten)]
+        feed: FeedArgs,
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+    /// Check installed docsets for tampering or bit-rot: their files against
+    /// the checksums of the archive they came from, and that archive against
+    /// the feed and the copy stored for `rollback`
+    Verify {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Export the best matching page as Markdown, text or HTML
+    Export {
+        docset: String,
+        query: Vec<String>,
+        #[arg(long, value_enum, default_value_t)]
+        format: export::ExportFormat,
+        /// Export every page with a matching entry instead of only the best one
+        #[arg(long)]
+        all: bool,
+        /// Write pages into this directory instead of stdout
+        #[arg(long, value_name = "DIR")]
+        out: Option<PathBuf>,
+    },
+    /// Write a tags file of the docsets' entries, each pointing at the line
+    /// of its page it is declared on, for editors' jump-to-tag commands
+    ExportTags {
+        #[arg(required = true)]
+        docsets: Vec<String>,
+        #[arg(long, value_enum, default_value_t)]
+        format: tags::TagsFormat,
+        /// File to write instead of stdout, e.g. `tags` or `TAGS`
+        #[arg(long, short = 'o', value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Copy the best matching page, with the images, stylesheets and
+    /// scripts it loads, into a directory, e.g. to vendor it into a wiki
+    Save {
+        docset: String,
+        query: Vec<String>,
+        /// Directory to save the page in
+        #[arg(long, value_name = "DIR")]
+        to: PathBuf,
+        /// Hard link the page's files instead of copying them, where the
+        /// file system allows
+        #[arg(long)]
+        hardlink: bool,
+    },
+    /// Run a read-only SQL query against a docset's index
+    Sql {
+        docset: String,
+        /// Statement to run, e.g. "SELECT type, count(*) FROM searchIndex GROUP BY type"
+        sql: String,
+        /// Print rows as a JSON array instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Pick a result in rofi, dmenu or wofi and open it
+    ///
+    /// Without `--launcher` this runs as a rofi script mode:
+    /// `rofi -show zeal -modi "zeal:zeal-cli menu Rust"`
+    Menu {
+        docset: String,
+        query: Vec<String>,
+        /// Spawn this launcher in dmenu mode instead of acting as a rofi script
+        #[arg(long, value_enum)]
+        launcher: Option<menu::Launcher>,
+        /// Copy a field of the picked result instead of opening it
+        #[arg(long, value_enum, value_name = "FIELD")]
+        copy: Option<clipboard::CopyField>,
+        /// Let several results be picked and apply this to all of them
+        #[arg(
+            long,
+            value_enum,
+            value_name = "ACTION",
+            requires = "launcher",
+            conflicts_with = "copy"
+        )]
+        batch: Option<menu::BatchAction>,
+    },
+    /// Answer search requests from editors and other programs: JSON lines
+    /// or msgpack-RPC on stdin/stdout or a Unix socket, the Language Server
+    /// Protocol, or HTTP
+    #[command(args_conflicts_with_subcommands = true)]
+    Serve {
+        #[command(subcommand)]
+        action: Option<ServeAction>,
+        /// Listen on this Unix socket instead of stdin/stdout
+        #[arg(long, value_name = "PATH", group = "transport")]
+        socket: Option<PathBuf>,
+        /// Speak the Language Server Protocol on stdin/stdout instead, for
+        /// hover documentation and workspace symbol search in editors
+        #[arg(long, conflicts_with = "socket")]
+        lsp: bool,
+        /// Speak msgpack-RPC instead of JSON lines, e.g. for Neovim's
+        /// `vim.rpcrequest`
+        #[arg(long, conflicts_with = "lsp")]
+        msgpack: bool,
+        /// Serve JSON requests as `POST /` over HTTP instead
+        #[arg(
+            long,
+            conflicts_with_all = ["socket", "lsp", "msgpack"],
+            group = "transport"
+        )]
+        http: bool,
+        /// Address and port to listen on with --http
+        #[arg(
+            long,
+            value_name = "ADDR",
+            default_value = "127.0.0.1:7470",
+            requires = "http"
+        )]
+        bind: std::net::SocketAddr,
+        /// File holding the bearer token clients of --http must send in
+        /// `Authorization` [default: $ZEAL_CLI_TOKEN]. Required to bind an
+        /// address other hosts can reach
+        #[arg(long, value_name = "FILE", requires = "http")]
+        token_file: Option<PathBuf>,
+        /// PEM certificate chain to serve --http over TLS with
+        #[arg(long, value_name = "FILE", requires_all = ["http", "tls_key"])]
+        tls_cert: Option<PathBuf>,
+        /// PEM private key for --tls-cert
+        #[arg(long, value_name = "FILE", requires = "tls_cert")]
+        tls_key: Option<PathBuf>,
+        /// Reload docsets when they are installed, updated or removed
+        #[arg(long)]
+        watch: bool,
+        /// Gzip what is sent to --socket clients, flushing after every
+        /// response, and --http responses for clients that send
+        /// `Accept-Encoding: gzip`
+        #[arg(long, requires = "transport")]
+        gzip: bool,
+    },
+    /// Pin a docset to a previously installed version, skipping it in `update`
+    Pin { docset: String, version: String },
+    /// Let `update` upgrade a pinned docset again
+    Unpin { docset: String },
+    /// Reinstall the version installed before the current one
+    Rollback { docset: String },
+    /// Compare a docset's entries with those of another version of it:
+    /// which were added, removed or renamed
+    Diff {
+        docset: String,
+        /// The older version: a docset directory, a docset archive, or a
+        /// version kept in the archive store
+        #[arg(long, value_name = "DIR|ARCHIVE|VERSION")]
+        against: String,
+        /// Print a JSON object of `added`, `removed` and `renamed` entries
+        #[arg(long)]
+        json: bool,
+    },
+    /// Time the stages of a search to find where latency comes from
+    Bench {
+        docset: String,
+        query: Vec<String>,
+        /// Number of timed runs per match mode
+        #[arg(long, short = 'n', default_value_t = 20)]
+        iterations: usize,
+        /// Match modes to compare, comma separated
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "fuzzy")]
+        modes: Vec<search::MatchMode>,
+    },
+    /// Pack docsets into one file, to install elsewhere with `bundle import`,
+    /// such as in air-gapped CI or dev containers
+    #[command(args_conflicts_with_subcommands = true)]
+    Bundle {
+        #[command(subcommand)]
+        action: Option<BundleAction>,
+        /// Docsets to pack
+        #[arg(required = true)]
+        docsets: Vec<String>,
+        /// Bundle file to write: `.tar.gz` or `.tgz`, or `.tar.zst` to
+        /// compress it with the `zstd` program
+        #[arg(long, short = 'o', value_name = "FILE", required = true)]
+        out: Option<PathBuf>,
+    },
+    /// Inspect or empty the search result cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Show the disk space of each docset, its indexes and stored versions,
+    /// and of zeal-cli's caches
+    Du {
+        /// Empty the search result cache first
+        #[arg(long)]
+        clean_cache: bool,
+        /// Remove what aborted installs and outdated docsets left behind
+        /// first: staging directories, symbol and archive caches
+        #[arg(long)]
+        prune_orphans: bool,
+    },
+    /// Print the icon and colour `--icons` shows for each entry type, and
+    /// the raw docset types that count as it
+    Icons,
+    /// Attach personal notes to entries, shown by `search --notes`
+    Note {
+        #[command(subcommand)]
+        action: NoteAction,
+    },
+    /// List or remove the entries bookmarked with `menu --batch bookmark`
+    Bookmarks {
+        #[command(subcommand)]
+        action: Option<BookmarkAction>,
+    },
+    /// Exit 0 when the docset has an entry of this name, 1 when it doesn't,
+    /// printing nothing; for shell conditionals and docs link checks
+    Exists {
+        docset: String,
+        name: String,
+        /// Accept any fuzzy match rather than only the exact name
+        #[arg(long, conflicts_with = "ignore_case")]
+        fuzzy: bool,
+        /// Compare the name ignoring ASCII case
+        #[arg(long, short = 'i')]
+        ignore_case: bool,
+    },
+    /// Check that the `zeal://Docset/name` links, and those matching the
+    /// config's `link_patterns`, in files name entries that exist; prints
+    /// `file:line:` for each dangling one and exits 1 if there are any, 2
+    /// on errors
+    CheckLinks {
+        /// Files, or directories searched recursively
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Compare entry names ignoring ASCII case
+        #[arg(long, short = 'i')]
+        ignore_case: bool,
+    },
+    /// Rank the lines read from stdin against the query with the search
+    /// matcher and print the matching ones, best first, like `fzf --filter`;
+    /// a picker backend for lists other than docsets
+    Filter {
+        /// Space separated terms; an empty query prints every line
+        #[arg(default_value = "")]
+        query: Vec<String>,
+        /// How lines are compared with the query
+        #[arg(long, value_enum, default_value_t)]
+        match_mode: search::MatchMode,
+        /// Fuzzy matching algorithm; only skim's so far, nucleo's isn't
+        /// available yet
+        #[arg(long, value_enum, default_value_t)]
+        matcher: search::Matcher,
+        /// Keep lines matching any of the query terms rather than all of them
+        #[arg(long)]
+        any: bool,
+        /// Print at most this many lines
+        #[arg(long, short = 'n', value_name = "N")]
+        limit: Option<usize>,
+        /// Embolden the matched characters
+        #[arg(long)]
+        highlight: bool,
+    },
+    /// Print past search queries, oldest first, when `[history]` is enabled
+    History {
+        /// Only the last N queries
+        #[arg(long, short = 'n', value_name = "N")]
+        limit: Option<usize>,
+        /// Print a JSON array of `{time, query}` objects
+        #[arg(long)]
+        json: bool,
+        /// Print the history file's path instead
+        #[arg(long, conflicts_with_all = ["json", "limit"])]
+        path: bool,
+    },
+    /// Report on how zeal-cli is used
+    Stats {
+        /// The most opened docsets and entries, recorded with `track_usage`
+        #[arg(long, required = true)]
+        usage: bool,
+        /// Only count opens of the last N days
+        #[arg(long, value_name = "N")]
+        days: Option<u64>,
+        /// Show at most this many docsets and entries
+        #[arg(long, short = 'n', value_name = "N", default_value_t = 20)]
+        limit: usize,
+        #[arg(long, value_enum, default_value_t)]
+        format: usage::UsageFormat,
+    },
+    /// Build or remove auxiliary indexes that speed up searches
+    Index {
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+    /// Move settings between machines as a single JSON file
+    State {
+        #[command(subcommand)]
+        action: StateAction,
+    },
+    /// Set up zeal-cli step by step: the docsets directory, a starter
+    /// config, shell completions and whether your font has the icons
+    Init,
+    /// Print as JSON what this build supports: option values, subcommands,
+    /// server methods, features and the config file in use
+    Capabilities,
+    /// Update zeal-cli to the latest GitHub release
+    SelfUpdate {
+        /// Only report whether a newer release exists
+        #[arg(long)]
+        check_only: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BundleAction {
+    /// Install the docsets of a bundle, replacing installed ones of the same name
+    Import { file: PathBuf },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ServeAction {
+    /// Show the uptime, loaded docsets, index cache hit rate and requests in
+    /// flight of a server listening on a socket
+    Status {
+        /// The server's --socket
+        #[arg(long, value_name = "PATH")]
+        socket: PathBuf,
+    },
+    /// Stop a server listening on a socket once it has answered the
+    /// requests in flight
+    Stop {
+        /// The server's --socket
+        #[arg(long, value_name = "PATH")]
+        socket: PathBuf,
+    },
+    /// Make a server listening on a socket reread the indexes of docsets,
+    /// all of them unless some are named
+    Invalidate {
+        /// The server's --socket
+        #[arg(long, value_name = "PATH")]
+        socket: PathBuf,
+        docsets: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheAction {
+    /// Remove every cached result set
+    Clear,
+    /// Show the cache location, size and hit rate
+    Stats,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NoteAction {
+    /// Attach a note to an entry, replacing any it had
+    Add {
+        docset: String,
+        name: String,
+        text: String,
+    },
+    /// List the notes of one docset, or all
+    List { docset: Option<String> },
+    /// Change a note in $VISUAL or $EDITOR, or replace it with TEXT
+    Edit {
+        docset: String,
+        name: String,
+        text: Option<String>,
+    },
+    /// Remove the note of an entry
+    Remove { docset: String, name: String },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BookmarkAction {
+    /// Print the bookmarks, oldest first (the default)
+    List {
+        #[arg(long, value_enum, default_value_t)]
+        format: output::OutputFormat,
+    },
+    /// Remove the bookmarks of entries of this name
+    Remove {
+        name: String,
+        /// Only those of this docset
+        #[arg(long)]
+        docset: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IndexAction {
+    /// Build indexes for docsets, replacing earlier builds
+    Build {
+        #[arg(required = true)]
+        docsets: Vec<String>,
+        /// An FTS5 trigram index over entry names, used by searches with
+        /// `--match-mode substring` or `exact`, or `--prefilter trigram`,
+        /// whose terms are at least three characters long
+        #[arg(long, required = true)]
+        symbols: bool,
+    },
+    /// Remove the indexes built for docsets
+    Remove {
+        #[arg(required = true)]
+        docsets: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum StateAction {
+    /// Write groups, synonyms and the other settings, the search history,
+    /// bookmarks and notes as JSON
+    Export {
+        /// File to write instead of stdout
+        #[arg(long, short = 'o', value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Merge a file written by `state export` into the local settings
+    Import { file: PathBuf },
+}
+
+/// Glyph and colour of the entry types that have one; other types show
+/// their name in lowercase.
+const TYPE_ICONS: &[(&str, Colour, &str)] = &[
+    ("Guide", Colour::Green, "󰗚"),
+    ("Section", Colour::Yellow, "§"),
+    ("Function", Colour::Cyan, "ƒ"),
+    ("Method", Colour::Blue, "m"),
+    ("Class", Colour::Purple, "🅒"),
+    ("Struct", Colour::Red, "🅢"),
+    ("Enum", Colour::Purple, "🄴"),
+    ("Constant", Colour::Blue, "𝑪"),
+    ("Property", Colour::Yellow, ""),
+    ("Macro", Colour::Cyan, "μ"),
+    ("Interface", Colour::Purple, "🄸"),
+    ("Type", Colour::Cyan, "𝙏"),
+    ("Attribute", Colour::Yellow, "󰓹"),
+    ("Event", Colour::Cyan, ""),
+    ("Variable", Colour::Blue, "𝚟"),
+    ("Module", Colour::Yellow, "󰏖"),
+    ("Constructor", Colour::Red, ""),
+];
+
+/// Icon for an entry type. Without ANSI support the console most likely
+/// lacks the glyphs as well, so a plain letter stands in.
+fn type_icon(type_: &str, ansi: bool) -> String {
+    let type_ = types::normalize(type_);
+    if !ansi {
+        return type_
+            .chars()
+            .next()
+            .map(|c| c.to_uppercase().to_string())
+            .unwrap_or_default();
+    }
+    match TYPE_ICONS
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(type_))
+    {
+        Some((name, colour, glyph)) => theme::type_style(name, colour.normal())
+            .paint(*glyph)
+            .to_string(),
+        None => type_.to_lowercase(),
+    }
+}
+
+/// `text` with the chars at `positions` in bold.
+fn emphasize(text: &str, positions: &[usize]) -> String {
+    let bold = theme::ui_style("match", ansi_term::Style::new().bold());
+    let mut out = String::new();
+    let mut run = String::new();
+    for (i, c) in text.chars().enumerate() {
+        if positions.binary_search(&i).is_ok() {
+            run.push(c);
+            continue;
+        }
+        if !run.is_empty() {
+            out.push_str(&bold.paint(std::mem::take(&mut run)).to_string());
+        }
+        out.push(c);
+    }
+    if !run.is_empty() {
+        out.push_str(&bold.paint(run).to_string());
+    }
+    out
+}
+
+/// Prints what `--icons` shows for each Dash entry type, with the raw
+/// docset types that count as it.
+fn print_icon_legend(ansi: bool) {
+    let width = types::CANONICAL.iter().map(|t| t.len()).max().unwrap_or(0);
+    println!("Icon  {:<width$}  {:<7}  Also from", "Type", "Colour");
+    for type_ in types::CANONICAL {
+        let (icon, colour) = match TYPE_ICONS.iter().find(|(name, _, _)| name == type_) {
+            Some((_, colour, _)) => {
+                let colour = theme::type_style(type_, colour.normal()).foreground;
+                let colour = colour.map(theme::describe).unwrap_or_default();
+                (type_icon(type_, ansi), colour)
+            }
+            // Shown by name, see the note below; without ANSI every type
+            // gets its letter.
+            None if ansi => ("-".to_string(), String::new()),
+            None => (type_icon(type_, ansi), String::new()),
+        };
+        let icon = console::pad_str(&icon, 4, console::Alignment::Left, None);
+        let line = format!(
+            "{}  {:<width$}  {:<7}  {}",
+            icon,
+            type_,
+            colour,
+            types::aliases(type_).join(", ")
+        );
+        println!("{}", line.trim_end());
     }
+    println!();
+    println!(
+        "Types match ignoring case, leading underscores and a plural `s`; \
+         types without an icon, and a docset's own types, show their name."
+    );
 }
 
+/// Environment variable naming the docsets directory, below `--docset-dir`.
+const DOCSET_DIR_ENV: &str = "ZEAL_CLI_DOCSET_DIR";
+
+/// Resolves the docsets directory: `--docset-dir`, then `$ZEAL_CLI_DOCSET_DIR`,
+/// then the directory set in Zeal's preferences, then Zeal's default under the
+/// platform data directory (`$XDG_DATA_HOME/Zeal/Zeal/docsets` on Linux).
 fn zeal_docsets_dir(override_dir: &Option<std::path::PathBuf>) -> Option<std::path::PathBuf> {
     if let Some(dir) = override_dir {
         return Some(dir.clone());
     }
-    #[cfg(target_os = "linux")]
-    {
-        dirs::home_dir().map(|h| h.join(".local/share/Zeal/Zeal/docsets"))
+    if let Some(dir) = std::env::var_os(DOCSET_DIR_ENV).filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir));
     }
-    #[cfg(target_os = "macos")]
-    {
-        dirs::home_dir().map(|h| h.join("Library/Application Support/Zeal/Zeal/docsets"))
+    if let Some(dir) = zeal_conf::docsets_path() {
+        return Some(dir);
     }
-    #[cfg(target_os = "windows")]
-    {
-        dirs::data_dir().map(|d| d.join("Zeal").join("Zeal").join("docsets"))
+    let default = zeal_conf::default_docsets_dir();
+    // Zeal's settings live in the registry on Windows, which isn't read, so
+    // an install that keeps its docsets elsewhere is taken instead.
+    match default.as_ref().filter(|d| d.is_dir()) {
+        Some(_) => default,
+        None => zeal_conf::windows_docsets_dirs()
+            .into_iter()
+            .next()
+            .or(default),
     }
 }
 
+/// Every docsets directory to read from. An explicit `--docset-dir`,
+/// `--portable` or `$ZEAL_CLI_DOCSET_DIR` is used alone; otherwise the native
+/// Zeal directory is followed by those of other Zeal installs: Flatpak and
+/// Snap ones, and on Windows per-machine and portable ones.
+fn docset_roots(override_dir: &Option<PathBuf>) -> Vec<PathBuf> {
+    let explicit =
+        override_dir.is_some() || std::env::var_os(DOCSET_DIR_ENV).is_some_and(|d| !d.is_empty());
+    let mut roots: Vec<PathBuf> = zeal_docsets_dir(override_dir).into_iter().collect();
+    if !explicit {
+        let others = zeal_conf::sandboxed_docsets_dirs()
+            .into_iter()
+            .chain(zeal_conf::windows_docsets_dirs());
+        for dir in others {
+            if !roots.contains(&dir) {
+                roots.push(dir);
+            }
+        }
+    }
+    roots
+}
+
 fn list_docsets(docsets_dir: &Option<std::path::PathBuf>) -> std::io::Result<Vec<String>> {
-    if let Some(dir) = zeal_docsets_dir(docsets_dir) {
-        let entries = fs::read_dir(dir)?
-            .filter_map(|entry| {
-                entry.ok().and_then(|e| {
-                    let path = e.path();
-                    if path.is_dir() {
-                        path.file_stem()
-                            .and_then(|s| s.to_str())
-                            .map(|s| s.to_string())
-                    } else {
-                        None
-                    }
-                })
-            })
-            .collect();
-        Ok(entries)
-    } else {
-        Ok(vec![])
+    Ok(installed_docsets(docsets_dir)?
+        .iter()
+        .map(|p| search::docset_name(p))
+        .collect())
+}
+
+/// Directories of all installed docsets across the docset roots, from the
+/// inventory when the roots are unchanged since the last scan.
+fn installed_docsets(docsets_dir: &Option<std::path::PathBuf>) -> std::io::Result<Vec<PathBuf>> {
+    let roots = docset_roots(docsets_dir);
+    inventory::installed(&roots, false, || scan_docsets(&roots))
+}
+
+/// Lists the docsets in `roots`, earlier roots shadowing later ones.
+fn scan_docsets(roots: &[PathBuf]) -> std::io::Result<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = Vec::new();
+    let mut errors = Vec::new();
+    for dir in roots {
+        let paths = match fs::read_dir(dir) {
+            Ok(dir) => dir,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        }
+        .filter_map(Result::ok)
+        // On a slow mount, what the listing says instead of a stat of each.
+        .filter(|entry| !slow_fs::enabled() || slow_fs::is_dir_entry(entry))
+        .map(|entry| entry.path())
+        // Like Zeal, only `*.docset` directories, symlinked ones included;
+        // hidden ones are installs still being unpacked.
+        .filter(|path| {
+            (if slow_fs::enabled() {
+                search::has_docset_extension(path)
+            } else {
+                search::is_docset_dir(path)
+            }) && path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .is_some_and(|s| !s.starts_with('.'))
+        })
+        // Unpacked docsets shadow archived ones of the same name.
+        .chain(archive::docsets(dir));
+        // A docset in an earlier root shadows one of the same name in a later one.
+        for path in paths {
+            if !entries.iter().any(|e| e.file_name() == path.file_name()) {
+                entries.push(path);
+            }
+        }
+    }
+    // Only fail when no root could be read at all.
+    match errors.pop() {
+        Some(e) if errors.len() + 1 == roots.len() => Err(e),
+        _ => Ok(entries),
     }
 }
 
+/// The name a value of a clap enum is given as on the command line.
+fn clap_name(value: &impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
 fn check_bin(bin: &str) -> Result<(), String> {
     which::which(bin)
         .map(|_| ())
         .map_err(|_| format!("Cannot find binary `{}`", bin))
 }
 
-fn search_docset(
-    docset_path: &Path,
+fn search_docsets(
+    docset_paths: &[PathBuf],
     query: &str,
-    icons: bool,
+    config: &config::Config,
+    args: &SearchArgs,
+    format: output::OutputFormat,
+    display: &output::OutputOptions,
 ) -> Result<usize, Box<dyn std::error::Error>> {
-    let db_path = docset_path.join("Contents/Resources/docSet.dsidx");
-    let docs_dir = docset_path.join("Contents/Resources/Documents");
-    let conn = Connection::open(&db_path)?;
+    let mut timing = bench::Timing {
+        docsets: docset_paths.len(),
+        ..Default::default()
+    };
+    let parsed = query::parse(query)?;
+    let query = parsed.text.as_str();
+    // `--count` counts every match, whatever the format's configured limit.
+    let limit = match args.limit {
+        Some(0) => None,
+        Some(limit) => Some(limit),
+        None if args.count => None,
+        None => config.limit_for(&clap_name(&format)),
+    };
+    // One budget for the whole search, however many docsets it spans.
+    let budget = args
+        .timeout
+        .map(|ms| search::Budget::new(std::time::Duration::from_millis(ms)));
+    let mut per_docset = Vec::new();
+    for docset_path in docset_paths {
+        tracing::info!(docset = %docset_path.display(), query, "searching");
+        let opts = search::SearchOptions {
+            synonyms: config.expand_query(&search::docset_name(docset_path), query),
+            mode: args.match_mode,
+            matcher: args.matcher,
+            prefilter: args.prefilter,
+            types: args.types.iter().chain(&parsed.types).cloned().collect(),
+            limit: plugin::scoring_limit(&config.plugins, limit),
+            fields: args
+                .search_in
+                .map_or_else(|| args.fields.clone(), search::SearchIn::fields),
+            ignore: config.ignore_for(&search::docset_name(docset_path)),
+            within: args.within.clone(),
+            any: args.any,
+            budget: budget.clone(),
+            filters: parsed.filters.clone(),
+            boosts: config.ranking.boosts,
+            anchors: args.include_anchors,
+        };
+        let cached = args
+            .cache
+            .then(|| cache::get(docset_path, query, &opts))
+            .flatten();
+        let found = match cached {
+            Some(found) => {
+                timing.cached += 1;
+                found
+            }
+            None => {
+                let start = Instant::now();
+                let entries = if opts.anchors {
+                    // Anchors are found from every page of the index, so
+                    // it is read whole, once, for both.
+                    let mut entries =
+                        search::load_entries_until(docset_path, opts.budget.as_deref())?;
+                    let anchors = anchors::entries(docset_path, &entries, opts.budget.as_deref());
+                    entries.extend(anchors);
+                    entries
+                } else {
+                    symbols::candidates(docset_path, query, &opts)?
+                };
+                timing.fetch += start.elapsed();
+                timing.fetched += entries.len();
+                if args.timing {
+                    timing.scored += entries.iter().filter(|e| opts.accepts(e)).count();
+                }
+                let start = Instant::now();
+                let mut found = search::match_entries(&entries, query, &opts);
+                timing.score += start.elapsed();
+                let docset = search::docset_name(docset_path);
+                for m in &mut found {
+                    m.docset.clone_from(&docset);
+                }
+                if args.cache && !budget.as_ref().is_some_and(|b| b.truncated()) {
+                    cache::put(docset_path, query, &opts, &found);
+                }
+                found
+            }
+        };
+        timing.matched += found.len();
+        per_docset.push((search::docset_name(docset_path), found));
+    }
+    let start = Instant::now();
+    let matches = if per_docset.len() > 1 {
+        let strategy = args.merge.unwrap_or(config.ranking.strategy);
+        ranking::merge(per_docset, query, strategy, &config.ranking)
+    } else {
+        per_docset.pop().map(|(_, found)| found).unwrap_or_default()
+    };
+    let mut matches = plugin::transform(&config.plugins, query, matches, limit)?;
+    if args.notes || display.columns.contains(&output::Column::Note) {
+        notes::annotate(&mut matches)?;
+    }
+    if args.breadcrumbs || display.columns.contains(&output::Column::Breadcrumbs) {
+        breadcrumbs::annotate(&mut matches);
+    }
+    if args.enrich || display.columns.iter().any(|c| c.is_enriched()) {
+        enrich::annotate(&mut matches);
+    }
+    timing.merge = start.elapsed();
+    let truncated = budget.is_some_and(|b| b.truncated());
+    if truncated {
+        eprintln!(
+            "Search stopped after {} ms; results are the best found by then",
+            args.timeout.unwrap_or_default()
+        );
+    }
+    let display = &output::OutputOptions {
+        truncated,
+        ..display.clone()
+    };
+    let start = Instant::now();
+    if let Some(n) = args.open.filter(|_| !matches.is_empty()) {
+        let picked = n
+            .checked_sub(1)
+            .and_then(|i| matches.get(i))
+            .ok_or_else(|| format!("No result number {}; only {} matched", n, matches.len()))?;
+        open::open_entry(&picked.path, Some(&picked.name))?;
+    } else if args.count {
+        println!("{}", matches.len());
+    } else if let Some(name) = &args.render {
+        plugin::render(&config.plugins, name, query, &matches)?;
+    } else {
+        output::print_results(&matches, format, display);
+    }
+    timing.output = start.elapsed();
+    timing.shown = matches.len();
+    if args.timing {
+        eprintln!("{}", timing.summary());
+    }
+    if let (Some(field), Some(top)) = (args.copy, matches.first()) {
+        clipboard::copy(field, &top.name, &top.path)?;
+    }
+    Ok(matches.len())
+}
 
-    let mut stmt = conn.prepare("SELECT name, type, path FROM searchIndex")?;
-    let mut rows = stmt.query([])?;
+/// The docset and entry names a note is filed under: the docset's name on
+/// disk and the entry's name as indexed, matched ignoring case if need be.
+fn note_target(
+    docset_dir: &Option<PathBuf>,
+    docset: &str,
+    name: &str,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let docset_path = resolve_docset(docset_dir, docset);
+    let entries = search::load_entries(&docset_path)?;
+    let entry = entries
+        .iter()
+        .find(|e| e.name == name)
+        .or_else(|| entries.iter().find(|e| e.name.eq_ignore_ascii_case(name)))
+        .ok_or_else(|| format!("No entry named '{}' in '{}'", name, docset))?;
+    Ok((notes::docset_key(&docset_path), entry.name.clone()))
+}
 
-    let matcher = SkimMatcherV2::default();
-    let mut matches = Vec::new();
+/// How long `serve stop` waits for the server to finish.
+const STOP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
 
-    while let Some(row) = rows.next()? {
-        let name: String = row.get(0)?;
-        let typ: String = row.get(1)?;
-        let path: String = row.get(2)?;
-        let html_path = docs_dir.join(&path);
+fn run_serve_action(action: &ServeAction) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        ServeAction::Status { socket } => {
+            let status = serve::request(socket, "status", serde_json::Value::Null)?;
+            let count = |key: &str| status["index_cache"][key].as_u64().unwrap_or_default();
+            let (hits, lookups) = (count("hits"), count("hits") + count("misses"));
+            let secs = status["uptime_secs"].as_u64().unwrap_or_default();
+            let docsets: Vec<&str> = status["docsets"]
+                .as_array()
+                .map(|d| d.iter().filter_map(|d| d.as_str()).collect())
+                .unwrap_or_default();
+            println!("Socket:    {}", socket.display());
+            println!("PID:       {}", status["pid"]);
+            println!("Version:   {}", status["version"].as_str().unwrap_or("?"));
+            println!(
+                "Uptime:    {}h {:02}m {:02}s",
+                secs / 3600,
+                secs / 60 % 60,
+                secs % 60
+            );
+            println!("Watching:  {}", status["watching"]);
+            if docsets.is_empty() {
+                println!("Docsets:   none loaded");
+            } else {
+                println!("Docsets:   {}", docsets.join(", "));
+            }
+            println!(
+                "Hits:      {} of {} index lookups ({:.0}%)",
+                hits,
+                lookups,
+                if lookups == 0 {
+                    0.0
+                } else {
+                    hits as f64 * 100.0 / lookups as f64
+                }
+            );
+            println!("In flight: {}", status["in_flight"]);
+            for query in status["queries"].as_array().into_iter().flatten() {
+                println!(
+                    "  {} {} '{}' for {} ms",
+                    query["method"].as_str().unwrap_or("?"),
+                    query["docset"].as_str().unwrap_or("?"),
+                    query["query"].as_str().unwrap_or_default(),
+                    query["elapsed_ms"]
+                );
+            }
+            Ok(())
+        }
+        ServeAction::Stop { socket } => {
+            let reply = serve::request(socket, "shutdown", serde_json::Value::Null)?;
+            eprintln!(
+                "Stopping, {} requests in flight",
+                reply["in_flight"].as_u64().unwrap_or_default()
+            );
+            let started = std::time::Instant::now();
+            while socket.exists() {
+                if started.elapsed() > STOP_TIMEOUT {
+                    return Err(format!(
+                        "The server on {} is still answering requests",
+                        socket.display()
+                    )
+                    .into());
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            eprintln!("Stopped");
+            Ok(())
+        }
+        ServeAction::Invalidate { socket, docsets } => {
+            let params = serde_json::json!({ "docsets": docsets });
+            let dropped = serve::request(socket, "invalidate", params)?;
+            for name in dropped.as_array().into_iter().flatten() {
+                println!("{}", name.as_str().unwrap_or_default());
+            }
+            Ok(())
+        }
+    }
+}
 
-        if query.is_empty() {
-            // List all if no query
-            matches.push((0, name, typ, html_path));
-        } else if let Some(score) = matcher.fuzzy_match(&name, query) {
-            matches.push((score, name, typ, html_path));
+fn run_note(
+    docset_dir: &Option<PathBuf>,
+    action: &NoteAction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        NoteAction::Add { docset, name, text } => {
+            let (docset, name) = note_target(docset_dir, docset, name)?;
+            notes::set(&docset, &name, text)
+        }
+        NoteAction::List { docset } => {
+            let docset = docset
+                .as_deref()
+                .map(|d| search::docset_name(&resolve_docset(docset_dir, d)));
+            for note in notes::list(docset.as_deref())? {
+                let text = notes::one_line(&note.text);
+                println!("{}\t{}\t{}", note.docset, note.name, text);
+            }
+            Ok(())
+        }
+        NoteAction::Edit { docset, name, text } => {
+            let (docset, name) = note_target(docset_dir, docset, name)?;
+            let text = match text {
+                Some(text) => text.clone(),
+                None => notes::edit(&notes::get(&docset, &name)?.unwrap_or_default())?,
+            };
+            if text.trim().is_empty() {
+                notes::remove(&docset, &name)?;
+                return Ok(());
+            }
+            notes::set(&docset, &name, &text)
+        }
+        NoteAction::Remove { docset, name } => {
+            let docset = notes::docset_key(&resolve_docset(docset_dir, docset));
+            if !notes::remove(&docset, name)? {
+                return Err(format!("No note on '{}' in '{}'", name, docset).into());
+            }
+            Ok(())
         }
     }
+}
 
-    if query.is_empty() {
-        matches.sort_by(|a, b| a.1.cmp(&b.1));
-    } else {
-        matches.sort_by(|a, b| b.0.cmp(&a.0));
+/// The installed docset `name` refers to in any of the docsets
+/// directories, exiting when it is ambiguous.
+fn find_installed(roots: &[PathBuf], name: &str) -> Option<PathBuf> {
+    roots.iter().find_map(|root| {
+        install::find_installed(root, name).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        })
+    })
+}
+
+/// The installed docset `name` refers to, exiting when it is ambiguous.
+fn find_named<'a>(installed: &'a [PathBuf], name: &str) -> Option<&'a PathBuf> {
+    search::find_named(installed, name).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+fn resolve_docset(docset_dir: &Option<PathBuf>, docset: &str) -> PathBuf {
+    let roots = docset_roots(docset_dir);
+    match find_installed(&roots, docset) {
+        Some(docset_path) => docset_path,
+        None => {
+            let searched: Vec<String> = roots.iter().map(|r| r.display().to_string()).collect();
+            let dirs = searched.join(", ");
+            eprintln!(
+                "{}",
+                i18n::message(
+                    "docset-not-found",
+                    &[("docset", docset.into()), ("dirs", dirs.into())]
+                )
+            );
+            std::process::exit(1);
+        }
     }
+}
 
-    for (_, name, typ, html_path) in &matches {
-        if icons {
-            println!(
-                "{}\t{}\t{}\t{}",
-                type_icon(&typ),
-                name,
-                typ,
-                html_path.display()
+/// Resolves a docset argument, expanding `@group` through the config.
+fn resolve_docsets(
+    docset_dir: &Option<PathBuf>,
+    config: &config::Config,
+    docset: &str,
+) -> Vec<PathBuf> {
+    let Some(group) = docset.strip_prefix('@') else {
+        return vec![resolve_docset(docset_dir, docset)];
+    };
+    match config.groups.get(group) {
+        Some(members) => members
+            .iter()
+            .map(|d| resolve_docset(docset_dir, d))
+            .collect(),
+        None => {
+            eprintln!(
+                "Unknown docset group '@{}'; define it under [groups] in the config file",
+                group
             );
-        } else {
-            println!("\t{}\t{}\t{}", name, typ, html_path.display());
+            std::process::exit(1);
         }
     }
+}
 
-    Ok(matches.len())
+/// Splits the words of `search` into the docsets to search and the query.
+///
+/// Without `--docset`, a first word naming an installed docset, a `@group`
+/// or a Zeal-style `keyword:query` selects the docsets; otherwise all words
+/// are the query and the default docsets are searched.
+fn resolve_search(
+    docset_dir: &Option<PathBuf>,
+    config: &config::Config,
+    docset: Option<&str>,
+    words: &[String],
+) -> (Vec<PathBuf>, String) {
+    if let Some(docset) = docset {
+        return (resolve_docsets(docset_dir, config, docset), words.join(" "));
+    }
+    let Some((first, rest)) = words.split_first() else {
+        return (Vec::new(), String::new());
+    };
+    if first.starts_with('@') {
+        return (resolve_docsets(docset_dir, config, first), rest.join(" "));
+    }
+    let installed = installed_docsets(docset_dir).unwrap_or_default();
+    if let Some((keywords, attached)) = keywords::split(first) {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        for keyword in keywords {
+            for path in keywords::resolve(keyword, config, &installed) {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        if !paths.is_empty() {
+            let query = std::iter::once(attached)
+                .chain(rest.iter().map(String::as_str))
+                .filter(|q| !q.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            return (paths, query);
+        }
+    }
+    if let Some(path) = find_named(&installed, first) {
+        return (vec![path.clone()], rest.join(" "));
+    }
+    (default_docsets(config, &installed), words.join(" "))
+}
+
+/// Docsets searched when none is named: `default_docsets` from the config,
+/// else those matching the project in the working directory, else the one
+/// picked on the terminal, else all.
+fn default_docsets(config: &config::Config, installed: &[PathBuf]) -> Vec<PathBuf> {
+    if !config.default_docsets.is_empty() {
+        return config
+            .default_docsets
+            .iter()
+            .filter_map(|name| {
+                let found = find_named(installed, name);
+                if found.is_none() {
+                    let args = [("docset", name.as_str().into())];
+                    eprintln!("{}", i18n::message("default-docset-missing", &args));
+                }
+                found.cloned()
+            })
+            .collect();
+    }
+    let detected = std::env::current_dir()
+        .map(|dir| project::detect(&dir, installed))
+        .unwrap_or_default();
+    if !detected.is_empty() {
+        return detected;
+    }
+    let mut all = installed.to_vec();
+    config.ranking.sort(&mut all);
+    chooser::choose(&all).unwrap_or(all)
+}
+
+/// `docsets` beside the zeal-cli executable, for `--portable`.
+fn portable_docsets_dir() -> std::io::Result<PathBuf> {
+    let exe = std::env::current_exe()?;
+    let dir = exe.parent().ok_or_else(|| {
+        std::io::Error::other(format!("{} has no parent directory", exe.display()))
+    })?;
+    Ok(dir.join("docsets"))
+}
+
+/// Parses the command line, namespaced spellings such as `docset install`
+/// included.
+fn parse_cli() -> Cli {
+    let args: Vec<_> = std::env::args_os().collect();
+    let matches = namespaces::command(Cli::command()).get_matches_from(&args);
+    let args = namespaces::flatten(args, &matches);
+    Cli::from_arg_matches(&Cli::command().get_matches_from(args)).unwrap_or_else(|e| e.exit())
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let mut cli = parse_cli();
+    if cli.portable && cli.docset_dir.is_none() {
+        match portable_docsets_dir() {
+            Ok(dir) => cli.docset_dir = Some(dir),
+            Err(e) => {
+                eprintln!("Error locating the portable docsets directory: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    logging::init(cli.verbose, cli.log_level, cli.log_json);
+    i18n::init(cli.lang.as_deref());
+    let ansi = platform::enable_ansi();
 
-    check_bin("zeal").unwrap_or_else(|e| eprintln!("{}", e));
+    if !cli.quiet {
+        check_bin("zeal").unwrap_or_else(|e| eprintln!("{}", e));
+    }
+    let config = config::load(cli.config.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Error reading config: {}", e);
+        std::process::exit(1);
+    });
+    if config.track_usage {
+        usage::enable();
+    }
+    if let Err(e) = theme::init(&config.colors) {
+        eprintln!("Error in [colors] of the config: {}", e);
+        std::process::exit(1);
+    }
+    if cli.slow_fs || config.slow_fs {
+        slow_fs::enable();
+    }
+    let net = net::Net {
+        proxy: cli.proxy.clone(),
+        offline: cli.offline,
+    };
 
     match &cli.command {
-        Some(Commands::ListDocsets) => match list_docsets(&cli.docset_dir) {
-            Ok(docsets) if !docsets.is_empty() => {
-                for d in docsets {
-                    println!("{}", d);
+        Some(Commands::ListDocsets { roots: true, .. }) => {
+            for root in docset_roots(&cli.docset_dir) {
+                println!("{}", root.display());
+            }
+        }
+        Some(Commands::ListDocsets {
+            roots: false,
+            filter,
+            sort,
+            long,
+            refresh,
+        }) => {
+            let roots = docset_roots(&cli.docset_dir);
+            match inventory::installed(&roots, *refresh, || scan_docsets(&roots)) {
+                Ok(mut docsets) if !docsets.is_empty() => {
+                    if let Some(pattern) = filter {
+                        docsets = listing::filter(docsets, pattern);
+                    }
+                    listing::sort(&mut docsets, *sort, &config.ranking);
+                    if *long {
+                        let details = inventory::details(&docsets);
+                        for (d, details) in docsets.iter().zip(details) {
+                            println!(
+                                "{}\t{}\t{}\t{}\t{}",
+                                search::docset_name(d),
+                                install::installed_version(d).as_deref().unwrap_or("manual"),
+                                details.entries,
+                                details.schema,
+                                d.display()
+                            );
+                        }
+                    } else {
+                        for d in docsets {
+                            println!("{}", search::docset_name(&d));
+                        }
+                    }
                 }
+                Ok(_) if !cli.quiet => eprintln!("{}", i18n::message("no-docsets", &[])),
+                Ok(_) => {}
+                Err(e) => eprintln!("Error listing docsets: {}", e),
             }
-            Ok(_) => println!("No docsets found."),
-            Err(e) => eprintln!("Error listing docsets: {}", e),
-        },
-        Some(Commands::Search { docset, query }) => {
-            let base = zeal_docsets_dir(&cli.docset_dir).expect("Docsets directory not found");
-            let docset_path = base.join(format!("{}.docset", docset));
-            if !docset_path.exists() {
-                eprintln!("Docset '{}' not found at {:?}", docset, docset_path);
+        }
+        Some(Commands::Info { docset }) => {
+            let docset_path = resolve_docset(&cli.docset_dir, docset);
+            listing::info(&docset_path);
+        }
+        Some(Commands::Search {
+            words,
+            docset,
+            suggest,
+            args,
+        }) => {
+            let typed = docset.iter().chain(words).cloned().collect::<Vec<_>>();
+            config.history.record(&typed.join(" "));
+            let (docset_paths, query) =
+                resolve_search(&cli.docset_dir, &config, docset.as_deref(), words);
+            let label = match docset_paths.as_slice() {
+                [] => {
+                    eprintln!("{}", i18n::message("no-docsets-to-search", &[]));
+                    std::process::exit(1);
+                }
+                [one] => {
+                    let args = [("docset", search::docset_name(one).into())];
+                    i18n::message("search-label-one", &args)
+                }
+                many => i18n::message("search-label-many", &[("count", many.len().into())]),
+            };
+            let display = output::OutputOptions {
+                icons: cli.icons,
+                docsets: docset_paths.len() > 1,
+                path_style: cli.path_style,
+                container: args.container,
+                group_by: args.group_by,
+                notes: args.notes,
+                breadcrumbs: args.breadcrumbs,
+                enrich: args.enrich,
+                ansi,
+                width: if cli.no_truncate {
+                    None
+                } else {
+                    output::terminal_width()
+                },
+                truncated: false,
+                columns: args.columns.clone(),
+            };
+            match search_docsets(&docset_paths, &query, &config, args, cli.output, &display) {
+                Ok(0) if cli.output == output::OutputFormat::Plain && !cli.quiet && !args.count => {
+                    let args = [
+                        ("query", query.as_str().into()),
+                        ("label", label.as_str().into()),
+                    ];
+                    eprintln!("{}", i18n::message("no-results", &args));
+                    if *suggest > 0 && !query.is_empty() {
+                        match suggest::suggestions(&docset_paths, &query, *suggest) {
+                            Ok(names) if !names.is_empty() => {
+                                let args = [("names", names.join(", ").into())];
+                                eprintln!("{}", i18n::message("did-you-mean", &args))
+                            }
+                            Ok(_) => {}
+                            Err(e) => eprintln!("Error computing suggestions: {}", e),
+                        }
+                    }
+                }
+                Ok(_) => {} // results already printed line-by-line
+                Err(e) => {
+                    let args = [
+                        ("label", label.as_str().into()),
+                        ("error", e.to_string().into()),
+                    ];
+                    eprintln!("{}", i18n::message("search-error", &args));
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Cheat { name, query }) => {
+            let installed = installed_docsets(&cli.docset_dir).unwrap_or_default();
+            let cheatsheets: Vec<&PathBuf> = installed
+                .iter()
+                .filter(|p| cheat::is_cheatsheet(p))
+                .collect();
+            let Some(docset_path) = cheatsheets
+                .iter()
+                .find(|p| search::is_named(p, name))
+                .or_else(|| {
+                    cheatsheets.iter().find(|p| {
+                        keywords::docset_keywords(p)
+                            .iter()
+                            .any(|k| k.eq_ignore_ascii_case(name))
+                    })
+                })
+            else {
+                eprintln!(
+                    "{}",
+                    i18n::message("cheat-not-found", &[("name", name.as_str().into())])
+                );
+                std::process::exit(1);
+            };
+            let query = query.join(" ");
+            match cheat::show(docset_path, &query) {
+                Ok(0) if !cli.quiet => {
+                    let args = [
+                        ("query", query.as_str().into()),
+                        ("name", name.as_str().into()),
+                    ];
+                    eprintln!("{}", i18n::message("cheat-no-entries", &args))
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Error reading cheat sheet '{}': {}", name, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Preview {
+            docset,
+            query,
+            path,
+            images,
+            image_columns,
+            related_limit,
+            related,
+            open,
+        }) => {
+            let (mut entry, docset_path, name) = match (path, docset) {
+                (Some(path), _) => (path.clone(), related::docset_of(path), None),
+                (None, Some(docset)) => {
+                    let docset_path = resolve_docset(&cli.docset_dir, docset);
+                    let query = query.join(" ");
+                    match search::find_matches(&docset_path, &query, &Default::default()) {
+                        Ok(mut matches) if !matches.is_empty() => {
+                            let top = matches.swap_remove(0);
+                            (top.path, Some(docset_path), Some(top.name))
+                        }
+                        Ok(_) => {
+                            if !cli.quiet {
+                                let args = [
+                                    ("query", query.as_str().into()),
+                                    ("docset", docset.as_str().into()),
+                                ];
+                                eprintln!("{}", i18n::message("no-entry", &args));
+                            }
+                            std::process::exit(1);
+                        }
+                        Err(e) => {
+                            eprintln!("Error searching docset '{}': {}", docset, e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                (None, None) => unreachable!("clap requires a docset or --path"),
+            };
+            let entries = match docset_path.as_deref().map(search::load_entries) {
+                Some(Ok(entries)) => entries,
+                Some(Err(e)) => {
+                    eprintln!("Error reading index: {}", e);
+                    Vec::new()
+                }
+                None => Vec::new(),
+            };
+            let mut name = name.unwrap_or_else(|| {
+                let found = entries.iter().find(|e| e.path == entry);
+                found.map(|e| e.name.clone()).unwrap_or_default()
+            });
+            if let Some(n) = related {
+                let listed = related::related(&entries, &name, &entry, *n);
+                let Some(picked) = n.checked_sub(1).and_then(|i| listed.get(i)) else {
+                    let args = [("number", (*n).into()), ("name", name.as_str().into())];
+                    eprintln!("{}", i18n::message("no-related-entry", &args));
+                    std::process::exit(1);
+                };
+                (entry, name) = (picked.path.clone(), picked.name.clone());
+            }
+            let result = if *open {
+                open::open_entry(&entry, Some(&name))
+            } else {
+                preview::show(&entry, *images, *image_columns)
+            };
+            if let Err(e) = result {
+                eprintln!("Error showing {}: {}", entry.display(), e);
                 std::process::exit(1);
             }
+            let related = related::related(&entries, &name, &entry, *related_limit);
+            if !*open && !related.is_empty() {
+                if !cli.quiet {
+                    println!("\n{}", i18n::message("related-header", &[]));
+                }
+                for (i, e) in related.iter().enumerate() {
+                    println!("{}\t{}\t{}\t{}", i + 1, e.name, e.type_, e.path.display());
+                }
+            }
+        }
+        Some(Commands::Examples {
+            docset,
+            query,
+            all,
+            limit,
+        }) => {
+            let docset_path = resolve_docset(&cli.docset_dir, docset);
             let query = query.join(" ");
-            match search_docset(&docset_path, &query, cli.icons) {
-                Ok(0) => println!("No results found for '{}' in docset '{}'", query, docset),
-                Ok(_) => {} // results already printed line-by-line
+            let top = match search::find_matches(&docset_path, &query, &Default::default()) {
+                Ok(matches) => matches.into_iter().next(),
                 Err(e) => {
                     eprintln!("Error searching docset '{}': {}", docset, e);
                     std::process::exit(1);
                 }
+            };
+            let Some(top) = top else {
+                if !cli.quiet {
+                    let args = [
+                        ("query", query.as_str().into()),
+                        ("docset", docset.as_str().into()),
+                    ];
+                    eprintln!("{}", i18n::message("no-entry", &args));
+                }
+                std::process::exit(1);
+            };
+            let examples = match examples::near(&top.path, *all) {
+                Ok(examples) => examples,
+                Err(e) => {
+                    eprintln!("Error reading page {:?}: {}", top.path, e);
+                    std::process::exit(1);
+                }
+            };
+            if examples.is_empty() {
+                if !cli.quiet {
+                    eprintln!("No code examples near {}", top.name);
+                }
+                std::process::exit(1);
+            }
+            // Headers and colours only for reading; piped output is just code.
+            let terminal = std::io::IsTerminal::is_terminal(&std::io::stdout());
+            for (i, example) in examples
+                .iter()
+                .take(limit.unwrap_or(usize::MAX))
+                .enumerate()
+            {
+                if terminal {
+                    let label = match &example.language {
+                        Some(language) => format!("── {} ({}) ──", i + 1, language),
+                        None => format!("── {} ──", i + 1),
+                    };
+                    if ansi {
+                        let dimmed = ansi_term::Style::new().dimmed();
+                        println!("{}", theme::ui_style("label", dimmed).paint(label));
+                        println!("{}", example.highlighted);
+                    } else {
+                        println!("{}", label);
+                        println!("{}", example.code);
+                    }
+                } else {
+                    if i > 0 {
+                        println!();
+                    }
+                    println!("{}", example.code);
+                }
+            }
+        }
+        Some(Commands::Toc { docset, page }) => {
+            let docset_path = resolve_docset(&cli.docset_dir, docset);
+            let page = page.join(" ");
+            let top = match search::find_matches(&docset_path, &page, &Default::default()) {
+                Ok(matches) => matches.into_iter().next(),
+                Err(e) => {
+                    eprintln!("Error searching docset '{}': {}", docset, e);
+                    std::process::exit(1);
+                }
+            };
+            let Some(top) = top else {
+                if !cli.quiet {
+                    let args = [
+                        ("page", page.as_str().into()),
+                        ("docset", docset.as_str().into()),
+                    ];
+                    eprintln!("{}", i18n::message("no-page", &args));
+                }
+                std::process::exit(1);
+            };
+            let page_path = toc::strip_fragment(&top.path);
+            match toc::page_toc(&page_path) {
+                Ok(entries) if !entries.is_empty() => {
+                    for e in entries {
+                        println!(
+                            "{}{}\t{}\t{}#{}",
+                            "  ".repeat(e.depth),
+                            e.name,
+                            e.type_,
+                            page_path.display(),
+                            e.anchor
+                        );
+                    }
+                }
+                Ok(_) if !cli.quiet => {
+                    let args = [("page", page_path.display().to_string().into())];
+                    eprintln!("{}", i18n::message("no-sections", &args))
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Error reading page {:?}: {}", page_path, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Available { feed }) => {
+            let installed = list_docsets(&cli.docset_dir).unwrap_or_default();
+            match feed::load_user_contrib(&net, feed.url(), feed.refresh) {
+                Ok(docsets) => {
+                    for d in docsets {
+                        let marker = if installed.contains(&d.name) {
+                            "*"
+                        } else {
+                            " "
+                        };
+                        println!("{}\t{}\t{}\t{}", marker, d.name, d.title, d.version);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error loading feed {}: {}", feed.url(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Update {
+            names,
+            check: true,
+            feed,
+            ..
+        }) => {
+            let base = zeal_docsets_dir(&cli.docset_dir).expect("Docsets directory not found");
+            let result = net
+                .ensure_online()
+                .and_then(|_| install::check(names, feed.url(), &base, &net, feed.refresh));
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Install {
+            names,
+            feed,
+            download,
+        })
+        | Some(Commands::Update {
+            names,
+            feed,
+            download,
+            ..
+        }) => {
+            let base = zeal_docsets_dir(&cli.docset_dir).expect("Docsets directory not found");
+            let result = net
+                .ensure_online()
+                .and_then(|_| feed::load_user_contrib(&net, feed.url(), feed.refresh))
+                .and_then(|docsets| {
+                    if matches!(cli.command, Some(Commands::Install { .. })) {
+                        install::install(
+                            names,
+                            &docsets,
+                            feed.url(),
+                            &base,
+                            &download.options(),
+                            &net,
+                        )
+                    } else {
+                        install::update(
+                            names,
+                            &docsets,
+                            feed.url(),
+                            &base,
+                            &download.options(),
+                            &net,
+                        )
+                    }
+                });
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Verify { names }) => {
+            let mut feeds: HashMap<String, Option<Vec<feed::FeedDocset>>> = HashMap::new();
+            let mut failed = false;
+            for name in names {
+                let docset_path = resolve_docset(&cli.docset_dir, name);
+                // The feed is only compared when it can be had; files are
+                // checked offline.
+                let feed = install::read_meta(&docset_path)
+                    .and_then(|meta| meta.feed_url)
+                    .and_then(|url| {
+                        feeds
+                            .entry(url.clone())
+                            .or_insert_with(|| {
+                                feed::load_user_contrib(&net, &url, false)
+                                    .inspect_err(|e| {
+                                        tracing::warn!("Could not load feed {}: {}", url, e)
+                                    })
+                                    .ok()
+                            })
+                            .clone()
+                    });
+                match verify::verify(&docset_path, feed.as_deref()) {
+                    Ok(report) => {
+                        failed |= !report.is_clean();
+                        report.print(&search::docset_name(&docset_path));
+                    }
+                    Err(e) => {
+                        eprintln!("Error verifying docset '{}': {}", name, e);
+                        failed = true;
+                    }
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Export {
+            docset,
+            query,
+            format,
+            all,
+            out,
+        }) => {
+            let docset_path = resolve_docset(&cli.docset_dir, docset);
+            let query = query.join(" ");
+            let result = search::find_matches(&docset_path, &query, &Default::default()).and_then(
+                |matches| {
+                    let mut pages = export::pages(&matches);
+                    if !*all {
+                        pages.truncate(1);
+                    }
+                    if pages.is_empty() {
+                        return Err(format!("No page found for '{}'", query).into());
+                    }
+                    export::export(&docset_path, &pages, *format, out.as_deref())
+                },
+            );
+            if let Err(e) = result {
+                eprintln!("Error exporting from docset '{}': {}", docset, e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::ExportTags {
+            docsets,
+            format,
+            out,
+        }) => {
+            let mut found = Vec::new();
+            for docset in docsets {
+                for docset_path in resolve_docsets(&cli.docset_dir, &config, docset) {
+                    match tags::tags(&docset_path) {
+                        Ok(tags) => found.extend(tags),
+                        Err(e) => {
+                            eprintln!("Error reading docset '{}': {}", docset, e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+            }
+            let result = match out {
+                Some(path) => fs::File::create(path).and_then(|file| {
+                    let mut writer = std::io::BufWriter::new(file);
+                    tags::write(&mut writer, &mut found, *format)
+                }),
+                None => tags::write(&mut std::io::stdout().lock(), &mut found, *format),
+            };
+            if let Err(e) = result {
+                eprintln!("Error writing tags: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Save {
+            docset,
+            query,
+            to,
+            hardlink,
+        }) => {
+            let docset_path = resolve_docset(&cli.docset_dir, docset);
+            let query = query.join(" ");
+            let result = search::find_matches(&docset_path, &query, &Default::default()).and_then(
+                |matches| {
+                    let top = matches
+                        .iter()
+                        .find(|m| !export::pages(std::slice::from_ref(m)).is_empty())
+                        .ok_or_else(|| format!("No page found for '{}'", query))?;
+                    save::save(&top.path, to, *hardlink)
+                },
+            );
+            match result {
+                Ok(page) => println!("{}", page.display()),
+                Err(e) => {
+                    eprintln!("Error saving from docset '{}': {}", docset, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Sql { docset, sql, json }) => {
+            let docset_path = resolve_docset(&cli.docset_dir, docset);
+            if let Err(e) = sql::run(&docset_path, sql, *json) {
+                eprintln!("Error running SQL on docset '{}': {}", docset, e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Menu {
+            docset,
+            query,
+            launcher,
+            copy,
+            batch,
+        }) => {
+            let result = if launcher.is_none() && menu::rofi_selected() {
+                menu::rofi_open_selection(query.last().map(String::as_str), *copy)
+            } else {
+                let docset_path = resolve_docset(&cli.docset_dir, docset);
+                let opts = search::SearchOptions {
+                    limit: config
+                        .limit_for(&launcher.map_or("rofi".to_string(), |l| clap_name(&l))),
+                    ..Default::default()
+                };
+                search::find_matches(&docset_path, &query.join(" "), &opts).and_then(|matches| {
+                    match launcher {
+                        Some(launcher) => {
+                            menu::run_launcher(&matches, &docset_path, *launcher, *copy, *batch)
+                        }
+                        None => menu::rofi_script(&matches, &docset_path),
+                    }
+                })
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Serve {
+            action: Some(action),
+            ..
+        }) => {
+            if let Err(e) = run_serve_action(action) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Serve {
+            action: None,
+            socket,
+            lsp,
+            msgpack,
+            http,
+            bind,
+            token_file,
+            tls_cert,
+            tls_key,
+            watch,
+            gzip,
+        }) => {
+            let roots = docset_roots(&cli.docset_dir);
+            let server = std::sync::Arc::new(serve::Server::new(roots, config));
+            if *watch {
+                if let Err(e) = serve::watch(&server) {
+                    eprintln!("Error watching docsets: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            let encoding = if *msgpack {
+                serve::Encoding::Msgpack
+            } else {
+                serve::Encoding::Json
+            };
+            let result = match socket {
+                Some(path) => serve::run_socket(server, path, encoding, *gzip).map_err(Into::into),
+                None if *lsp => lsp::run_stdio(&server).map_err(Into::into),
+                None if *http => http::token(token_file.as_deref()).and_then(|token| {
+                    let opts = http::HttpOptions {
+                        bind: *bind,
+                        token,
+                        tls: tls_cert.clone().zip(tls_key.clone()),
+                        gzip: *gzip,
+                    };
+                    http::run(server, &opts)
+                }),
+                None => serve::run_stdio(server, encoding).map_err(Into::into),
+            };
+            if let Err(e) = result {
+                eprintln!("Error serving: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Pin { docset, version }) => {
+            let base = zeal_docsets_dir(&cli.docset_dir).expect("Docsets directory not found");
+            if let Err(e) = versions::pin(docset, version, &base) {
+                eprintln!("Error pinning '{}': {}", docset, e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Unpin { docset }) => {
+            let base = zeal_docsets_dir(&cli.docset_dir).expect("Docsets directory not found");
+            if let Err(e) = versions::unpin(docset, &base) {
+                eprintln!("Error unpinning '{}': {}", docset, e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Rollback { docset }) => {
+            let base = zeal_docsets_dir(&cli.docset_dir).expect("Docsets directory not found");
+            if let Err(e) = versions::rollback(docset, &base) {
+                eprintln!("Error rolling back '{}': {}", docset, e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Diff {
+            docset,
+            against,
+            json,
+        }) => {
+            let docset_path = resolve_docset(&cli.docset_dir, docset);
+            let compared = diff::other_version(&docset_path, against).and_then(|old_path| {
+                let old = search::load_entries(&old_path)?;
+                let new = search::load_entries(&docset_path)?;
+                Ok(diff::diff(&old, &new))
+            });
+            let changes = compared.unwrap_or_else(|e| {
+                eprintln!("Error comparing '{}' with {}: {}", docset, against, e);
+                std::process::exit(1);
+            });
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&changes).unwrap());
+            } else {
+                for s in &changes.added {
+                    println!("+\t{}\t{}", s.type_, s.name);
+                }
+                for s in &changes.removed {
+                    println!("-\t{}\t{}", s.type_, s.name);
+                }
+                for r in &changes.renamed {
+                    println!("~\t{}\t{}\t{}", r.to.type_, r.from.name, r.to.name);
+                }
+                if !cli.quiet {
+                    eprintln!(
+                        "{} added, {} removed, {} renamed",
+                        changes.added.len(),
+                        changes.removed.len(),
+                        changes.renamed.len()
+                    );
+                }
+            }
+        }
+        Some(Commands::Bench {
+            docset,
+            query,
+            iterations,
+            modes,
+        }) => {
+            let docset_path = resolve_docset(&cli.docset_dir, docset);
+            let query = query.join(" ");
+            if let Err(e) = bench::run(&docset_path, &query, modes, *iterations, cli.output) {
+                eprintln!("Error benchmarking docset '{}': {}", docset, e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Bundle {
+            action: Some(BundleAction::Import { file }),
+            ..
+        }) => {
+            let base = zeal_docsets_dir(&cli.docset_dir).expect("Docsets directory not found");
+            let spinner = progress::spinner(!cli.quiet, format!("Importing {}", file.display()));
+            let imported = bundle::import(file, &base);
+            spinner.finish_and_clear();
+            match imported {
+                Ok(imported) => {
+                    for bundle::Imported { name, version } in imported {
+                        match version {
+                            Some(version) => {
+                                eprintln!("Installed {} {} to {}", name, version, base.display())
+                            }
+                            None => eprintln!("Installed {} to {}", name, base.display()),
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error importing bundle: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Commands::Bundle {
+            action: None,
+            docsets,
+            out,
+        }) => {
+            let out = out.as_deref().expect("--out is required");
+            let paths: Vec<PathBuf> = docsets
+                .iter()
+                .map(|d| resolve_docset(&cli.docset_dir, d))
+                .collect();
+            let spinner = progress::spinner(!cli.quiet, format!("Bundling into {}", out.display()));
+            let created = bundle::create(&paths, out);
+            spinner.finish_and_clear();
+            if let Err(e) = created {
+                eprintln!("Error writing bundle: {}", e);
+                std::process::exit(1);
+            }
+            if !cli.quiet {
+                eprintln!("Bundled {} docsets into {}", paths.len(), out.display());
+            }
+        }
+        Some(Commands::Cache { action }) => match action {
+            CacheAction::Clear => match cache::clear() {
+                Ok(n) => eprintln!("{}", i18n::message("cache-cleared", &[("count", n.into())])),
+                Err(e) => {
+                    eprintln!("Error clearing cache: {}", e);
+                    std::process::exit(1);
+                }
+            },
+            CacheAction::Stats => {
+                let stats = cache::stats();
+                let lookups = stats.hits + stats.misses;
+                println!("Directory: {}", stats.dir.display());
+                println!("Entries:   {}", stats.entries);
+                println!("Size:      {:.1} KiB", stats.bytes as f64 / 1024.0);
+                println!(
+                    "Hits:      {} of {} lookups ({:.0}%)",
+                    stats.hits,
+                    lookups,
+                    if lookups == 0 {
+                        0.0
+                    } else {
+                        stats.hits as f64 * 100.0 / lookups as f64
+                    }
+                );
+            }
+        },
+        Some(Commands::Du {
+            clean_cache,
+            prune_orphans,
+        }) => {
+            let roots = docset_roots(&cli.docset_dir);
+            if *clean_cache {
+                let bytes = cache::stats().bytes;
+                match cache::clear() {
+                    Ok(n) => eprintln!("Removed {} cached result sets, {}", n, du::size(bytes)),
+                    Err(e) => {
+                        eprintln!("Error clearing cache: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            if *prune_orphans {
+                match du::prune_orphans(&roots) {
+                    Ok((n, bytes)) => eprintln!("Removed {} orphans, {}", n, du::size(bytes)),
+                    Err(e) => {
+                        eprintln!("Error removing orphans: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            if *clean_cache || *prune_orphans {
+                println!();
+            }
+            let docsets = installed_docsets(&cli.docset_dir).unwrap_or_else(|e| {
+                eprintln!("Error listing docsets: {}", e);
+                std::process::exit(1);
+            });
+            let usage: Vec<_> = docsets.iter().map(|p| du::usage(p)).collect();
+            du::print(&usage, &roots);
+        }
+        Some(Commands::Icons) => print_icon_legend(ansi),
+        Some(Commands::Filter {
+            query,
+            match_mode,
+            matcher,
+            any,
+            limit,
+            highlight,
+        }) => {
+            let lines: Vec<String> = match std::io::stdin().lines().collect() {
+                Ok(lines) => lines,
+                Err(e) => {
+                    eprintln!("Error reading stdin: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let query = query.join(" ");
+            let opts = search::SearchOptions {
+                mode: *match_mode,
+                matcher: *matcher,
+                any: *any,
+                limit: *limit,
+                ..Default::default()
+            };
+            let scorer = search::Scorer::new(&query, &opts);
+            for i in search::rank_lines(&lines, &query, &opts) {
+                if *highlight && ansi {
+                    println!("{}", emphasize(&lines[i], &scorer.positions(&lines[i])));
+                } else {
+                    println!("{}", lines[i]);
+                }
+            }
+        }
+        Some(Commands::Note { action }) => {
+            if let Err(e) = run_note(&cli.docset_dir, action) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Bookmarks { action }) => {
+            let list = |format| {
+                let display = output::OutputOptions {
+                    docsets: true,
+                    ..Default::default()
+                };
+                bookmarks::load().map(|b| output::print_results(&b, format, &display))
+            };
+            let result = match action {
+                None => list(output::OutputFormat::Plain),
+                Some(BookmarkAction::List { format }) => list(*format),
+                Some(BookmarkAction::Remove { name, docset }) => {
+                    let docset = docset
+                        .as_deref()
+                        .map(|d| search::docset_name(&resolve_docset(&cli.docset_dir, d)));
+                    bookmarks::remove(docset.as_deref(), name).and_then(|removed| match removed {
+                        0 => Err(format!("No bookmark named '{}'", name).into()),
+                        _ => Ok(()),
+                    })
+                }
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Exists {
+            docset,
+            name,
+            fuzzy,
+            ignore_case,
+        }) => {
+            // Errors exit 2, kept apart from "not found" so scripts can tell.
+            let roots = docset_roots(&cli.docset_dir);
+            let found = roots
+                .iter()
+                .map(|r| install::find_installed(r, docset))
+                .find(|found| !matches!(found, Ok(None)));
+            let docset_path = match found {
+                Some(Ok(Some(docset_path))) => docset_path,
+                Some(Err(e)) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+                _ => {
+                    eprintln!("Docset '{}' not found", docset);
+                    std::process::exit(2);
+                }
+            };
+            let found = if *fuzzy {
+                let opts = search::SearchOptions {
+                    limit: Some(1),
+                    ..Default::default()
+                };
+                search::load_entries(&docset_path)
+                    .map(|entries| !search::match_entries(&entries, name, &opts).is_empty())
+            } else {
+                search::has_entry(&docset_path, name, *ignore_case)
+            };
+            match found {
+                Ok(found) => std::process::exit(if found { 0 } else { 1 }),
+                Err(e) => {
+                    eprintln!("Error searching docset '{}': {}", docset, e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Some(Commands::CheckLinks { paths, ignore_case }) => {
+            let fail = |e: &dyn std::fmt::Display| -> ! {
+                eprintln!("Error checking links: {}", e);
+                std::process::exit(2);
+            };
+            let patterns = links::patterns(&config.link_patterns).unwrap_or_else(|e| fail(&e));
+            let mut found = Vec::new();
+            let mut files = 0;
+            for path in paths {
+                let (links, read) = links::scan(path, &patterns)
+                    .unwrap_or_else(|e| fail(&format!("{}: {}", path.display(), e)));
+                found.extend(links);
+                files += read;
+            }
+            let roots = docset_roots(&cli.docset_dir);
+            let resolve = |name: &str| {
+                roots
+                    .iter()
+                    .map(|r| install::find_installed(r, name))
+                    .find(|found| !matches!(found, Ok(None)))
+                    .unwrap_or(Ok(None))
+            };
+            let dangling =
+                links::dangling(&found, resolve, *ignore_case).unwrap_or_else(|e| fail(&e));
+            for (link, reason) in &dangling {
+                println!(
+                    "{}:{}: {}: {}",
+                    link.file.display(),
+                    link.line,
+                    link.text,
+                    reason
+                );
+            }
+            if !cli.quiet {
+                eprintln!(
+                    "Checked {} links in {} files, {} dangling",
+                    found.len(),
+                    files,
+                    dangling.len()
+                );
+            }
+            if !dangling.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::History { limit, json, path }) => {
+            let Some(file) = config.history.path() else {
+                eprintln!("No data directory on this platform");
+                std::process::exit(1);
+            };
+            if *path {
+                println!("{}", file.display());
+                return;
+            }
+            let mut lines = history::read(&file).unwrap_or_else(|e| {
+                eprintln!("Error reading {}: {}", file.display(), e);
+                std::process::exit(1);
+            });
+            if let Some(n) = limit {
+                lines.drain(..lines.len().saturating_sub(*n));
+            }
+            if *json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&lines).unwrap_or_default()
+                );
+            } else {
+                for line in &lines {
+                    println!("{}", line.query);
+                }
+            }
+            if !config.history.enabled && !cli.quiet {
+                eprintln!("History is off; set enabled = true under [history] in the config file");
+            }
+        }
+        Some(Commands::Stats {
+            days,
+            limit,
+            format,
+            ..
+        }) => {
+            let result = usage::report(*days, *limit)
+                .map_err(Into::into)
+                .and_then(|(docsets, entries)| usage::print(&docsets, &entries, *format));
+            if let Err(e) = result {
+                eprintln!("Error reading usage: {}", e);
+                std::process::exit(1);
+            }
+            if !config.track_usage && !cli.quiet {
+                eprintln!("Usage tracking is off; set track_usage = true in the config file");
+            }
+        }
+        Some(Commands::Index { action }) => match action {
+            IndexAction::Build { docsets, .. } => {
+                for docset in docsets {
+                    let docset_path = resolve_docset(&cli.docset_dir, docset);
+                    let spinner = progress::spinner(!cli.quiet, format!("Indexing {}", docset));
+                    let built = symbols::build(&docset_path);
+                    spinner.finish_and_clear();
+                    match built {
+                        Ok(n) => eprintln!("Indexed {} entries of {}", n, docset),
+                        Err(e) => {
+                            eprintln!("Error indexing docset '{}': {}", docset, e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+            }
+            IndexAction::Remove { docsets } => {
+                for docset in docsets {
+                    let docset_path = resolve_docset(&cli.docset_dir, docset);
+                    if symbols::remove(&docset_path) {
+                        eprintln!("Removed the index of {}", docset);
+                    } else if !cli.quiet {
+                        eprintln!("No index built for {}", docset);
+                    }
+                }
+            }
+        },
+        Some(Commands::State { action }) => {
+            let result = match action {
+                StateAction::Export { out } => state::export(cli.config.as_deref(), out.as_deref()),
+                StateAction::Import { file } => state::import(cli.config.as_deref(), file),
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Init) => {
+            let Some(config_path) = cli.config.clone().or_else(config::default_config_path) else {
+                eprintln!("Error: no config directory on this system; pass --config");
+                std::process::exit(1);
+            };
+            let icons: Vec<String> = TYPE_ICONS
+                .iter()
+                .map(|(type_, _, _)| type_icon(type_, true))
+                .collect();
+            let setup = wizard::Setup {
+                detected: zeal_docsets_dir(&cli.docset_dir),
+                docset_dir_env: DOCSET_DIR_ENV,
+                config_path,
+                icons: icons.join(" "),
+                cli: namespaces::command(Cli::command()),
+            };
+            if let Err(e) = wizard::run(setup) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(Commands::Capabilities) => {
+            let commands = namespaces::spellings(&Cli::command());
+            let capabilities = capabilities::capabilities(commands, cli.config.as_deref());
+            println!("{:#}", capabilities);
+        }
+        Some(Commands::SelfUpdate { check_only }) => {
+            if let Err(e) = self_update::self_update(&net, *check_only) {
+                eprintln!("Error updating zeal-cli: {}", e);
+                std::process::exit(1);
             }
         }
         None => {
-            println!("No command provided.");
+            eprintln!("{}", i18n::message("no-command", &[]));
             std::process::exit(1);
         }
     }
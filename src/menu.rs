@@ -0,0 +1,211 @@
+use clap::ValueEnum;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::clipboard::{self, CopyField};
+use crate::output::{self, OutputFormat, OutputOptions};
+use crate::search::{self, SearchResult};
+use crate::{bookmarks, open};
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Launcher {
+    Rofi,
+    Dmenu,
+    Wofi,
+    /// In the terminal
+    Fzf,
+}
+
+/// What to do with every entry picked in one go.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BatchAction {
+    /// Print them as `search` does
+    Print,
+    /// Copy their URLs, one per line
+    CopyUrls,
+    /// Print them as a Markdown list of links
+    Markdown,
+    /// Add them to the bookmarks
+    Bookmark,
+}
+
+impl BatchAction {
+    fn apply(self, picked: &[SearchResult]) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            BatchAction::Print => {
+                output::print_results(picked, OutputFormat::Plain, &OutputOptions::default())
+            }
+            BatchAction::Markdown => {
+                output::print_results(picked, OutputFormat::Markdown, &OutputOptions::default())
+            }
+            BatchAction::CopyUrls => {
+                let urls: Vec<String> = picked.iter().map(|m| open::entry_url(&m.path)).collect();
+                clipboard::set_text(&urls.join("\n"))?;
+            }
+            BatchAction::Bookmark => {
+                let added = bookmarks::add(picked)?;
+                eprintln!("Bookmarked {} of {} entries", added, picked.len());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shows `results` in the launcher and returns the indices of those picked,
+/// several when `multi` is set and the launcher can pick several.
+fn pick(
+    launcher: Launcher,
+    prompt: &str,
+    multi: bool,
+    icon: &Path,
+    results: &[&SearchResult],
+) -> Result<Vec<usize>, Box<dyn std::error::Error>> {
+    let mut cmd = match launcher {
+        Launcher::Rofi => {
+            let mut cmd = Command::new("rofi");
+            cmd.args(["-dmenu", "-i", "-show-icons", "-format", "i", "-p", prompt]);
+            if multi {
+                cmd.arg("-multi-select");
+            }
+            cmd
+        }
+        Launcher::Dmenu => {
+            let mut cmd = Command::new("dmenu");
+            cmd.args(["-i", "-p", prompt]);
+            cmd
+        }
+        Launcher::Wofi => {
+            let mut cmd = Command::new("wofi");
+            cmd.args(["--dmenu", "-i", "-p", prompt]);
+            cmd
+        }
+        Launcher::Fzf => {
+            let mut cmd = Command::new("fzf");
+            cmd.args(["-i", "--prompt", &format!("{}> ", prompt)]);
+            if multi {
+                cmd.arg("--multi");
+            }
+            cmd
+        }
+    };
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+
+    {
+        let mut stdin = child.stdin.take().ok_or("Launcher stdin unavailable")?;
+        for m in results {
+            match launcher {
+                Launcher::Rofi if icon.exists() => {
+                    writeln!(stdin, "{}\0icon\x1f{}", label(m), icon.display())?
+                }
+                _ => writeln!(stdin, "{}", label(m))?,
+            }
+        }
+    }
+
+    let out = child.wait_with_output()?;
+    let selection = String::from_utf8_lossy(&out.stdout);
+    let mut picked = Vec::new();
+    for line in selection.lines().filter(|l| !l.is_empty()) {
+        let i = match launcher {
+            Launcher::Rofi => line.parse::<usize>().ok().filter(|&i| i < results.len()),
+            _ => results.iter().position(|m| label(m) == line),
+        };
+        picked.push(i.ok_or_else(|| format!("Unknown selection '{}'", line))?);
+    }
+    Ok(picked)
+}
+
+fn label(m: &SearchResult) -> String {
+    format!("{}  ({})", m.name, m.type_)
+}
+
+/// Opens the picked entry, or copies one of its fields when `copy` is set.
+fn act(name: &str, path: &Path, copy: Option<CopyField>) -> Result<(), Box<dyn std::error::Error>> {
+    match copy {
+        Some(field) => clipboard::copy(field, name, path),
+        None => Ok(open::open_entry(path, Some(name))?),
+    }
+}
+
+/// Whether rofi is calling the script back with a picked entry (`ROFI_RETV=1`).
+pub fn rofi_selected() -> bool {
+    std::env::var("ROFI_RETV").as_deref() == Ok("1")
+}
+
+/// Acts on the entry rofi passed back: its path travels in `ROFI_INFO` and
+/// the picked line is rofi's last argument.
+pub fn rofi_open_selection(
+    selected: Option<&str>,
+    copy: Option<CopyField>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let info = std::env::var("ROFI_INFO").map_err(|_| "rofi did not pass ROFI_INFO")?;
+    let name = selected
+        .map(|line| line.rsplit_once("  (").map_or(line, |(name, _)| name))
+        .unwrap_or_default();
+    act(name, &PathBuf::from(info), copy)
+}
+
+/// Rofi script mode (`rofi -show zeal -modi "zeal:zeal-cli menu Rust"`).
+///
+/// Rofi runs the script once to list entries and again with `ROFI_RETV=1`
+/// once one is picked, see [`rofi_open_selection`].
+pub fn rofi_script(
+    results: &[SearchResult],
+    docset_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let icon = docset_path.join("icon.png");
+    let mut out = std::io::stdout().lock();
+    writeln!(out, "\0prompt\x1f{}", search::docset_name(docset_path))?;
+    for m in results {
+        write!(out, "{}\0info\x1f{}", label(m), m.path.display())?;
+        if icon.exists() {
+            write!(out, "\x1ficon\x1f{}", icon.display())?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Pipes results into a dmenu-style launcher and opens whatever it returns.
+///
+/// With `batch` several entries can be picked, with Tab in fzf, Shift+Enter
+/// in rofi and Ctrl+Enter in dmenu, and the action applies to all of them.
+/// wofi has no multi-select, so it opens again after each pick, without the
+/// entries picked so far, until it is closed with Escape.
+pub fn run_launcher(
+    results: &[SearchResult],
+    docset_path: &Path,
+    launcher: Launcher,
+    copy: Option<CopyField>,
+    batch: Option<BatchAction>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = search::docset_name(docset_path);
+    let icon = docset_path.join("icon.png");
+    let listed: Vec<&SearchResult> = results.iter().collect();
+    let picked = match launcher {
+        Launcher::Wofi if batch.is_some() => {
+            let (mut left, mut picked) = (listed, Vec::new());
+            while !left.is_empty() {
+                let prompt = format!("{} ({} picked)", name, picked.len());
+                let Some(&i) = pick(launcher, &prompt, false, &icon, &left)?.first() else {
+                    break;
+                };
+                picked.push(left.remove(i));
+            }
+            picked
+        }
+        _ => pick(launcher, &name, batch.is_some(), &icon, &listed)?
+            .into_iter()
+            .map(|i| listed[i])
+            .collect(),
+    };
+    match (batch, picked.as_slice()) {
+        (_, []) => Ok(()),
+        (Some(action), _) => {
+            let picked: Vec<SearchResult> = picked.into_iter().cloned().collect();
+            action.apply(&picked)
+        }
+        (None, [m, ..]) => act(&m.name, &m.path, copy),
+    }
+}
@@ -0,0 +1,106 @@
+use clap::{ArgMatches, Command};
+use std::ffi::OsString;
+
+/// A group of related commands under one name, each with the name it has
+/// in the group and the top-level command it stands for.
+struct Namespace {
+    name: &'static str,
+    about: &'static str,
+    commands: &'static [(&'static str, &'static str)],
+}
+
+/// The namespaced spellings: `docset install` runs `install`. The top-level
+/// spellings keep working, for scripts and editor plugins written against
+/// them, but are left out of the help. Commands that already have their
+/// own actions, such as `index build` or `state export`, stay as they are.
+const NAMESPACES: &[Namespace] = &[
+    Namespace {
+        name: "docset",
+        about: "Install, update and inspect docsets",
+        commands: &[
+            ("list", "list-docsets"),
+            ("info", "info"),
+            ("available", "available"),
+            ("install", "install"),
+            ("update", "update"),
+            ("verify", "verify"),
+            ("pin", "pin"),
+            ("unpin", "unpin"),
+            ("rollback", "rollback"),
+            ("diff", "diff"),
+            ("du", "du"),
+        ],
+    },
+    Namespace {
+        name: "page",
+        about: "Read, export and save the pages of entries",
+        commands: &[
+            ("preview", "preview"),
+            ("toc", "toc"),
+            ("examples", "examples"),
+            ("export", "export"),
+            ("save", "save"),
+        ],
+    },
+];
+
+/// The command line with the namespaces added and the top-level commands
+/// they group hidden, for parsing and help.
+pub fn command(flat: Command) -> Command {
+    let mut cmd = flat;
+    for namespace in NAMESPACES {
+        let mut group = Command::new(namespace.name)
+            .about(namespace.about)
+            .subcommand_required(true)
+            .arg_required_else_help(true);
+        for (name, target) in namespace.commands {
+            if let Some(sub) = cmd.find_subcommand(target) {
+                group = group.subcommand(sub.clone().name(*name));
+            }
+            cmd = cmd.mut_subcommand(*target, |sub| sub.hide(true));
+        }
+        cmd = cmd.subcommand(group);
+    }
+    cmd
+}
+
+/// Every spelling of the commands, namespaced and top-level.
+pub fn spellings(flat: &Command) -> Vec<String> {
+    let mut spellings: Vec<String> = flat
+        .get_subcommands()
+        .map(|c| c.get_name().to_string())
+        .collect();
+    for namespace in NAMESPACES {
+        for (name, _) in namespace.commands {
+            spellings.push(format!("{} {}", namespace.name, name));
+        }
+    }
+    spellings
+}
+
+/// `args` with a namespaced command, as `matches` parsed it, replaced by its
+/// top-level spelling, so the rest of the program only knows those.
+pub fn flatten(mut args: Vec<OsString>, matches: &ArgMatches) -> Vec<OsString> {
+    let Some((group, sub)) = matches.subcommand() else {
+        return args;
+    };
+    let Some(namespace) = NAMESPACES.iter().find(|n| n.name == group) else {
+        return args;
+    };
+    let Some(name) = sub.subcommand_name() else {
+        return args;
+    };
+    let Some((_, target)) = namespace.commands.iter().find(|(n, _)| *n == name) else {
+        return args;
+    };
+    // The first `group name` pair: a global option's value spelled like the
+    // namespace is followed by the namespace itself, not the command.
+    if let Some(i) = args
+        .windows(2)
+        .skip(1)
+        .position(|pair| pair[0] == *group && pair[1] == *name)
+    {
+        args.splice(i + 1..i + 3, [OsString::from(*target)]);
+    }
+    args
+}
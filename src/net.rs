@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+const USER_AGENT: &str = concat!("zeal-cli/", env!("CARGO_PKG_VERSION"));
+
+/// Network settings shared by every network-dependent subcommand.
+#[derive(Debug, Default, Clone)]
+pub struct Net {
+    /// Proxy from `--proxy`, taking precedence over the environment.
+    pub proxy: Option<String>,
+    pub offline: bool,
+}
+
+impl Net {
+    /// Fails with a clear message when running with `--offline`.
+    pub fn ensure_online(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.offline {
+            return Err("This command needs network access, which --offline disables".into());
+        }
+        Ok(())
+    }
+
+    /// HTTP agent for `url`, routed through the configured proxy unless the
+    /// host is excluded by `NO_PROXY`.
+    pub fn agent_for(&self, url: &str) -> Result<ureq::Agent, Box<dyn std::error::Error>> {
+        self.builder_for(url).map(|b| b.build())
+    }
+
+    /// Like [`Net::agent_for`] but returns the builder so callers can tune timeouts.
+    pub fn builder_for(&self, url: &str) -> Result<ureq::AgentBuilder, Box<dyn std::error::Error>> {
+        if self.offline {
+            return Err(format!("Cannot reach {} in --offline mode", url).into());
+        }
+        let mut builder = ureq::AgentBuilder::new()
+            .user_agent(USER_AGENT)
+            .timeout_connect(Duration::from_secs(15));
+        if let Some(proxy) = self.proxy_for(url) {
+            builder = builder.proxy(ureq::Proxy::new(&proxy)?);
+        }
+        Ok(builder)
+    }
+
+    fn proxy_for(&self, url: &str) -> Option<String> {
+        if let Some(proxy) = &self.proxy {
+            return Some(proxy.clone());
+        }
+        if is_no_proxy(host_of(url)) {
+            return None;
+        }
+        let vars: &[&str] = if url.starts_with("https://") {
+            &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        } else {
+            &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+        };
+        vars.iter()
+            .find_map(|v| std::env::var(v).ok())
+            .filter(|p| !p.is_empty())
+    }
+}
+
+fn host_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let host = authority.rsplit_once('@').map_or(authority, |(_, h)| h);
+    host.rsplit_once(':')
+        .filter(|(_, port)| port.chars().all(|c| c.is_ascii_digit()))
+        .map_or(host, |(h, _)| h)
+}
+
+/// Whether `NO_PROXY` (comma separated hosts, `.domain` suffixes or `*`) covers `host`.
+fn is_no_proxy(host: &str) -> bool {
+    let Some(no_proxy) = std::env::var("NO_PROXY")
+        .or_else(|_| std::env::var("no_proxy"))
+        .ok()
+    else {
+        return false;
+    };
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| {
+            let entry = entry.split(':').next().unwrap_or(entry);
+            entry == "*"
+                || host.eq_ignore_ascii_case(entry.trim_start_matches('.'))
+                || host.to_ascii_lowercase().ends_with(&format!(
+                    ".{}",
+                    entry.trim_start_matches('.').to_ascii_lowercase()
+                ))
+        })
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::search::{self, SearchResult};
+use crate::{install, related};
+
+/// Notes by docset key, see [`docset_key`], then entry name.
+pub type Notes = BTreeMap<String, BTreeMap<String, String>>;
+
+/// What notes on a docset's entries are filed under: its name and
+/// installed version, `Rust@1.80.0`, so notes taken on one version don't
+/// show on entries of another; the name alone for docsets without a
+/// version.
+pub fn docset_key(docset_path: &Path) -> String {
+    let name = search::docset_name(docset_path);
+    match install::read_meta(docset_path).filter(|meta| !meta.version.is_empty()) {
+        Some(meta) => format!("{}@{}", name, meta.version),
+        None => name,
+    }
+}
+
+fn notes_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("zeal-cli").join("notes.json"))
+}
+
+/// Every note, as `state export` writes them.
+pub fn load() -> Result<Notes, Box<dyn std::error::Error>> {
+    let Some(path) = notes_path() else {
+        return Ok(Notes::new());
+    };
+    match fs::read(&path) {
+        Ok(body) => {
+            Ok(serde_json::from_slice(&body).map_err(|e| format!("{}: {}", path.display(), e))?)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Notes::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+    }
+}
+
+fn save(notes: &Notes) -> Result<(), Box<dyn std::error::Error>> {
+    let path = notes_path().ok_or("No data directory on this platform")?;
+    let dir = path.parent().ok_or("No data directory on this platform")?;
+    fs::create_dir_all(dir)?;
+    // Written aside and renamed so a failed write keeps the old notes.
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer_pretty(&mut tmp, notes)?;
+    tmp.persist(&path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+pub fn get(docset: &str, name: &str) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    Ok(load()?.get(docset).and_then(|n| n.get(name)).cloned())
+}
+
+/// Attaches `text` to an entry, replacing any note it had.
+pub fn set(docset: &str, name: &str, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut notes = load()?;
+    notes
+        .entry(docset.to_string())
+        .or_default()
+        .insert(name.to_string(), text.trim_end().to_string());
+    save(&notes)
+}
+
+/// Adds `incoming` notes, replacing those of the same entries, and returns
+/// how many there were.
+pub fn merge(incoming: Notes) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut notes = load()?;
+    let mut merged = 0;
+    for (docset, entries) in incoming {
+        merged += entries.len();
+        notes.entry(docset).or_default().extend(entries);
+    }
+    if merged > 0 {
+        save(&notes)?;
+    }
+    Ok(merged)
+}
+
+/// Removes an entry's note, returning whether it had one.
+pub fn remove(docset: &str, name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut notes = load()?;
+    let Some(entries) = notes.get_mut(docset) else {
+        return Ok(false);
+    };
+    let removed = entries.remove(name).is_some();
+    if entries.is_empty() {
+        notes.remove(docset);
+    }
+    if removed {
+        save(&notes)?;
+    }
+    Ok(removed)
+}
+
+/// A note on an entry, as `note list` prints it.
+pub struct Note {
+    /// The docset key it is filed under, see [`docset_key`].
+    pub docset: String,
+    pub name: String,
+    pub text: String,
+}
+
+/// Every note, or only those on a docset's entries, by name, in any
+/// version.
+pub fn list(docset: Option<&str>) -> Result<Vec<Note>, Box<dyn std::error::Error>> {
+    let of_docset = |key: &str, name: &str| {
+        key.strip_prefix(name)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('@'))
+    };
+    Ok(load()?
+        .into_iter()
+        .filter(|(d, _)| docset.is_none_or(|wanted| of_docset(d, wanted)))
+        .flat_map(|(d, entries)| {
+            entries.into_iter().map(move |(name, text)| Note {
+                docset: d.clone(),
+                name,
+                text,
+            })
+        })
+        .collect())
+}
+
+/// Fills in the notes of results, from the version of their docset their
+/// paths lie in.
+pub fn annotate(results: &mut [SearchResult]) -> Result<(), Box<dyn std::error::Error>> {
+    let notes = load()?;
+    let mut keys: HashMap<PathBuf, String> = HashMap::new();
+    for m in results {
+        let Some(docset_path) = related::docset_of(&m.path) else {
+            continue;
+        };
+        let key = keys
+            .entry(docset_path)
+            .or_insert_with_key(|path| docset_key(path));
+        m.note = notes
+            .get(key.as_str())
+            .and_then(|n| n.get(&m.name))
+            .cloned();
+    }
+    Ok(())
+}
+
+/// Lets the user edit `text` in `$VISUAL` or `$EDITOR`, returning the result.
+pub fn edit(text: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|e| !e.trim().is_empty())
+        .ok_or("Set $VISUAL or $EDITOR, or pass the note's text")?;
+    let file = tempfile::Builder::new().suffix(".md").tempfile()?;
+    fs::write(file.path(), format!("{}\n", text))?;
+    // Editors are often configured with arguments, `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_default();
+    let status = Command::new(program)
+        .args(words)
+        .arg(file.path())
+        .status()?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", program, status).into());
+    }
+    Ok(fs::read_to_string(file.path())?)
+}
+
+/// A note on one line, for output formats that are line based.
+pub fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
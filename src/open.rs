@@ -0,0 +1,147 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{archive, platform, usage};
+
+/// Resolves a `searchIndex.path` value against the docset's Documents dir.
+///
+/// Index paths may carry Dash `<dash_entry_...>` metadata prefixes or be
+/// absolute `http(s)://` URLs for online-only entries; neither survives a
+/// plain `Path::join`.
+pub fn entry_path(docs_dir: &Path, index_path: &str) -> PathBuf {
+    let mut path = index_path;
+    while let Some(rest) = path.strip_prefix("<dash_entry_") {
+        path = rest.split_once('>').map_or("", |(_, rest)| rest);
+    }
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return PathBuf::from(path);
+    }
+    docs_dir.join(path)
+}
+
+/// Builds a URL for an entry path: remote URLs pass through, local files get a
+/// `file://` URL with each segment and the `#fragment` percent-encoded.
+pub fn entry_url(path: &Path) -> String {
+    let path = platform::strip_verbatim(&path.to_string_lossy()).replace('\\', "/");
+    if path.starts_with("http://") || path.starts_with("https://") {
+        return path;
+    }
+    let (file, fragment) = match path.split_once('#') {
+        Some((file, fragment)) => (file, Some(fragment)),
+        None => (path.as_str(), None),
+    };
+    let encoded: Vec<String> = file
+        .split('/')
+        .map(|segment| {
+            // Keep Windows drive letters (`C:`) readable.
+            if segment.len() == 2 && segment.ends_with(':') {
+                return segment.to_string();
+            }
+            // Some docsets store already-escaped paths; decode first so they
+            // aren't escaped twice.
+            let decoded = urlencoding::decode(segment)
+                .map(|s| s.into_owned())
+                .unwrap_or_else(|_| segment.to_string());
+            urlencoding::encode(&decoded).into_owned()
+        })
+        .collect();
+    let mut url = String::from("file://");
+    if !file.starts_with('/') {
+        url.push('/');
+    }
+    url.push_str(&encoded.join("/"));
+    if let Some(fragment) = fragment {
+        url.push('#');
+        url.push_str(&encode_fragment(fragment));
+    }
+    url
+}
+
+/// Percent-encodes what a URL fragment may not hold, keeping the `/`, `:`
+/// and the like that anchors such as `//apple_ref/cpp/func/foo` are made of.
+fn encode_fragment(fragment: &str) -> String {
+    let decoded = urlencoding::decode(fragment)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| fragment.to_string());
+    let mut encoded = String::new();
+    for byte in decoded.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/?".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Command that opens `url` in the default browser without dropping its fragment.
+///
+/// `$BROWSER` wins when set. Otherwise `xdg-open` is used on Linux/BSD;
+/// macOS goes through AppleScript's `open location` because `open` hands
+/// `file://` URLs to Finder, which discards the anchor; Windows uses
+/// `url.dll` to stay clear of `cmd` quoting, where `%` and `&` are special.
+fn opener(url: &str) -> Command {
+    if let Some(browser) = std::env::var_os("BROWSER").filter(|b| !b.is_empty()) {
+        let mut cmd = Command::new(browser);
+        cmd.arg(url);
+        return cmd;
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut cmd = Command::new("osascript");
+        cmd.arg("-e").arg(format!(
+            "open location \"{}\"",
+            url.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+        cmd
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let mut cmd = Command::new("rundll32");
+        cmd.arg("url.dll,FileProtocolHandler").arg(url);
+        cmd
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let mut cmd = Command::new("xdg-open");
+        cmd.arg(url);
+        cmd
+    }
+}
+
+/// Opens a URL with the platform's default handler.
+pub fn open_url(url: &str) -> std::io::Result<()> {
+    let status = opener(url).status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!(
+            "Opener exited with {} for {}",
+            status, url
+        )));
+    }
+    Ok(())
+}
+
+/// Opens an entry path (as returned by [`entry_path`]) in the browser,
+/// recording the open under the entry's `name` when known.
+pub fn open_entry(path: &Path, name: Option<&str>) -> std::io::Result<()> {
+    archive::materialize(path)?;
+    open_url(&entry_url(path))?;
+    usage::record(path, name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_fragments_once() {
+        let url = |path: &str| entry_url(Path::new(path));
+        assert_eq!(
+            url("/d/a b.html#//apple_ref/cpp/Method/operator<"),
+            "file:///d/a%20b.html#//apple_ref/cpp/Method/operator%3C"
+        );
+        assert_eq!(url("/d/a.html#x%20y"), "file:///d/a.html#x%20y");
+        assert_eq!(url("/d/a.html#größe"), "file:///d/a.html#gr%C3%B6%C3%9Fe");
+    }
+}
@@ -0,0 +1,697 @@
+use clap::ValueEnum;
+use serde_json::json;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::search::SearchResult;
+use crate::{breadcrumbs, http, notes, open, related, slow_fs, toc, type_icon, types};
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Tab separated `icon name type path` lines, plus `docset` when
+    /// several docsets are searched
+    #[default]
+    Plain,
+    /// Vim quickfix lines: `file:1: name [type]`
+    Quickfix,
+    /// JSON array of LSP `Location` objects
+    Lsp,
+    /// Alfred Script Filter JSON, for `Run Script` workflow inputs
+    Alfred,
+    /// `[name](file:///page#anchor) — type` lines, to paste into notes
+    Markdown,
+    /// A Markdown table of linked names and their types
+    MarkdownTable,
+    /// `{items: [...]}` of Raycast list items with open and copy actions,
+    /// for Raycast extensions
+    Raycast,
+    /// Array of Ulauncher result items with enter and alt-enter actions,
+    /// for Ulauncher extensions
+    Ulauncher,
+    /// A standalone HTML page of links to the entries, to share a list of
+    /// references
+    Html,
+    /// One JSON object per result and line, each written as soon as it is
+    /// formatted, for `jq` and clients that read results as they come
+    Jsonl,
+}
+
+/// How entry paths are printed, and what the formats that link to pages
+/// link to. Quickfix and LSP output keep the files editors open.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathStyle {
+    /// Absolute file paths
+    #[default]
+    Absolute,
+    /// Paths relative to the docset's Documents directory
+    DocsetRelative,
+    /// `file://` URLs, as passed to a browser
+    Url,
+    /// `http://localhost` URLs of the pages a running `serve --http`
+    /// serves; `file://` URLs while none runs
+    Localhost,
+}
+
+/// Result fields output can be grouped on.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    /// The parent of hierarchical names, `Vec` for `Vec::push`
+    Container,
+    /// The entry type, under headers such as `Functions` and `Guides`
+    Type,
+}
+
+impl GroupBy {
+    /// Header of the group `m` belongs to.
+    fn label(self, m: &SearchResult) -> String {
+        match self {
+            GroupBy::Container => m.container.as_deref().unwrap_or("(top level)").to_string(),
+            GroupBy::Type => plural(types::normalize(&m.type_)),
+        }
+    }
+}
+
+/// A column of plain output chosen with `--columns`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    /// The type icon, see `icons`
+    Icon,
+    Name,
+    Type,
+    /// The entry's page, in the `--path-style` asked for
+    Path,
+    Docset,
+    /// The match score, higher is better
+    Score,
+    /// The part of the path after `#`
+    Anchor,
+    /// The parent of hierarchical names, `Vec` for `Vec::push`
+    Container,
+    Note,
+    Breadcrumbs,
+    /// The declaration on the entry's page, see `--enrich`
+    Signature,
+    /// The deprecation notice on the entry's page, see `--enrich`
+    Deprecated,
+    /// The version the entry appeared in, see `--enrich`
+    Since,
+}
+
+impl Column {
+    fn cell(self, m: &SearchResult, opts: &OutputOptions) -> String {
+        match self {
+            Column::Icon => type_icon(&m.type_, opts.ansi),
+            Column::Name => m.name.clone(),
+            Column::Type => m.type_.clone(),
+            Column::Path => styled_path(&m.path, opts.path_style),
+            Column::Docset => m.docset.clone(),
+            Column::Score => m.score.to_string(),
+            Column::Anchor => m
+                .path
+                .to_string_lossy()
+                .split_once('#')
+                .map(|(_, anchor)| anchor.to_string())
+                .unwrap_or_default(),
+            Column::Container => m.container.clone().unwrap_or_default(),
+            Column::Note => m.note.as_deref().map(notes::one_line).unwrap_or_default(),
+            Column::Breadcrumbs => m
+                .breadcrumbs
+                .as_deref()
+                .unwrap_or_default()
+                .join(breadcrumbs::SEPARATOR),
+            Column::Signature | Column::Deprecated | Column::Since => {
+                let Some(e) = &m.enrichment else {
+                    return String::new();
+                };
+                let field = match self {
+                    Column::Signature => &e.signature,
+                    Column::Deprecated => &e.deprecated,
+                    _ => &e.since,
+                };
+                field.clone().unwrap_or_default()
+            }
+        }
+    }
+
+    /// Whether the column is read from pages by `enrich`.
+    pub fn is_enriched(self) -> bool {
+        matches!(self, Column::Signature | Column::Deprecated | Column::Since)
+    }
+}
+
+/// English plural of a type name: `Classes`, `Properties`, `Functions`.
+fn plural(word: &str) -> String {
+    let consonant_y = word
+        .strip_suffix('y')
+        .filter(|stem| !stem.ends_with(|c: char| "aeiouAEIOU".contains(c)));
+    if let Some(stem) = consonant_y {
+        format!("{}ies", stem)
+    } else if ["s", "x", "ch", "sh"].iter().any(|end| word.ends_with(end)) {
+        format!("{}es", word)
+    } else {
+        format!("{}s", word)
+    }
+}
+
+/// Presentation settings shared by the output formats.
+#[derive(Clone, Debug, Default)]
+pub struct OutputOptions {
+    /// Prefix plain lines with a type icon.
+    pub icons: bool,
+    /// Label each result with its docset, for searches over several docsets.
+    pub docsets: bool,
+    pub path_style: PathStyle,
+    /// Append each result's container, empty for top-level entries.
+    pub container: bool,
+    pub group_by: Option<GroupBy>,
+    /// Append each result's note, empty for entries without one.
+    pub notes: bool,
+    /// Append each result's breadcrumbs, empty for entries without any.
+    pub breadcrumbs: bool,
+    /// Append each result's signature, deprecation notice and version.
+    pub enrich: bool,
+    /// The terminal shows ANSI colors and icon glyphs.
+    pub ansi: bool,
+    /// Columns plain lines are fitted to by eliding the middle of their
+    /// paths; `None` leaves them whole.
+    pub width: Option<usize>,
+    /// The search ran out of time; JSON outputs with room for it say so.
+    pub truncated: bool,
+    /// Plain output as these aligned columns instead of the tab separated
+    /// fields; empty keeps the fields.
+    pub columns: Vec<Column>,
+}
+
+/// Columns of the terminal stdout writes to, `$COLUMNS` taking precedence;
+/// `None` when output goes to a pipe or file, which get whole lines.
+pub fn terminal_width() -> Option<usize> {
+    let term = console::Term::stdout();
+    if !term.is_term() {
+        return None;
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .filter(|&w| w > 0)
+        .or_else(|| term.size_checked().map(|(_, w)| usize::from(w)))
+}
+
+/// Display width of a line, tabs expanded to the usual 8-column stops and
+/// ANSI escapes not counted.
+fn line_width(line: &str) -> usize {
+    let mut col = 0;
+    for (i, field) in line.split('\t').enumerate() {
+        if i > 0 {
+            col = (col / 8 + 1) * 8;
+        }
+        col += console::measure_text_width(field);
+    }
+    col
+}
+
+/// `path` within `max` columns by eliding the middle, keeping the last
+/// component and any anchor whole: `/home/me/…/struct.Vec.html#method.push`.
+fn elide_path(path: &str, max: usize) -> String {
+    if console::measure_text_width(path) <= max {
+        return path.to_string();
+    }
+    // Anchors can hold slashes, so the page's name ends where one begins.
+    let page_end = path.find('#').unwrap_or(path.len());
+    let split = path[..page_end].rfind(['/', '\\']).unwrap_or(0);
+    let (head, tail) = path.split_at(split);
+    let mut room = max.saturating_sub(console::measure_text_width(tail) + 1);
+    let kept: String = head
+        .chars()
+        .take_while(|c| {
+            let w = console::measure_text_width(c.encode_utf8(&mut [0; 4]));
+            room = match room.checked_sub(w) {
+                Some(left) => left,
+                None => return false,
+            };
+            true
+        })
+        .collect();
+    format!("{}…{}", kept, tail)
+}
+
+/// An entry path in the requested style. Online entries are URLs already and
+/// are printed as they are.
+pub fn styled_path(path: &Path, style: PathStyle) -> String {
+    match style {
+        PathStyle::Absolute => path.display().to_string(),
+        PathStyle::Url | PathStyle::Localhost => styled_url(path, style),
+        PathStyle::DocsetRelative => {
+            let docs_dir = path
+                .ancestors()
+                .find(|a| a.ends_with("Contents/Resources/Documents"));
+            match docs_dir.and_then(|d| path.strip_prefix(d).ok()) {
+                Some(relative) => relative.display().to_string(),
+                None => path.display().to_string(),
+            }
+        }
+    }
+}
+
+/// The URL formats that link to pages use for an entry: where a running
+/// `serve --http` serves it with [`PathStyle::Localhost`], else its
+/// `file://` URL.
+pub fn styled_url(path: &Path, style: PathStyle) -> String {
+    let served = match style {
+        PathStyle::Localhost => http::running_base().and_then(|base| http::page_url(base, path)),
+        _ => None,
+    };
+    served.unwrap_or_else(|| open::entry_url(path))
+}
+
+pub fn print_results(results: &[SearchResult], format: OutputFormat, opts: &OutputOptions) {
+    // A closed pipe (`| head`) is not worth reporting.
+    let _ = write_results(&mut io::stdout().lock(), results, format, opts);
+}
+
+pub fn write_results(
+    out: &mut dyn Write,
+    results: &[SearchResult],
+    format: OutputFormat,
+    opts: &OutputOptions,
+) -> io::Result<()> {
+    let _span = tracing::debug_span!("output", ?format, results = results.len()).entered();
+    let grouped;
+    let results = match opts.group_by {
+        Some(by) => {
+            grouped = group(results, by);
+            grouped.as_slice()
+        }
+        None => results,
+    };
+    match format {
+        OutputFormat::Plain if !opts.columns.is_empty() => write_columns(out, results, opts)?,
+        OutputFormat::Plain => {
+            let mut header: Option<String> = None;
+            for m in results {
+                if let Some(by) = opts.group_by {
+                    let label = by.label(m);
+                    if header.as_ref() != Some(&label) {
+                        if header.is_some() {
+                            writeln!(out)?;
+                        }
+                        writeln!(out, "{}", label)?;
+                        header = Some(label);
+                    }
+                }
+                let icon = if opts.icons {
+                    type_icon(&m.type_, opts.ansi)
+                } else {
+                    String::new()
+                };
+                let path = styled_path(&m.path, opts.path_style);
+                let line = |path: &str| {
+                    let mut line = format!("{}\t{}\t{}\t{}", icon, m.name, m.type_, path);
+                    // Appended so the existing columns keep their positions.
+                    if opts.docsets {
+                        line.push_str(&format!("\t{}", m.docset));
+                    }
+                    if opts.container {
+                        let container = m.container.as_deref().unwrap_or_default();
+                        line.push_str(&format!("\t{}", container));
+                    }
+                    if opts.notes {
+                        let note = m.note.as_deref().map(notes::one_line).unwrap_or_default();
+                        line.push_str(&format!("\t{}", note));
+                    }
+                    if opts.breadcrumbs {
+                        let crumbs = m.breadcrumbs.as_deref().unwrap_or_default();
+                        line.push_str(&format!("\t{}", crumbs.join(breadcrumbs::SEPARATOR)));
+                    }
+                    if opts.enrich {
+                        for column in [Column::Signature, Column::Deprecated, Column::Since] {
+                            line.push_str(&format!("\t{}", column.cell(m, opts)));
+                        }
+                    }
+                    line
+                };
+                let mut text = line(&path);
+                if let Some(width) = opts.width {
+                    // Tab stops shift with the path, so shorten until it fits.
+                    let mut max = console::measure_text_width(&path);
+                    while line_width(&text) > width {
+                        max = max.saturating_sub(line_width(&text) - width);
+                        let shorter = line(&elide_path(&path, max));
+                        if shorter == text {
+                            break;
+                        }
+                        text = shorter;
+                    }
+                }
+                writeln!(out, "{}", text)?;
+            }
+        }
+        OutputFormat::Quickfix => {
+            for m in results {
+                write!(
+                    out,
+                    "{}:1: {} [{}]",
+                    toc::strip_fragment(&m.path).display(),
+                    m.name,
+                    m.type_
+                )?;
+                if opts.docsets {
+                    write!(out, " ({})", m.docset)?;
+                }
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Lsp => {
+            let zero = json!({ "line": 0, "character": 0 });
+            let locations: Vec<_> = results
+                .iter()
+                .map(|m| {
+                    json!({
+                        "uri": open::entry_url(&toc::strip_fragment(&m.path)),
+                        "range": { "start": zero, "end": zero },
+                    })
+                })
+                .collect();
+            writeln!(out, "{}", serde_json::Value::Array(locations))?;
+        }
+        OutputFormat::Markdown => {
+            let mut header: Option<String> = None;
+            for m in results {
+                if let Some(by) = opts.group_by {
+                    let label = by.label(m);
+                    if header.as_ref() != Some(&label) {
+                        if header.is_some() {
+                            writeln!(out)?;
+                        }
+                        writeln!(out, "## {}\n", label)?;
+                        header = Some(label);
+                    }
+                }
+                write!(out, "{} — {}", markdown_link(m, opts), m.type_)?;
+                if opts.docsets {
+                    write!(out, " ({})", m.docset)?;
+                }
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::MarkdownTable => {
+            let cell = |text: &str| text.replace('|', "\\|");
+            if opts.docsets {
+                writeln!(out, "| Name | Type | Docset |\n| --- | --- | --- |")?;
+            } else {
+                writeln!(out, "| Name | Type |\n| --- | --- |")?;
+            }
+            for m in results {
+                write!(
+                    out,
+                    "| {} | {} |",
+                    cell(&markdown_link(m, opts)),
+                    cell(&m.type_)
+                )?;
+                if opts.docsets {
+                    write!(out, " {} |", cell(&m.docset))?;
+                }
+                writeln!(out)?;
+            }
+        }
+        OutputFormat::Html => write_html(out, results, opts)?,
+        OutputFormat::Alfred | OutputFormat::Raycast => {
+            let item = match format {
+                OutputFormat::Alfred => alfred_item,
+                _ => raycast_item,
+            };
+            let items: Vec<_> = results.iter().map(|m| item(m, opts)).collect();
+            let mut body = json!({ "items": items });
+            if opts.truncated {
+                body["truncated"] = json!(true);
+            }
+            writeln!(out, "{}", body)?;
+        }
+        OutputFormat::Jsonl => {
+            for m in results {
+                let mut result = serde_json::to_value(m)?;
+                result["path"] = json!(styled_path(&m.path, opts.path_style));
+                serde_json::to_writer(&mut *out, &result)?;
+                writeln!(out)?;
+                out.flush()?;
+            }
+        }
+        OutputFormat::Ulauncher => {
+            let items: Vec<_> = results.iter().map(|m| ulauncher_item(m, opts)).collect();
+            writeln!(out, "{}", serde_json::Value::Array(items))?;
+        }
+    }
+    out.flush()
+}
+
+/// Plain output as the chosen columns, each padded to its widest cell.
+/// Paths are elided to fit the terminal, if need be.
+fn write_columns(
+    out: &mut dyn Write,
+    results: &[SearchResult],
+    opts: &OutputOptions,
+) -> io::Result<()> {
+    const GAP: &str = "  ";
+    let mut rows: Vec<(String, Vec<String>)> = results
+        .iter()
+        .map(|m| {
+            let label = opts.group_by.map(|by| by.label(m)).unwrap_or_default();
+            let cells = opts.columns.iter().map(|c| c.cell(m, opts)).collect();
+            (label, cells)
+        })
+        .collect();
+    let widths = |rows: &[(String, Vec<String>)]| -> Vec<usize> {
+        (0..opts.columns.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|(_, cells)| console::measure_text_width(&cells[i]))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect()
+    };
+    let mut width = widths(&rows);
+    let path = opts.columns.iter().position(|c| *c == Column::Path);
+    if let (Some(max), Some(path)) = (opts.width, path) {
+        let total = width.iter().sum::<usize>() + GAP.len() * (width.len() - 1);
+        if total > max {
+            let room = width[path].saturating_sub(total - max);
+            for (_, cells) in &mut rows {
+                cells[path] = elide_path(&cells[path], room);
+            }
+            width = widths(&rows);
+        }
+    }
+    let mut header: Option<&str> = None;
+    for (label, cells) in &rows {
+        if opts.group_by.is_some() && header != Some(label.as_str()) {
+            if header.is_some() {
+                writeln!(out)?;
+            }
+            writeln!(out, "{}", label)?;
+            header = Some(label);
+        }
+        let last = cells.len() - 1;
+        let mut line = String::new();
+        for (i, cell) in cells.iter().enumerate() {
+            line.push_str(cell);
+            if i < last {
+                let pad = width[i] - console::measure_text_width(cell);
+                line.push_str(&" ".repeat(pad));
+                line.push_str(GAP);
+            }
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// `text` safe in HTML text and double-quoted attributes.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A page of tables linking each result's page, one table per
+/// group when grouped, with the columns the other flags ask for.
+fn write_html(
+    out: &mut dyn Write,
+    results: &[SearchResult],
+    opts: &OutputOptions,
+) -> io::Result<()> {
+    let mut headings = vec!["Name", "Type"];
+    let extra: Vec<(&str, Column)> = [
+        (opts.docsets, "Docset", Column::Docset),
+        (opts.container, "Container", Column::Container),
+        (opts.notes, "Note", Column::Note),
+        (opts.breadcrumbs, "Breadcrumbs", Column::Breadcrumbs),
+        (opts.enrich, "Signature", Column::Signature),
+        (opts.enrich, "Deprecated", Column::Deprecated),
+        (opts.enrich, "Since", Column::Since),
+    ]
+    .into_iter()
+    .filter(|(on, _, _)| *on)
+    .map(|(_, heading, column)| (heading, column))
+    .collect();
+    headings.extend(extra.iter().map(|(heading, _)| *heading));
+    let table_head: String = headings.iter().map(|h| format!("<th>{}</th>", h)).collect();
+
+    writeln!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Documentation references</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         table {{ border-collapse: collapse; margin-bottom: 2em; }}\n\
+         th, td {{ text-align: left; padding: 0.3em 1em 0.3em 0; }}\n\
+         tr + tr td {{ border-top: 1px solid #ddd; }}\n\
+         </style>\n</head>\n<body>"
+    )?;
+    let mut group: Option<String> = None;
+    for (i, m) in results.iter().enumerate() {
+        let label = opts.group_by.map(|by| by.label(m));
+        if i == 0 || label != group {
+            if i > 0 {
+                writeln!(out, "</table>")?;
+            }
+            if let Some(label) = &label {
+                writeln!(out, "<h2>{}</h2>", escape_html(label))?;
+            }
+            writeln!(out, "<table>\n<tr>{}</tr>", table_head)?;
+            group = label;
+        }
+        write!(
+            out,
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td>",
+            escape_html(&styled_url(&m.path, opts.path_style)),
+            escape_html(&m.name),
+            escape_html(&m.type_)
+        )?;
+        for (_, column) in &extra {
+            write!(out, "<td>{}</td>", escape_html(&column.cell(m, opts)))?;
+        }
+        writeln!(out, "</tr>")?;
+    }
+    if !results.is_empty() {
+        writeln!(out, "</table>")?;
+    }
+    writeln!(out, "</body>\n</html>")
+}
+
+/// A result as a Markdown link to its page.
+fn markdown_link(m: &SearchResult, opts: &OutputOptions) -> String {
+    let mut text = String::new();
+    for c in m.name.chars() {
+        if matches!(c, '[' | ']' | '\\' | '*' | '_' | '`') {
+            text.push('\\');
+        }
+        text.push(c);
+    }
+    // Encoded already, but for the parentheses Markdown ends a link on.
+    let url = styled_url(&m.path, opts.path_style)
+        .replace('(', "%28")
+        .replace(')', "%29");
+    format!("[{}]({})", text, url)
+}
+
+/// The line launchers show under a result's name.
+fn subtitle(m: &SearchResult) -> String {
+    match &m.note {
+        Some(note) => format!("{} · {} · {}", m.type_, m.docset, notes::one_line(note)),
+        None => format!("{} · {}", m.type_, m.docset),
+    }
+}
+
+/// An Alfred Script Filter item. `arg` is the page URL so the workflow
+/// can pass it straight to `Open URL`; the icon is the docset's own.
+fn alfred_item(m: &SearchResult, opts: &OutputOptions) -> serde_json::Value {
+    let url = styled_url(&m.path, opts.path_style);
+    let mut item = json!({
+        "uid": url,
+        "title": m.name,
+        "subtitle": subtitle(m),
+        "arg": url,
+        "autocomplete": m.name,
+        "quicklookurl": url,
+    });
+    if let Some(icon) = docset_icon(&m.path) {
+        item["icon"] = json!({ "path": icon });
+    }
+    item
+}
+
+/// A result as the props of a Raycast `List.Item`, with the `Action`s an
+/// extension renders for it: open the page, then copy its URL or the name.
+fn raycast_item(m: &SearchResult, opts: &OutputOptions) -> serde_json::Value {
+    let url = styled_url(&m.path, opts.path_style);
+    let mut item = json!({
+        "id": url,
+        "title": m.name,
+        "subtitle": m.type_,
+        "accessories": [{ "text": m.docset }],
+        "keywords": m.container.iter().collect::<Vec<_>>(),
+        "actions": [
+            { "type": "open", "title": "Open Documentation", "url": url },
+            { "type": "copy", "title": "Copy URL", "content": url },
+            { "type": "copy", "title": "Copy Name", "content": m.name },
+        ],
+    });
+    if let Some(note) = &m.note {
+        item["accessories"]
+            .as_array_mut()
+            .expect("accessories is an array")
+            .push(json!({ "text": notes::one_line(note), "tooltip": note }));
+    }
+    if let Some(icon) = docset_icon(&m.path) {
+        item["icon"] = json!({ "source": icon });
+    }
+    item
+}
+
+/// A result as the fields of a Ulauncher `ExtensionResultItem`, with its
+/// actions described for the extension to build: enter opens the page and
+/// alt-enter copies its URL.
+fn ulauncher_item(m: &SearchResult, opts: &OutputOptions) -> serde_json::Value {
+    let url = styled_url(&m.path, opts.path_style);
+    json!({
+        "name": m.name,
+        "description": subtitle(m),
+        "icon": docset_icon(&m.path),
+        "on_enter": { "action": "open_url", "url": url },
+        "on_alt_enter": { "action": "copy_to_clipboard", "text": url },
+    })
+}
+
+/// The docset's icon file, preferring the high resolution one.
+fn docset_icon(path: &Path) -> Option<PathBuf> {
+    let docset = related::docset_of(path)?;
+    ["icon@2x.png", "icon.png"]
+        .iter()
+        .map(|name| docset.join(name))
+        .find(|icon| slow_fs::is_file(icon))
+}
+
+/// Results gathered by group, groups in order of their best result and
+/// each group keeping its ranking.
+fn group(results: &[SearchResult], by: GroupBy) -> Vec<SearchResult> {
+    let mut groups: Vec<(String, Vec<&SearchResult>)> = Vec::new();
+    for m in results {
+        let label = by.label(m);
+        match groups.iter_mut().find(|(l, _)| *l == label) {
+            Some((_, members)) => members.push(m),
+            None => groups.push((label, vec![m])),
+        }
+    }
+    groups
+        .into_iter()
+        .flat_map(|(_, members)| members)
+        .cloned()
+        .collect()
+}
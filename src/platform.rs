@@ -0,0 +1,70 @@
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Switches the Windows console to ANSI escape processing and reports whether
+/// escapes (and the wide glyphs of icons) can be shown. Legacy `cmd.exe`
+/// consoles refuse, and would print the escapes as text. Other terminals are
+/// taken to understand them.
+pub fn enable_ansi() -> bool {
+    #[cfg(windows)]
+    {
+        ansi_term::enable_ansi_support().is_ok()
+    }
+    #[cfg(not(windows))]
+    {
+        true
+    }
+}
+
+/// `path` in the `\\?\` form Windows requires past `MAX_PATH`, for SQLite,
+/// which unlike `std::fs` doesn't convert long paths itself. Local drives and
+/// UNC shares (`\\server\share`) are both handled; shorter paths and other
+/// platforms are left alone.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        use std::path::{Component, PathBuf, Prefix};
+
+        const MAX_PATH: usize = 260;
+        if path.as_os_str().len() < MAX_PATH {
+            return Cow::Borrowed(path);
+        }
+        // Verbatim paths skip normalization, so `/` and `..` must go first.
+        let Ok(absolute) = std::path::absolute(path) else {
+            return Cow::Borrowed(path);
+        };
+        let verbatim = match absolute.components().next() {
+            Some(Component::Prefix(p)) => match p.kind() {
+                Prefix::Disk(_) => r"\\?\",
+                // `\\server\share` keeps one of its backslashes after `UNC`.
+                Prefix::UNC(..) => r"\\?\UNC",
+                _ => "",
+            },
+            _ => "",
+        };
+        let Some(text) = absolute.to_str().filter(|_| !verbatim.is_empty()) else {
+            return Cow::Owned(absolute);
+        };
+        let rest = if verbatim.ends_with("UNC") {
+            &text[1..]
+        } else {
+            text
+        };
+        Cow::Owned(PathBuf::from(format!("{}{}", verbatim, rest)))
+    }
+    #[cfg(not(windows))]
+    {
+        Cow::Borrowed(path)
+    }
+}
+
+/// `path` without a `\\?\` prefix, as browsers and users expect it:
+/// `\\?\C:\x` becomes `C:\x` and `\\?\UNC\server\share` becomes
+/// `\\server\share`.
+pub fn strip_verbatim(path: &str) -> Cow<'_, str> {
+    if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        Cow::Owned(format!(r"\\{}", share))
+    } else {
+        Cow::Borrowed(path.strip_prefix(r"\\?\").unwrap_or(path))
+    }
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use crate::search::SearchResult;
+
+/// Version of the JSON plugins are sent; bumped on incompatible changes.
+pub const API: u32 = 1;
+
+/// A `[plugins.<name>]` config table: an external program handed a search's
+/// results as JSON on stdin.
+///
+/// ```toml
+/// [plugins.jira]
+/// command = ["jira-links", "--project", "WEB"]
+/// # Rewrite the results of every search, rather than only printing them
+/// # when asked with `search --render jira`
+/// transform = true
+/// ```
+///
+/// The program reads `{"api": 1, "stage": "transform" | "render",
+/// "query": ..., "results": [...]}`, each result an object with `score`,
+/// `name`, `type_`, `path`, `docset` and `container`. A transform prints
+/// the same object back with its `results` changed; a renderer prints
+/// whatever it likes, which is passed through as is.
+///
+/// Transforms rewrite the results of `search`, `serve`, the language
+/// server and the C library alike.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Plugin {
+    /// Program and arguments, looked up on `PATH`.
+    pub command: Vec<String>,
+    pub transform: bool,
+}
+
+#[derive(Serialize)]
+struct Input<'a> {
+    api: u32,
+    stage: &'a str,
+    query: &'a str,
+    results: &'a [SearchResult],
+}
+
+#[derive(Deserialize)]
+struct Output {
+    results: Vec<SearchResult>,
+}
+
+/// The limit to score matches with before [`transform`]: none when a
+/// transform plugin is configured, so one dropping results leaves room for
+/// others, which [`transform`] then cuts to `limit`.
+pub fn scoring_limit(plugins: &BTreeMap<String, Plugin>, limit: Option<usize>) -> Option<usize> {
+    if plugins.values().any(|p| p.transform) {
+        None
+    } else {
+        limit
+    }
+}
+
+/// Runs the results through each transform plugin in turn, by name, then
+/// keeps the first `limit` of what they return. Matches scored with
+/// [`scoring_limit`] are cut to the limit only here.
+pub fn transform(
+    plugins: &BTreeMap<String, Plugin>,
+    query: &str,
+    mut results: Vec<SearchResult>,
+    limit: Option<usize>,
+) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+    for (name, plugin) in plugins.iter().filter(|(_, p)| p.transform) {
+        let stdout = run(name, plugin, "transform", query, &results, true)?;
+        let output: Output = serde_json::from_slice(&stdout)
+            .map_err(|e| format!("plugin '{}' printed invalid results: {}", name, e))?;
+        tracing::debug!(
+            plugin = %name,
+            before = results.len(),
+            after = output.results.len(),
+            "transformed results"
+        );
+        results = output.results;
+    }
+    if let Some(limit) = limit {
+        results.truncate(limit);
+    }
+    Ok(results)
+}
+
+/// Prints the results with the plugin `name` in place of an output format.
+pub fn render(
+    plugins: &BTreeMap<String, Plugin>,
+    name: &str,
+    query: &str,
+    results: &[SearchResult],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let plugin = plugins.get(name).ok_or_else(|| {
+        format!(
+            "Unknown plugin '{}'; define it under [plugins.{}] in the config file",
+            name, name
+        )
+    })?;
+    run(name, plugin, "render", query, results, false)?;
+    Ok(())
+}
+
+/// Feeds a plugin its input and waits for it, returning its stdout when
+/// `capture` is set and letting it write to ours otherwise.
+fn run(
+    name: &str,
+    plugin: &Plugin,
+    stage: &str,
+    query: &str,
+    results: &[SearchResult],
+    capture: bool,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (program, args) = plugin
+        .command
+        .split_first()
+        .ok_or_else(|| format!("plugin '{}' has no command", name))?;
+    let _span = tracing::debug_span!("plugin", plugin = %name, stage).entered();
+    let input = serde_json::to_vec(&Input {
+        api: API,
+        stage,
+        query,
+        results,
+    })?;
+    let mut child = Command::new(program)
+        .args(args)
+        .env("ZEAL_CLI_PLUGIN", name)
+        .stdin(Stdio::piped())
+        .stdout(if capture {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        })
+        .spawn()
+        .map_err(|e| format!("plugin '{}': cannot run {}: {}", name, program, e))?;
+    let mut stdin = child.stdin.take().ok_or("plugin stdin unavailable")?;
+    // Written from another thread so a plugin printing before it has read
+    // everything can't block both sides on full pipes.
+    let output = std::thread::scope(|scope| {
+        scope.spawn(move || {
+            // A plugin may stop reading early; its exit status tells.
+            let _ = stdin.write_all(&input);
+        });
+        child.wait_with_output()
+    })?;
+    if !output.status.success() {
+        return Err(format!("plugin '{}' exited with {}", name, output.status).into());
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::search::{self, Entry, SearchOptions};
+    use serde_json::json;
+    use std::path::{Path, PathBuf};
+
+    /// A transform that keeps only `kept`: it prints them from a file
+    /// written for it beforehand, whatever it is sent.
+    fn filtering(dir: &Path, kept: &[SearchResult]) -> BTreeMap<String, Plugin> {
+        let output = dir.join("kept.json");
+        std::fs::write(
+            &output,
+            serde_json::to_vec(&json!({ "results": kept })).unwrap(),
+        )
+        .unwrap();
+        let plugin = Plugin {
+            command: vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "cat > /dev/null; cat \"$0\"".to_string(),
+                output.display().to_string(),
+            ],
+            transform: true,
+        };
+        BTreeMap::from([("filter".to_string(), plugin)])
+    }
+
+    #[test]
+    fn a_filtering_transform_still_fills_the_limit() {
+        let entries: Vec<Entry> = ["Vec::push", "Vec::pop", "Vec::len", "Vec::drain"]
+            .iter()
+            .map(|name| Entry {
+                name: name.to_string(),
+                type_: "Method".to_string(),
+                path: PathBuf::from("/d/vec.html"),
+            })
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let limit = Some(2);
+        assert_eq!(scoring_limit(&BTreeMap::new(), limit), limit);
+        let plugins = filtering(dir.path(), &[]);
+        let opts = SearchOptions {
+            limit: scoring_limit(&plugins, limit),
+            ..Default::default()
+        };
+        let matches = search::match_entries(&entries, "vec", &opts);
+        assert_eq!(matches.len(), 4);
+
+        // The plugin drops the two best matches.
+        let plugins = filtering(dir.path(), &matches[2..]);
+        let results = transform(&plugins, "vec", matches.clone(), limit).unwrap();
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, [matches[2].name.as_str(), matches[3].name.as_str()]);
+    }
+}
@@ -0,0 +1,177 @@
+use base64::Engine;
+use clap::ValueEnum;
+use scraper::{ElementRef, Html};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::render::{self, Flavor, IMAGE_END, IMAGE_START};
+use crate::{archive, toc};
+
+/// Rendered Markdown a region is widened until it reaches, in bytes.
+const MIN_REGION: usize = 200;
+/// Base64 bytes per kitty graphics escape; the protocol's limit.
+const KITTY_CHUNK: usize = 4096;
+
+/// How images are drawn in the terminal.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageProtocol {
+    /// Whichever the terminal supports, none when output isn't a terminal
+    #[default]
+    Auto,
+    /// kitty graphics protocol (kitty, Ghostty, WezTerm), PNG only
+    Kitty,
+    /// iTerm2 inline images (iTerm2, WezTerm)
+    Iterm2,
+    /// Only the alt text
+    None,
+}
+
+impl ImageProtocol {
+    /// Resolves `Auto` from the variables terminals set for their programs.
+    fn resolve(self) -> ImageProtocol {
+        if self != ImageProtocol::Auto {
+            return self;
+        }
+        if !io::stdout().is_terminal() {
+            return ImageProtocol::None;
+        }
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+        let (term, program) = (var("TERM"), var("TERM_PROGRAM"));
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || program == "ghostty"
+        {
+            ImageProtocol::Kitty
+        } else if program == "iTerm.app" || program == "WezTerm" {
+            ImageProtocol::Iterm2
+        } else {
+            ImageProtocol::None
+        }
+    }
+}
+
+/// The HTML an entry points at: the element its `#fragment` names, widened
+/// to its parents until it says something, or the whole page.
+pub fn region(path: &Path) -> io::Result<String> {
+    archive::materialize(path)?;
+    let html = fs::read_to_string(toc::strip_fragment(path))?;
+    let doc = Html::parse_document(&html);
+    let fragment = path
+        .to_string_lossy()
+        .split_once('#')
+        .map(|(_, f)| f.to_string());
+    let anchor = fragment.and_then(|fragment| {
+        doc.root_element()
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .find(|el| {
+                let el = el.value();
+                el.id() == Some(fragment.as_str()) || el.attr("name") == Some(fragment.as_str())
+            })
+    });
+    let Some(mut el) = anchor else {
+        return Ok(html);
+    };
+    // Anchors are often empty `<a name>` tags or bare headings.
+    while render::element_to(el, Flavor::Markdown).trim().len() < MIN_REGION {
+        let Some(parent) = el.parent().and_then(ElementRef::wrap) else {
+            break;
+        };
+        if matches!(parent.value().name(), "body" | "html") {
+            break;
+        }
+        el = parent;
+    }
+    Ok(el.html())
+}
+
+/// Local files of the images in [`Flavor::Terminal`] text, relative `src`
+/// resolved against `page`. Remote images are left out.
+pub fn images(text: &str, page: &Path) -> Vec<PathBuf> {
+    text.lines()
+        .filter_map(|line| image_line(line, page))
+        .filter_map(|(file, _)| file)
+        .collect()
+}
+
+/// Splits a [`Flavor::Terminal`] image line into the image file, if it is
+/// a local one, and its alt text.
+fn image_line<'a>(line: &'a str, page: &Path) -> Option<(Option<PathBuf>, &'a str)> {
+    let (src, alt) = line.strip_prefix(IMAGE_START)?.split_once(IMAGE_END)?;
+    if src.contains("://") || src.starts_with("data:") {
+        return Some((None, alt));
+    }
+    let src = src.split(['#', '?']).next().unwrap_or(src);
+    let src = urlencoding::decode(src).map_or_else(|_| src.to_string(), |s| s.into_owned());
+    let file = toc::strip_fragment(page).parent()?.join(src);
+    Some((Some(file).filter(|f| f.is_file()), alt))
+}
+
+/// Prints the region of the page an entry points at as text, drawing its
+/// images with `protocol`, at most `columns` cells wide when given.
+pub fn show(path: &Path, protocol: ImageProtocol, columns: Option<u32>) -> io::Result<()> {
+    let text = render::html_to(&region(path)?, Flavor::Terminal);
+    let protocol = protocol.resolve();
+    let mut out = io::stdout().lock();
+    for line in text.lines() {
+        let Some((file, alt)) = image_line(line, path) else {
+            writeln!(out, "{}", line)?;
+            continue;
+        };
+        let drawn = match file {
+            Some(file) => draw(&mut out, &file, protocol, columns)?,
+            None => false,
+        };
+        if !drawn && !alt.is_empty() {
+            writeln!(out, "[{}]", alt)?;
+        }
+    }
+    out.flush()
+}
+
+/// Draws an image file, returning whether `protocol` could show it.
+fn draw(
+    out: &mut impl Write,
+    file: &Path,
+    protocol: ImageProtocol,
+    columns: Option<u32>,
+) -> io::Result<bool> {
+    let is_png = file
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let encoded = match protocol {
+        ImageProtocol::Kitty if is_png => base64_file(file)?,
+        ImageProtocol::Iterm2 => base64_file(file)?,
+        _ => return Ok(false),
+    };
+    if protocol == ImageProtocol::Kitty {
+        let width = columns.map(|c| format!(",c={}", c)).unwrap_or_default();
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            // Only the first escape carries the image's settings.
+            let settings = if i == 0 {
+                format!("f=100,a=T{},", width)
+            } else {
+                String::new()
+            };
+            out.write_all(format!("\x1b_G{}m={};", settings, more).as_bytes())?;
+            out.write_all(chunk)?;
+            out.write_all(b"\x1b\\")?;
+        }
+    } else {
+        let width = columns.map(|c| format!(";width={}", c)).unwrap_or_default();
+        write!(
+            out,
+            "\x1b]1337;File=inline=1;preserveAspectRatio=1{}:{}\x07",
+            width, encoded
+        )?;
+    }
+    writeln!(out)?;
+    Ok(true)
+}
+
+fn base64_file(file: &Path) -> io::Result<String> {
+    Ok(base64::engine::general_purpose::STANDARD.encode(fs::read(file)?))
+}
@@ -0,0 +1,20 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use std::borrow::Cow;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+/// A spinner on stderr showing `message` while something slow runs, unless
+/// `show` is off or stderr isn't a terminal, so redirected output only gets
+/// data and messages. Finish it with `finish_and_clear` before printing.
+pub fn spinner(show: bool, message: impl Into<Cow<'static, str>>) -> ProgressBar {
+    if !show || !std::io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new_spinner();
+    if let Ok(style) = ProgressStyle::with_template("{spinner} {msg}") {
+        bar.set_style(style);
+    }
+    bar.set_message(message);
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar
+}
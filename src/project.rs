@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+
+use crate::search;
+
+/// Files marking a project's language, with the docsets covering it. Names
+/// compare case-insensitively and as prefixes, so `Python` finds `Python_3`.
+const MARKERS: &[(&str, &[&str])] = &[
+    ("Cargo.toml", &["Rust"]),
+    ("package.json", &["JavaScript", "NodeJS"]),
+    ("tsconfig.json", &["TypeScript"]),
+    ("deno.json", &["TypeScript", "Deno"]),
+    ("pyproject.toml", &["Python"]),
+    ("setup.py", &["Python"]),
+    ("requirements.txt", &["Python"]),
+    ("go.mod", &["Go"]),
+    ("Gemfile", &["Ruby"]),
+    ("composer.json", &["PHP"]),
+    ("pom.xml", &["Java"]),
+    ("build.gradle", &["Java"]),
+    ("build.gradle.kts", &["Kotlin", "Java"]),
+    ("CMakeLists.txt", &["C++", "C", "CMake"]),
+    ("mix.exs", &["Elixir"]),
+    ("pubspec.yaml", &["Dart", "Flutter"]),
+    ("Package.swift", &["Swift"]),
+    ("stack.yaml", &["Haskell"]),
+    ("build.zig", &["Zig"]),
+];
+
+/// Installed docsets matching the project around `dir`: the markers of the
+/// nearest directory, walking up, that has any.
+pub fn detect(dir: &Path, installed: &[PathBuf]) -> Vec<PathBuf> {
+    for dir in dir.ancestors() {
+        let wanted: Vec<&str> = MARKERS
+            .iter()
+            .filter(|(marker, _)| dir.join(marker).exists())
+            .flat_map(|(_, docsets)| docsets.iter().copied())
+            .collect();
+        if wanted.is_empty() {
+            continue;
+        }
+        let mut found: Vec<PathBuf> = Vec::new();
+        for want in wanted {
+            let want = want.to_lowercase();
+            for path in installed {
+                let name = search::docset_name(path).to_lowercase();
+                if (name == want || name.starts_with(&format!("{}_", want)))
+                    && !found.contains(path)
+                {
+                    found.push(path.clone());
+                }
+            }
+        }
+        tracing::info!(project = %dir.display(), docsets = found.len(), "detected project");
+        return found;
+    }
+    Vec::new()
+}
@@ -0,0 +1,165 @@
+use regex::{Regex, RegexBuilder};
+
+/// Largest compiled `re:` pattern, in bytes. Queries come from editor
+/// buffers and clients of `serve`, so a pattern that would take too much
+/// memory is refused rather than built.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// A query with its operators taken out of the text:
+///
+/// - `type:Method` or `t:Method,Function` keeps entries of those types;
+/// - `^Vec` keeps entries whose name starts with `Vec`;
+/// - `re:^Vec::p` keeps entries whose name matches a regex, ignoring case;
+///   spaces are written `\s`.
+///
+/// The other terms are the text names are scored against. Parsing never
+/// panics, whatever the input: what isn't a well-formed operator is text,
+/// and an invalid regex is an error.
+#[derive(Debug, Default, Clone)]
+pub struct Query {
+    pub text: String,
+    pub types: Vec<String>,
+    pub filters: Vec<NameFilter>,
+}
+
+/// A condition on entry names besides the scored text.
+#[derive(Debug, Clone)]
+pub enum NameFilter {
+    /// The name starts with this, ignoring ASCII case.
+    Prefix(String),
+    Regex(Regex),
+}
+
+impl NameFilter {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NameFilter::Prefix(prefix) => name
+                .get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+            NameFilter::Regex(re) => re.is_match(name),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Query, Box<dyn std::error::Error>> {
+    let mut query = Query::default();
+    let mut text = Vec::new();
+    for term in input.split_whitespace() {
+        if let Some(pattern) = term.strip_prefix("re:").filter(|p| !p.is_empty()) {
+            let re = RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .size_limit(REGEX_SIZE_LIMIT)
+                .build()
+                .map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?;
+            query.filters.push(NameFilter::Regex(re));
+        } else if let Some(types) = type_operator(term) {
+            query.types.extend(
+                types
+                    .split(',')
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_string),
+            );
+        } else if let Some(prefix) = term.strip_prefix('^').filter(|p| !p.is_empty()) {
+            query.filters.push(NameFilter::Prefix(prefix.to_string()));
+        } else {
+            text.push(term);
+        }
+    }
+    query.text = text.join(" ");
+    Ok(query)
+}
+
+/// The value of a `type:` or `t:` term. Qualified names such as
+/// `type::Foo` are not one.
+fn type_operator(term: &str) -> Option<&str> {
+    let (key, value) = term.split_once(':')?;
+    let is_type = key == "type" || key == "t";
+    (is_type && !value.is_empty() && !value.starts_with(':')).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefixes(query: &Query) -> Vec<&str> {
+        query
+            .filters
+            .iter()
+            .filter_map(|f| match f {
+                NameFilter::Prefix(p) => Some(p.as_str()),
+                NameFilter::Regex(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn takes_operators_out_of_the_text() {
+        let query = parse("push t:Method,Function ^Vec re:p.sh$").unwrap();
+        assert_eq!(query.text, "push");
+        assert_eq!(query.types, ["Method", "Function"]);
+        assert_eq!(prefixes(&query), ["Vec"]);
+        assert!(query.filters[1].matches("Vec::PUSH"));
+    }
+
+    #[test]
+    fn empty_operators_are_text() {
+        let query = parse("type: t: ^ re: t:,").unwrap();
+        assert_eq!(query.text, "type: t: ^ re:");
+        assert!(query.types.is_empty());
+        assert!(query.filters.is_empty());
+        // Qualified names aren't type operators either.
+        assert_eq!(parse("type::Foo").unwrap().text, "type::Foo");
+    }
+
+    #[test]
+    fn quotes_and_dashes_are_text() {
+        let query = parse("\"unbalanced 'quote - --").unwrap();
+        assert_eq!(query.text, "\"unbalanced 'quote - --");
+        assert!(query.types.is_empty() && query.filters.is_empty());
+    }
+
+    #[test]
+    fn invalid_regexes_are_errors() {
+        assert!(parse("re:(").is_err());
+        assert!(parse("re:\\").is_err());
+        assert!(parse("re:a{99999999}").is_err());
+    }
+
+    #[test]
+    fn unicode_prefixes_never_split_a_char() {
+        let query = parse("^Äb t:Méthode").unwrap();
+        assert_eq!(query.types, ["Méthode"]);
+        let prefix = &query.filters[0];
+        assert!(prefix.matches("Äbc"));
+        // Shorter names and char boundaries inside the prefix don't panic.
+        assert!(!prefix.matches("Ä"));
+        assert!(!prefix.matches("aé"));
+        assert!(parse("^a").unwrap().filters[0].matches("abc"));
+        assert!(!parse("^a").unwrap().filters[0].matches("é"));
+        assert!(!parse("^é").unwrap().filters[0].matches("a"));
+    }
+
+    #[test]
+    fn never_panics_on_arbitrary_input() {
+        // Every string of up to three of these pieces, operators and their
+        // fragments among them.
+        let pieces = [
+            "", " ", "\t", "^", "re:", "t:", "type:", ":", ",", "\"", "'", "-", "(", "\\", "é",
+            "🦀", "\u{0}", "a",
+        ];
+        for a in pieces {
+            for b in pieces {
+                for c in pieces {
+                    let input = format!("{}{}{}", a, b, c);
+                    if let Ok(query) = parse(&input) {
+                        for filter in &query.filters {
+                            for name in ["", "a", "é", "🦀x", "Vec::push"] {
+                                filter.matches(name);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::i18n;
+use crate::search::{self, SearchResult};
+
+/// How results of several docsets are combined into one list.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeStrategy {
+    /// Sort everything by weighted score
+    #[default]
+    GlobalScore,
+    /// Take each docset's best result in turn, in the order docsets were given
+    Interleave,
+    /// Keep each docset's top results only, then sort by weighted score
+    PerDocsetTopk,
+}
+
+/// The `[ranking]` config table.
+///
+/// ```toml
+/// [ranking]
+/// strategy = "per-docset-topk"
+/// per_docset_limit = 5
+/// priority = ["Rust", "C++"]
+///
+/// [ranking.weights]
+/// Rust = 2.0
+/// JavaScript = 0.5
+///
+/// [ranking.boosts]
+/// exact = 100
+/// prefix = 40
+/// length = 1
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Ranking {
+    pub strategy: MergeStrategy,
+    /// Results kept per docset by `per-docset-topk`.
+    pub per_docset_limit: usize,
+    /// Score multipliers by docset name; unlisted docsets weigh 1.
+    pub weights: BTreeMap<String, f64>,
+    /// Docsets whose results come first among those of equal score, in
+    /// this order. The rest follow by name, as Zeal lists them, which is
+    /// also the order all docsets are searched in.
+    pub priority: Vec<String>,
+    /// Score added to names that match the query closely, on top of the
+    /// fuzzy score.
+    pub boosts: Boosts,
+}
+
+/// What names equal to, starting with or not much longer than the query
+/// score extra, so `map` ranks the `map` entry above
+/// `unordered_multimap_example_helper`, which the fuzzy score alone may
+/// not. Only fuzzy scores are boosted, and never below zero. Zero turns a
+/// boost off.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct Boosts {
+    /// For a name, or its member part, equal to the query ignoring case.
+    pub exact: i64,
+    /// For a name, or its member part, starting with the query.
+    pub prefix: i64,
+    /// Taken off per char a name is longer than the query, up to
+    /// [`Boosts::MAX_LENGTH_CHARS`] chars, so shorter names win ties.
+    pub length: i64,
+}
+
+impl Boosts {
+    /// Past this many extra chars, longer names aren't penalized further.
+    pub const MAX_LENGTH_CHARS: i64 = 20;
+}
+
+impl Default for Boosts {
+    fn default() -> Self {
+        Boosts {
+            exact: 100,
+            prefix: 40,
+            length: 1,
+        }
+    }
+}
+
+impl Default for Ranking {
+    fn default() -> Self {
+        Ranking {
+            strategy: MergeStrategy::default(),
+            per_docset_limit: 10,
+            weights: BTreeMap::new(),
+            priority: Vec::new(),
+            boosts: Boosts::default(),
+        }
+    }
+}
+
+impl Ranking {
+    pub fn weight(&self, docset: &str) -> f64 {
+        self.weights
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(docset))
+            .map_or(1.0, |(_, w)| *w)
+    }
+
+    /// Where the docset is in `priority`; unlisted docsets come after all.
+    pub fn priority(&self, docset: &str) -> usize {
+        self.priority
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(docset))
+            .unwrap_or(self.priority.len())
+    }
+
+    /// Sorts docsets by `priority`, then by name.
+    pub fn sort(&self, docsets: &mut [PathBuf]) {
+        docsets.sort_by(|a, b| {
+            let (a, b) = (search::docset_name(a), search::docset_name(b));
+            self.priority(&a)
+                .cmp(&self.priority(&b))
+                .then_with(|| i18n::compare(&a, &b))
+        });
+    }
+}
+
+/// Combines per-docset result lists, each already sorted, into one.
+pub fn merge(
+    mut per_docset: Vec<(String, Vec<SearchResult>)>,
+    query: &str,
+    strategy: MergeStrategy,
+    ranking: &Ranking,
+) -> Vec<SearchResult> {
+    if strategy == MergeStrategy::Interleave {
+        let mut lists: Vec<_> = per_docset.into_iter().map(|(_, r)| r.into_iter()).collect();
+        let mut merged = Vec::new();
+        loop {
+            let before = merged.len();
+            merged.extend(lists.iter_mut().filter_map(Iterator::next));
+            if merged.len() == before {
+                return merged;
+            }
+        }
+    }
+
+    // Sorting by score keeps this order among results of equal score.
+    per_docset.sort_by_key(|(docset, _)| ranking.priority(docset));
+    if strategy == MergeStrategy::PerDocsetTopk {
+        for (_, results) in &mut per_docset {
+            results.truncate(ranking.per_docset_limit);
+        }
+    }
+    // Boosts can take scores below zero, where a weight above 1 would sink
+    // them further, and equal scores stay equal however they're weighed:
+    // weights apply to scores shifted to start at 1.
+    let lowest = per_docset
+        .iter()
+        .flat_map(|(_, results)| results.iter().map(|r| r.score))
+        .min()
+        .unwrap_or(0);
+    let mut merged = Vec::new();
+    for (docset, mut results) in per_docset {
+        let weight = ranking.weight(&docset);
+        for r in &mut results {
+            r.score = ((r.score - lowest + 1) as f64 * weight).round() as i64;
+        }
+        merged.extend(results);
+    }
+    search::sort_matches(&mut merged, query);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(docset: &str, scores: &[i64]) -> (String, Vec<SearchResult>) {
+        let results = scores
+            .iter()
+            .enumerate()
+            .map(|(i, &score)| SearchResult {
+                score,
+                name: format!("{}{}", docset, i),
+                type_: "Function".into(),
+                path: PathBuf::new(),
+                docset: docset.into(),
+                container: None,
+                note: None,
+                breadcrumbs: None,
+                enrichment: None,
+            })
+            .collect();
+        (docset.to_string(), results)
+    }
+
+    fn names(merged: &[SearchResult]) -> Vec<&str> {
+        merged.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[test]
+    fn weights_lift_negative_and_equal_scores() {
+        let ranking = Ranking {
+            weights: BTreeMap::from([("Main".to_string(), 3.0)]),
+            ..Ranking::default()
+        };
+        let merge = |a, b| merge(vec![a, b], "q", MergeStrategy::GlobalScore, &ranking);
+
+        let merged = merge(results("Other", &[-4]), results("Main", &[-5]));
+        assert_eq!(names(&merged), ["Main0", "Other0"]);
+
+        let merged = merge(results("Other", &[0]), results("Main", &[0]));
+        assert_eq!(names(&merged), ["Main0", "Other0"]);
+
+        let merged = merge(results("Other", &[100]), results("Main", &[10]));
+        assert_eq!(names(&merged), ["Other0", "Main0"]);
+    }
+}
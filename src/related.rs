@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+use crate::search::{self, Entry};
+use crate::{slow_fs, toc};
+
+/// Docset directory an entry path lies in, for paths given without one.
+pub fn docset_of(path: &Path) -> Option<PathBuf> {
+    // On a slow mount, by name alone, rather than a stat of each ancestor.
+    let is_docset = |a: &Path| {
+        if slow_fs::enabled() {
+            search::has_docset_extension(a)
+        } else {
+            search::is_docset_dir(a)
+        }
+    };
+    path.ancestors()
+        .find(|a| is_docset(a))
+        .map(Path::to_path_buf)
+}
+
+/// Entries related to the one at `path` named `name`, at most `limit`: the
+/// other entries on its page (sections of the same guide), then the other
+/// members of its container (methods of the same class), each by name.
+pub fn related<'e>(entries: &'e [Entry], name: &str, path: &Path, limit: usize) -> Vec<&'e Entry> {
+    let page = toc::strip_fragment(path);
+    let container = search::container(name);
+    let others = || entries.iter().filter(|e| e.name != name || e.path != path);
+    let mut same_page: Vec<&Entry> = others()
+        .filter(|e| toc::strip_fragment(&e.path) == page)
+        .collect();
+    let mut same_container: Vec<&Entry> = others()
+        .filter(|e| container.is_some() && search::container(&e.name) == container)
+        .filter(|e| !same_page.iter().any(|p| std::ptr::eq(*p, *e)))
+        .collect();
+    same_page.sort_by(|a, b| a.name.cmp(&b.name));
+    same_container.sort_by(|a, b| a.name.cmp(&b.name));
+    same_page.extend(same_container);
+    same_page.truncate(limit);
+    same_page
+}
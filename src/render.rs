@@ -0,0 +1,360 @@
+use scraper::{ElementRef, Html, Node};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flavor {
+    Markdown,
+    Text,
+    /// Text with each image on a line of its own, as [`IMAGE_START`], its
+    /// `src`, [`IMAGE_END`] and its alt text, for a terminal to draw.
+    Terminal,
+}
+
+/// Opens an image line in [`Flavor::Terminal`] output.
+pub const IMAGE_START: char = '\u{E000}';
+/// Ends the `src` of an image line in [`Flavor::Terminal`] output.
+pub const IMAGE_END: char = '\u{E001}';
+
+/// Converts an HTML page to Markdown or plain text, keeping code blocks
+/// (with their language, when the page declares one) and tables intact.
+pub fn html_to(html: &str, flavor: Flavor) -> String {
+    let doc = Html::parse_document(html);
+    let root = doc.root_element();
+    element_to(root, flavor)
+}
+
+/// Like [`html_to`] for a single element and its descendants.
+pub fn element_to(el: ElementRef, flavor: Flavor) -> String {
+    let mut r = Renderer::new(flavor);
+    r.element(el);
+    r.finish()
+}
+
+struct Renderer {
+    out: String,
+    flavor: Flavor,
+}
+
+impl Renderer {
+    fn new(flavor: Flavor) -> Self {
+        Renderer {
+            out: String::new(),
+            flavor,
+        }
+    }
+
+    fn md(&self) -> bool {
+        self.flavor == Flavor::Markdown
+    }
+
+    fn finish(self) -> String {
+        let mut result = String::new();
+        let mut blank_run = 0;
+        for line in self.out.lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                blank_run += 1;
+                if blank_run > 1 || result.is_empty() {
+                    continue;
+                }
+            } else {
+                blank_run = 0;
+            }
+            result.push_str(line);
+            result.push('\n');
+        }
+        result.trim_end().to_string()
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn block_break(&mut self) {
+        if !self.out.is_empty() {
+            self.out.push_str("\n\n");
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        let mut collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if collapsed.is_empty() {
+            if !text.is_empty() && !self.at_line_start() && !self.out.ends_with(' ') {
+                self.out.push(' ');
+            }
+            return;
+        }
+        if text.starts_with(char::is_whitespace) && !self.at_line_start() {
+            collapsed.insert(0, ' ');
+        }
+        if text.ends_with(char::is_whitespace) {
+            collapsed.push(' ');
+        }
+        if self.at_line_start() || self.out.ends_with(' ') {
+            collapsed = collapsed.trim_start().to_string();
+        }
+        self.out.push_str(&collapsed);
+    }
+
+    fn children(&mut self, el: ElementRef) {
+        for child in el.children() {
+            match child.value() {
+                Node::Text(t) => self.text(t),
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Renders the children of `el` on their own and returns the result,
+    /// for constructs that need to post-process their content.
+    fn sub(&self, el: ElementRef) -> String {
+        let mut r = Renderer::new(self.flavor);
+        r.children(el);
+        r.finish()
+    }
+
+    fn inline_wrap(&mut self, el: ElementRef, marker: &str) {
+        let inner = self.sub(el);
+        let inner = inner.trim();
+        if inner.is_empty() {
+            return;
+        }
+        if !self.at_line_start() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        if self.md() {
+            self.out.push_str(&format!("{}{}{}", marker, inner, marker));
+        } else {
+            self.out.push_str(inner);
+        }
+    }
+
+    fn element(&mut self, el: ElementRef) {
+        let name = el.value().name();
+        match name {
+            "script" | "style" | "head" | "noscript" | "template" => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let title = self.sub(el).replace('\n', " ");
+                self.block_break();
+                if self.md() {
+                    self.out.push_str(&"#".repeat(level));
+                    self.out.push(' ');
+                }
+                self.out.push_str(title.trim());
+                self.block_break();
+            }
+            "p" | "div" | "section" | "article" | "main" | "header" | "footer" | "body"
+            | "html" | "dl" | "figure" | "details" | "summary" => {
+                self.block_break();
+                self.children(el);
+                self.block_break();
+            }
+            "br" => self.out.push('\n'),
+            "hr" => {
+                self.block_break();
+                self.out.push_str("---");
+                self.block_break();
+            }
+            "pre" => self.code_block(el),
+            "code" | "kbd" | "samp" | "tt" => {
+                let code = el.text().collect::<String>();
+                if self.md() {
+                    let fence = if code.contains('`') { "``" } else { "`" };
+                    self.out.push_str(&format!("{}{}{}", fence, code, fence));
+                } else {
+                    self.out.push_str(&code);
+                }
+            }
+            "strong" | "b" => self.inline_wrap(el, "**"),
+            "em" | "i" => self.inline_wrap(el, "*"),
+            "a" => {
+                let text = self.sub(el).replace('\n', " ");
+                match el.value().attr("href") {
+                    Some(href) if self.md() && !text.is_empty() => {
+                        self.out.push_str(&format!("[{}]({})", text.trim(), href))
+                    }
+                    _ => self.out.push_str(text.trim()),
+                }
+            }
+            "img" => {
+                let alt = el.value().attr("alt").unwrap_or("");
+                match el.value().attr("src") {
+                    Some(src) if self.md() => self.out.push_str(&format!("![{}]({})", alt, src)),
+                    Some(src) if self.flavor == Flavor::Terminal => self
+                        .out
+                        .push_str(&format!("\n{}{}{}{}\n", IMAGE_START, src, IMAGE_END, alt)),
+                    _ if !alt.is_empty() => self.out.push_str(&format!("[{}]", alt)),
+                    _ => {}
+                }
+            }
+            "ul" | "ol" => self.list(el, name == "ol"),
+            "table" => self.table(el),
+            "blockquote" => {
+                let inner = self.sub(el);
+                self.block_break();
+                let prefix = if self.md() { "> " } else { "  " };
+                for line in inner.lines() {
+                    self.out.push_str(prefix);
+                    self.out.push_str(line);
+                    self.out.push('\n');
+                }
+                self.block_break();
+            }
+            "dt" => {
+                let term = self.sub(el).replace('\n', " ");
+                self.block_break();
+                if self.md() {
+                    self.out.push_str(&format!("**{}**", term.trim()));
+                } else {
+                    self.out.push_str(term.trim());
+                }
+                self.out.push('\n');
+            }
+            "dd" => {
+                let inner = self.sub(el);
+                if !self.at_line_start() {
+                    self.out.push('\n');
+                }
+                for line in inner.lines() {
+                    self.out.push_str(if self.md() { ": " } else { "    " });
+                    self.out.push_str(line);
+                    self.out.push('\n');
+                }
+            }
+            _ => self.children(el),
+        }
+    }
+
+    fn code_block(&mut self, el: ElementRef) {
+        let code = el.text().collect::<String>();
+        let lang = code_language(el).unwrap_or_default();
+        self.block_break();
+        if self.md() {
+            let fence = if code.contains("```") { "~~~" } else { "```" };
+            self.out.push_str(&format!(
+                "{}{}\n{}\n{}",
+                fence,
+                lang,
+                code.trim_end(),
+                fence
+            ));
+        } else {
+            for line in code.trim_end().lines() {
+                self.out.push_str("    ");
+                self.out.push_str(line);
+                self.out.push('\n');
+            }
+        }
+        self.block_break();
+    }
+
+    fn list(&mut self, el: ElementRef, ordered: bool) {
+        self.block_break();
+        let items = el.child_elements().filter(|c| c.value().name() == "li");
+        for (n, item) in (1..).zip(items) {
+            let marker = if ordered {
+                format!("{}. ", n)
+            } else {
+                "- ".to_string()
+            };
+            let indent = " ".repeat(marker.len());
+            let inner = self.sub(item);
+            for (i, line) in inner.lines().enumerate() {
+                if i == 0 {
+                    self.out.push_str(&marker);
+                } else if !line.is_empty() {
+                    self.out.push_str(&indent);
+                }
+                self.out.push_str(line);
+                self.out.push('\n');
+            }
+        }
+        self.block_break();
+    }
+
+    fn table(&mut self, el: ElementRef) {
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut header = false;
+        for (i, tr) in el
+            .descendants()
+            .filter_map(ElementRef::wrap)
+            .filter(|e| e.value().name() == "tr")
+            .enumerate()
+        {
+            let cells: Vec<ElementRef> = tr
+                .child_elements()
+                .filter(|c| matches!(c.value().name(), "td" | "th"))
+                .collect();
+            if i == 0 {
+                header = cells.iter().all(|c| c.value().name() == "th");
+            }
+            rows.push(
+                cells
+                    .into_iter()
+                    .map(|c| self.sub(c).replace('\n', " ").replace('|', "\\|"))
+                    .collect(),
+            );
+        }
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        for row in &mut rows {
+            row.resize(columns, String::new());
+        }
+
+        self.block_break();
+        if self.md() {
+            if !header {
+                rows.insert(0, vec![String::new(); columns]);
+            }
+            for (i, row) in rows.iter().enumerate() {
+                self.out.push_str(&format!("| {} |\n", row.join(" | ")));
+                if i == 0 {
+                    self.out
+                        .push_str(&format!("|{}\n", " --- |".repeat(columns)));
+                }
+            }
+        } else {
+            let mut widths = vec![0; columns];
+            for row in &rows {
+                for (w, cell) in widths.iter_mut().zip(row) {
+                    *w = (*w).max(cell.chars().count());
+                }
+            }
+            for row in &rows {
+                let line: Vec<String> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, w)| format!("{:<width$}", cell, width = w))
+                    .collect();
+                self.out.push_str(&line.join("  "));
+                self.out.push('\n');
+            }
+        }
+        self.block_break();
+    }
+}
+
+/// Language of a code block from `class="language-x"`, `lang-x` or
+/// `highlight-x` on the `<pre>` or its `<code>` child, or a `data-lang` attribute.
+pub fn code_language(pre: ElementRef) -> Option<String> {
+    std::iter::once(pre)
+        .chain(pre.child_elements().filter(|c| c.value().name() == "code"))
+        .find_map(|el| {
+            if let Some(lang) = el.value().attr("data-lang") {
+                return Some(lang.to_string());
+            }
+            el.value().classes().find_map(|class| {
+                ["language-", "lang-", "highlight-"]
+                    .iter()
+                    .find_map(|prefix| class.strip_prefix(prefix))
+                    .map(str::to_string)
+            })
+        })
+}
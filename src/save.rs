@@ -0,0 +1,313 @@
+use regex::{Captures, Regex};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{archive, open, search, toc};
+
+/// Copies the page the entry at `path` lies on into `dir`, with the images,
+/// stylesheets and scripts it loads in a `<page>_files` directory beside
+/// it, as browsers save pages. The page's links to those files are
+/// rewritten to the copies, its links to pages saved in `dir` before to
+/// those, and its links to other pages of the docset to their `file://`
+/// URLs; pages saved in `dir` before that link to this one are pointed at
+/// it. A page saved before under the same file name from elsewhere, as
+/// docsets' many `index.html` pages are, is kept: this one is saved as
+/// `index-2.html`. With `hardlink` the files are linked rather than copied
+/// where the file system allows. Returns the saved page.
+pub fn save(
+    path: &Path,
+    dir: &Path,
+    hardlink: bool,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    archive::materialize(path)?;
+    let page = toc::strip_fragment(path);
+    let html = fs::read_to_string(&page).map_err(|e| format!("{}: {}", page.display(), e))?;
+    let base = page.parent().ok_or("page without a directory")?;
+    let docs_dir = page
+        .ancestors()
+        .find(|a| search::is_docset_dir(a))
+        .map(|docset| docset.join("Contents/Resources/Documents"))
+        .ok_or_else(|| format!("{} is not in a docset", page.display()))?;
+    let saved = saved_pages(dir);
+    let url = open::entry_url(&page);
+    let (file_name, stem) = saved_name(&page, &url, dir, &saved)?;
+    let files_dir = format!("{}_files", stem);
+
+    // Asset path in the docset to its path relative to the saved page.
+    let mut copies = BTreeMap::new();
+    for asset in archive::assets(&page) {
+        let Ok(relative) = asset.strip_prefix(&docs_dir) else {
+            continue;
+        };
+        if !asset.is_file() {
+            continue;
+        }
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        copies.insert(asset, format!("{}/{}", files_dir, relative));
+    }
+
+    let attribute = Regex::new(r#"(?i)\b(src|href)(\s*=\s*)("([^"]*)"|'([^']*)')"#).unwrap();
+    let rewritten = attribute.replace_all(&html, |caps: &Captures| {
+        let value = caps
+            .get(4)
+            .or_else(|| caps.get(5))
+            .map_or("", |v| v.as_str());
+        let quote = if caps.get(4).is_some() { '"' } else { '\'' };
+        match rewrite(value, base, &copies, &page, &file_name, &saved) {
+            Some(link) => format!("{}{}{}{}{}", &caps[1], &caps[2], quote, link, quote),
+            None => caps[0].to_string(),
+        }
+    });
+
+    fs::create_dir_all(dir)?;
+    for (asset, copy) in &copies {
+        let target = dir.join(copy);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        place(asset, &target, hardlink)?;
+    }
+    let target = dir.join(&file_name);
+    // Marked as browsers mark saved pages, so later saves know its source.
+    let marked = format!(
+        "<!-- saved from url=({:04}){} -->\n{}",
+        url.len(),
+        url,
+        rewritten
+    );
+    fs::write(&target, marked.as_bytes())?;
+    link_earlier(dir, &page, &target)?;
+    Ok(target)
+}
+
+/// The file name `page`, at `url`, is saved under in `dir`, and its stem:
+/// the one it was saved under before, else its own or, when a page saved
+/// from elsewhere has that, the first free one numbered from 2.
+fn saved_name(
+    page: &Path,
+    url: &str,
+    dir: &Path,
+    saved: &BTreeMap<String, String>,
+) -> Result<(String, String), Box<dyn std::error::Error>> {
+    let stem = page
+        .file_stem()
+        .ok_or("page without a file name")?
+        .to_string_lossy()
+        .into_owned();
+    let extension = page
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    if let Some(name) = saved.get(url) {
+        let stem = name.strip_suffix(&extension).unwrap_or(name).to_string();
+        return Ok((name.clone(), stem));
+    }
+    for n in 1.. {
+        let stem = if n == 1 {
+            stem.clone()
+        } else {
+            format!("{}-{}", stem, n)
+        };
+        let name = format!("{}{}", stem, extension);
+        let taken = dir.join(&name).exists() || dir.join(format!("{}_files", stem)).exists();
+        if !taken {
+            return Ok((name, stem));
+        }
+    }
+    unreachable!("some numbered name is free")
+}
+
+/// Pages saved in `dir` before, by the URL each was saved from, to their
+/// file names.
+fn saved_pages(dir: &Path) -> BTreeMap<String, String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return BTreeMap::new();
+    };
+    entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|path| is_html(path))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            Some((saved_from(&path)?, name))
+        })
+        .collect()
+}
+
+fn is_html(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("html") || ext.eq_ignore_ascii_case("htm"))
+}
+
+/// Points the links of pages saved in `dir` before at `saved`, the copy of
+/// `page`, where they link to `page`'s `file://` URL.
+fn link_earlier(dir: &Path, page: &Path, saved: &Path) -> io::Result<()> {
+    let file_name = saved.file_name().unwrap_or_default().to_string_lossy();
+    let link = encode_path(&file_name);
+    let url = Regex::new(&format!(
+        "{}([#\"'])",
+        regex::escape(&open::entry_url(page))
+    ))
+    .unwrap();
+    for other in fs::read_dir(dir)?.filter_map(|e| e.ok().map(|e| e.path())) {
+        if other == saved || !is_html(&other) {
+            continue;
+        }
+        let Ok(body) = fs::read_to_string(&other) else {
+            continue;
+        };
+        if url.is_match(&body) {
+            let relinked =
+                url.replace_all(&body, |caps: &Captures| format!("{}{}", link, &caps[1]));
+            fs::write(&other, relinked.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// The URL a page saved by [`save`] was saved from.
+fn saved_from(saved: &Path) -> Option<String> {
+    let body = fs::read_to_string(saved).ok()?;
+    let marker = body.lines().next()?.strip_prefix("<!-- saved from url=(")?;
+    let url = marker.split_once(')')?.1.strip_suffix(" -->")?;
+    Some(url.to_string())
+}
+
+/// A relative path as a link, each component percent-encoded.
+fn encode_path(path: &str) -> String {
+    let encoded: Vec<_> = path.split('/').map(urlencoding::encode).collect();
+    encoded.join("/")
+}
+
+/// The link `value` on `page` becomes in its copy, saved as `name` beside
+/// the `saved` pages, or `None` to keep it.
+fn rewrite(
+    value: &str,
+    base: &Path,
+    copies: &BTreeMap<PathBuf, String>,
+    page: &Path,
+    name: &str,
+    saved: &BTreeMap<String, String>,
+) -> Option<String> {
+    let remote =
+        value.contains("://") || value.starts_with("data:") || value.starts_with("mailto:");
+    if remote || value.starts_with('#') || value.starts_with('/') || value.is_empty() {
+        return None;
+    }
+    let end = value.find(['#', '?']).unwrap_or(value.len());
+    let (file, suffix) = value.split_at(end);
+    let decoded = urlencoding::decode(file).ok()?;
+    let local = archive::normalize(&base.join(decoded.as_ref()));
+    if let Some(copy) = copies.get(&local) {
+        return Some(format!("{}{}", encode_path(copy), suffix));
+    }
+    if !local.is_file() {
+        return None;
+    }
+    let fragment = suffix.find('#').map_or("", |i| &suffix[i..]);
+    // The page itself, or one saved beside it before, is linked where it
+    // is saved.
+    let saved_as = if local == page {
+        Some(name)
+    } else {
+        saved.get(&open::entry_url(&local)).map(String::as_str)
+    };
+    if let Some(name) = saved_as {
+        return Some(format!("{}{}", encode_path(name), fragment));
+    }
+    Some(open::entry_url(Path::new(&format!(
+        "{}{}",
+        local.display(),
+        fragment
+    ))))
+}
+
+/// Hard links `from` to `to` when asked and possible, else copies it.
+fn place(from: &Path, to: &Path, hardlink: bool) -> io::Result<()> {
+    if to.exists() {
+        fs::remove_file(to)?;
+    }
+    if hardlink && fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A docset with `a/index.html` and `b/index.html`, each loading its
+    /// own `style.css` and linking to the other.
+    fn docset(root: &Path) -> PathBuf {
+        let docs = root.join("Ex.docset/Contents/Resources/Documents");
+        for (dir, other) in [("a", "b"), ("b", "a")] {
+            fs::create_dir_all(docs.join(dir)).unwrap();
+            fs::write(docs.join(dir).join("style.css"), dir).unwrap();
+            let html = format!(
+                r#"<link rel="stylesheet" href="style.css"><a href="../{}/index.html#top">{}</a><a href='index.html#self'>me</a>"#,
+                other, other
+            );
+            fs::write(docs.join(dir).join("index.html"), html).unwrap();
+        }
+        docs
+    }
+
+    #[test]
+    fn pages_sharing_a_file_name_are_saved_apart() {
+        let root = tempfile::tempdir().unwrap();
+        let docs = docset(root.path());
+        let out = root.path().join("saved");
+
+        let a = save(&docs.join("a/index.html#top"), &out, false).unwrap();
+        assert_eq!(a, out.join("index.html"));
+        let b = save(&docs.join("b/index.html"), &out, false).unwrap();
+        assert_eq!(b, out.join("index-2.html"));
+        assert_eq!(
+            fs::read_to_string(out.join("index_files/a/style.css")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            fs::read_to_string(out.join("index-2_files/b/style.css")).unwrap(),
+            "b"
+        );
+        // Saving a page again replaces its copy.
+        assert_eq!(save(&docs.join("a/index.html"), &out, false).unwrap(), a);
+        assert!(!out.join("index-3.html").exists());
+
+        let saved_a = fs::read_to_string(&a).unwrap();
+        let saved_b = fs::read_to_string(&b).unwrap();
+        assert_eq!(
+            saved_from(&a).as_deref(),
+            Some(open::entry_url(&docs.join("a/index.html")).as_str())
+        );
+        assert!(saved_a.contains(r#"href="index_files/a/style.css""#));
+        assert!(saved_a.contains(r#"href="index-2.html#top""#));
+        assert!(saved_a.contains("href='index.html#self'"));
+        assert!(saved_b.contains(r#"href="index-2_files/b/style.css""#));
+        assert!(saved_b.contains(r#"href="index.html#top""#));
+        assert!(saved_b.contains("href='index-2.html#self'"));
+    }
+
+    #[test]
+    fn earlier_pages_are_linked_to_later_ones() {
+        let root = tempfile::tempdir().unwrap();
+        let docs = docset(root.path());
+        let out = root.path().join("saved");
+
+        let a = save(&docs.join("a/index.html"), &out, false).unwrap();
+        let b_url = open::entry_url(&docs.join("b/index.html"));
+        assert!(fs::read_to_string(&a)
+            .unwrap()
+            .contains(&format!(r#"href="{}#top""#, b_url)));
+        save(&docs.join("b/index.html"), &out, false).unwrap();
+        let saved_a = fs::read_to_string(&a).unwrap();
+        assert!(saved_a.contains(r#"href="index-2.html#top""#));
+        assert!(!saved_a.contains(&b_url));
+    }
+}
@@ -0,0 +1,1041 @@
+use clap::ValueEnum;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::config::Ignore;
+use crate::enrich::Enrichment;
+use crate::query::NameFilter;
+use crate::ranking::Boosts;
+use crate::{db, install, open, types};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SearchResult {
+    pub score: i64,
+    pub name: String,
+    pub type_: String,
+    pub path: PathBuf,
+    /// Name of the docset the entry belongs to, when known.
+    #[serde(default)]
+    pub docset: String,
+    /// Parent of the entry for hierarchical names, `Vec` for `Vec::push`.
+    #[serde(default)]
+    pub container: Option<String>,
+    /// The user's note on the entry, filled in when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// Where a guide or section sits, `["Book", "Ownership"]`, filled in
+    /// when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breadcrumbs: Option<Vec<String>>,
+    /// The signature, deprecation and version its page declares, filled in
+    /// when asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enrichment: Option<Enrichment>,
+}
+
+/// How entry names are compared with the query.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Skim-style fuzzy matching
+    #[default]
+    Fuzzy,
+    /// Case-insensitive substring, earlier and shorter matches first
+    Substring,
+    /// Case-insensitive whole-name equality
+    Exact,
+}
+
+/// Algorithm behind fuzzy matching. Only skim's is built in; nucleo's,
+/// which is faster, needs the `nucleo-matcher` crate this build lacks.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Matcher {
+    /// skim's V2 algorithm
+    #[default]
+    Skim,
+}
+
+/// Which entries a search scores, when a symbol index is built for the
+/// docset.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Prefilter {
+    /// The index for substring and exact searches, which it never drops a
+    /// match of; every entry for fuzzy ones
+    #[default]
+    Auto,
+    /// Every entry
+    None,
+    /// The index for fuzzy searches too: only entries containing every
+    /// term whole are scored, or every entry when none does
+    Trigram,
+}
+
+/// Entry fields a query is matched against.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchField {
+    Name,
+    Type,
+    /// The path below the docset's Documents directory
+    Path,
+}
+
+/// Shorthand for the [`MatchField`]s of names and document paths.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchIn {
+    Name,
+    /// The path below the docset's Documents directory, `tokio/sync/`
+    Path,
+    Both,
+}
+
+impl SearchIn {
+    pub fn fields(self) -> Vec<MatchField> {
+        match self {
+            SearchIn::Name => vec![MatchField::Name],
+            SearchIn::Path => vec![MatchField::Path],
+            SearchIn::Both => vec![MatchField::Name, MatchField::Path],
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct SearchOptions {
+    /// Alternative spellings of the query; an entry scores as its best match
+    /// against the query or any of these.
+    pub synonyms: Vec<String>,
+    pub mode: MatchMode,
+    pub matcher: Matcher,
+    /// Entries read from the symbol index rather than all of them.
+    pub prefilter: Prefilter,
+    /// Entry types to keep, any spelling; empty keeps all.
+    pub types: Vec<String>,
+    /// Keep only this many best matches.
+    pub limit: Option<usize>,
+    /// Fields each query term may match; empty means the name only.
+    pub fields: Vec<MatchField>,
+    /// Entries left out whatever they score, from the config.
+    pub ignore: Ignore,
+    /// Keep only entries on these pages.
+    pub within: Option<Within>,
+    /// An entry matches when any query term does, scoring as its best one.
+    pub any: bool,
+    /// When to stop fetching and scoring, keeping the best matches so far.
+    pub budget: Option<Arc<Budget>>,
+    /// Conditions names must meet besides matching, from the query's
+    /// operators.
+    pub filters: Vec<NameFilter>,
+    /// Extra score for names close to the query, from the config.
+    pub boosts: Boosts,
+    /// Also search the Dash anchors of pages the index has no entry for.
+    pub anchors: bool,
+}
+
+/// A time limit on a search. Past it, candidates are no longer fetched or
+/// scored, and the search returns the best matches it has, marked truncated.
+/// A search its client gave up on ends the same way once cancelled.
+pub struct Budget {
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    cancelled: Option<Arc<AtomicBool>>,
+    truncated: AtomicBool,
+}
+
+impl Budget {
+    pub fn new(timeout: Duration) -> Arc<Budget> {
+        Budget::cancellable(Some(timeout), None)
+    }
+
+    /// A budget of `timeout`, if any, that is also spent once `cancelled`
+    /// is set.
+    pub fn cancellable(
+        timeout: Option<Duration>,
+        cancelled: Option<Arc<AtomicBool>>,
+    ) -> Arc<Budget> {
+        Arc::new(Budget {
+            timeout,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            cancelled,
+            truncated: AtomicBool::new(false),
+        })
+    }
+
+    /// Whether the time is up or the search was cancelled, which marks it
+    /// truncated.
+    pub fn expired(&self) -> bool {
+        let cancelled = self
+            .cancelled
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::Relaxed));
+        if !cancelled
+            && self
+                .deadline
+                .is_none_or(|deadline| Instant::now() < deadline)
+        {
+            return false;
+        }
+        self.truncated.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Whether the search gave up on some candidates.
+    pub fn truncated(&self) -> bool {
+        self.truncated.load(Ordering::Relaxed)
+    }
+}
+
+// Options are keyed on by the result cache, which must not see the deadline.
+impl std::fmt::Debug for Budget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut budget = f.debug_struct("Budget");
+        if let Some(timeout) = &self.timeout {
+            budget.field("timeout", timeout);
+        }
+        budget.finish()
+    }
+}
+
+/// Pages results are restricted to: a page or directory below the docset's
+/// Documents directory, such as `book/`, or a glob over those paths, where
+/// `*` stays within a directory and `**` doesn't.
+#[derive(Debug, Clone)]
+pub struct Within(Regex);
+
+impl Within {
+    pub fn parse(pattern: &str) -> Result<Within, regex::Error> {
+        let pattern = pattern.trim_matches('/');
+        let mut re = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    re.push_str(".*");
+                }
+                '*' => re.push_str("[^/]*"),
+                '?' => re.push_str("[^/]"),
+                c => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        // A directory takes in everything below it.
+        re.push_str("(/.*)?$");
+        Regex::new(&re).map(Within)
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        let page = relative_path(path).split('#').next().unwrap_or_default();
+        self.0.is_match(page)
+    }
+}
+
+impl SearchOptions {
+    pub fn accepts_type(&self, raw: &str) -> bool {
+        self.types.is_empty() || self.types.iter().any(|t| types::matches(raw, t))
+    }
+
+    /// Whether everything matching a query also matches the query typed on,
+    /// so the next keystroke only needs the previous matches re-scored.
+    /// Synonyms can match entries the previous query did not, with `any`
+    /// another term widens the search, and typing on an operator such as
+    /// `t:Meth` changes what it keeps.
+    pub fn narrows_on_extension(&self) -> bool {
+        self.synonyms.is_empty()
+            && !self.any
+            && self.types.is_empty()
+            && self.filters.is_empty()
+            && self.mode.narrows_on_extension()
+    }
+
+    /// Whether the search's [`Budget`] is spent.
+    pub fn out_of_time(&self) -> bool {
+        self.budget.as_ref().is_some_and(|b| b.expired())
+    }
+
+    /// Whether `entry` passes the type and name filters and isn't ignored.
+    pub fn accepts(&self, entry: &Entry) -> bool {
+        self.accepts_type(&entry.type_)
+            && !self.ignore.ignores(&entry.name, &entry.type_)
+            && self.within.as_ref().is_none_or(|w| w.contains(&entry.path))
+            && self.filters.iter().all(|f| f.matches(&entry.name))
+    }
+}
+
+/// Docset name as used on the command line, e.g. `Rust` for `Rust.docset`.
+pub fn docset_name(docset_path: &Path) -> String {
+    docset_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// The docset's `Info.plist`, read once per process while it is unchanged;
+/// resolving names and keywords looks at every installed docset's.
+pub fn info_plist(docset_path: &Path) -> Option<Arc<plist::Dictionary>> {
+    type Cache = Mutex<HashMap<PathBuf, (SystemTime, Option<Arc<plist::Dictionary>>)>>;
+    static CACHE: OnceLock<Cache> = OnceLock::new();
+    let path = docset_path.join("Contents/Info.plist");
+    let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+    let cache = CACHE.get_or_init(Default::default);
+    if let Some((at, info)) = cache.lock().unwrap().get(&path) {
+        if *at == modified {
+            return info.clone();
+        }
+    }
+    let info = plist::from_file(&path).ok().map(Arc::new);
+    cache.lock().unwrap().insert(path, (modified, info.clone()));
+    info
+}
+
+/// Whether `name` refers to the docset, ignoring case: by its directory name
+/// or, like in Zeal, by the `name` of its `meta.json` or the `CFBundleName`
+/// of its `Info.plist`, which a renamed directory no longer matches.
+pub fn is_named(docset_path: &Path, name: &str) -> bool {
+    if docset_name(docset_path).eq_ignore_ascii_case(name) {
+        return true;
+    }
+    let bundle_name = || {
+        let info = info_plist(docset_path)?;
+        Some(info.get("CFBundleName")?.as_string()?.to_string())
+    };
+    install::read_meta(docset_path).is_some_and(|m| m.name.eq_ignore_ascii_case(name))
+        || bundle_name().is_some_and(|b| b.eq_ignore_ascii_case(name))
+}
+
+/// The docset `name` refers to, see [`is_named`]. A directory name match
+/// wins over another docset's metadata, and one matching in case over
+/// others that don't. When several docsets match alike, `name` is
+/// ambiguous, which is an error naming them.
+pub fn find_named<'a>(
+    docsets: &'a [PathBuf],
+    name: &str,
+) -> Result<Option<&'a PathBuf>, Box<dyn std::error::Error>> {
+    let by_dir: Vec<&PathBuf> = docsets
+        .iter()
+        .filter(|p| docset_name(p).eq_ignore_ascii_case(name))
+        .collect();
+    if let Some(exact) = by_dir.iter().find(|p| docset_name(p) == name) {
+        return Ok(Some(exact));
+    }
+    let mut found = if by_dir.is_empty() {
+        docsets.iter().filter(|p| is_named(p, name)).collect()
+    } else {
+        by_dir
+    };
+    // A symlinked docset is the docset it points at.
+    let mut seen = Vec::new();
+    found.retain(|p| {
+        let real = std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+        let new = !seen.contains(&real);
+        seen.push(real);
+        new
+    });
+    if found.len() > 1 {
+        let paths: Vec<String> = found.iter().map(|p| p.display().to_string()).collect();
+        return Err(format!(
+            "'{}' names several docsets: {}; give the directory name as it is cased",
+            name,
+            paths.join(", ")
+        )
+        .into());
+    }
+    Ok(found.pop())
+}
+
+/// Whether a directory entry is a docset bundle, `*.docset` in any case.
+pub fn is_docset_dir(path: &Path) -> bool {
+    has_docset_extension(path) && path.is_dir()
+}
+
+/// Whether a path is named like a docset bundle, without looking at it.
+pub fn has_docset_extension(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("docset"))
+}
+
+/// One row of a docset's `searchIndex`, with its path resolved on disk.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub name: String,
+    pub type_: String,
+    pub path: PathBuf,
+}
+
+pub fn load_entries(docset_path: &Path) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    load_entries_until(docset_path, None)
+}
+
+/// Rows read between checks of a [`Budget`].
+pub const ROWS_PER_CHECK: usize = 1024;
+
+/// [`load_entries`], stopping with the rows read so far once `budget` is spent.
+pub fn load_entries_until(
+    docset_path: &Path,
+    budget: Option<&Budget>,
+) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let docs_dir = docset_path.join("Contents/Resources/Documents");
+    let _span = tracing::debug_span!("query", docset = %docset_name(docset_path)).entered();
+    let entries = db::read(docset_path, |conn| {
+        let mut stmt = conn.prepare("SELECT name, type, path FROM searchIndex")?;
+        let mut rows = stmt.query([])?;
+        let mut entries = Vec::new();
+        while let Some(row) = rows.next()? {
+            if entries.len() % ROWS_PER_CHECK == 0 && budget.is_some_and(Budget::expired) {
+                break;
+            }
+            let name: String = row.get(0)?;
+            let type_: String = row.get(1)?;
+            let path: String = row.get(2)?;
+            entries.push(Entry {
+                name,
+                type_,
+                path: open::entry_path(&docs_dir, &path),
+            });
+        }
+        Ok(entries)
+    })?;
+    if entries.is_empty() && !budget.is_some_and(Budget::truncated) {
+        tracing::warn!("{} has an empty searchIndex", docset_path.display());
+    }
+    tracing::debug!(rows = entries.len(), "loaded searchIndex");
+    Ok(entries)
+}
+
+/// Whether the docset has an entry named exactly `name`, asked of the index
+/// directly rather than by loading every entry.
+pub fn has_entry(
+    docset_path: &Path,
+    name: &str,
+    ignore_case: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let sql = if ignore_case {
+        "SELECT EXISTS (SELECT 1 FROM searchIndex WHERE name = ?1 COLLATE NOCASE)"
+    } else {
+        "SELECT EXISTS (SELECT 1 FROM searchIndex WHERE name = ?1)"
+    };
+    db::read(docset_path, |conn| {
+        Ok(conn.query_row(sql, [name], |row| row.get(0))?)
+    })
+}
+
+/// Scores already loaded entries against `query`, best match first.
+///
+/// With a `limit`, only the best `limit` entries are ever cloned: each rayon
+/// worker keeps a bounded heap and the heaps are merged at the end.
+pub fn match_entries(entries: &[Entry], query: &str, opts: &SearchOptions) -> Vec<SearchResult> {
+    let Some(limit) = opts.limit else {
+        let mut matches = score_entries(entries, query, opts);
+        sort_matches(&mut matches, query);
+        return matches;
+    };
+    let _span = tracing::debug_span!("score", query, mode = ?opts.mode, limit).entered();
+    // Ties keep index order, as the stable sort of the unlimited path does.
+    let top = if query.is_empty() {
+        top_k(entries, query, limit, opts, |_, e| {
+            Some((Reverse(e.name.as_str()), 0))
+        })
+    } else {
+        top_k(entries, query, limit, opts, |scorer, e| {
+            scorer.score(e).map(|score| (score, score))
+        })
+    };
+    top.into_iter()
+        .map(|(score, i)| result(&entries[i], score))
+        .collect()
+}
+
+/// The matches of a query typed a keystroke at a time, kept by interactive
+/// modes to score the next keystroke against instead of every entry.
+pub struct Narrowed {
+    pub entries: Arc<Vec<Entry>>,
+    pub query: String,
+    /// Indices into `entries` of everything matching `query`.
+    pub candidates: Vec<usize>,
+}
+
+impl Narrowed {
+    /// Whether `query` extends this one over the same loaded entries.
+    ///
+    /// Queries with a member separator never do: they are scored by
+    /// container and member rather than as one term, so their matches need
+    /// not be among the shorter query's.
+    pub fn extends(&self, entries: &Arc<Vec<Entry>>, query: &str) -> bool {
+        let qualified = |q: &str| MEMBER_SEPARATORS.iter().any(|sep| q.contains(sep));
+        Arc::ptr_eq(&self.entries, entries)
+            && query.starts_with(&self.query)
+            && !qualified(&self.query)
+            && !qualified(query)
+    }
+}
+
+/// [`match_entries`] for a query typed a keystroke at a time. When it
+/// extends the query in `last`, only that query's matches are scored; a
+/// changed prefix scores every entry again. `last` is left holding this
+/// query's matches for the next keystroke.
+pub fn match_narrowing(
+    entries: &Arc<Vec<Entry>>,
+    query: &str,
+    opts: &SearchOptions,
+    last: &mut Option<Narrowed>,
+) -> Vec<SearchResult> {
+    let narrows = opts.narrows_on_extension();
+    let previous = last
+        .take()
+        .filter(|last| narrows && last.extends(entries, query));
+    let _span = tracing::debug_span!("narrow", query, narrowed = previous.is_some()).entered();
+    let candidates = match previous {
+        Some(last) => last.candidates,
+        None => (0..entries.len()).collect(),
+    };
+    let mut scored: Vec<(i64, usize)> = candidates
+        .par_iter()
+        .filter(|&&i| opts.accepts(&entries[i]))
+        .map_init(
+            || Scorer::new(query, opts),
+            |scorer, &i| scorer.score(&entries[i]).map(|score| (score, i)),
+        )
+        .flatten()
+        .collect();
+    tracing::debug!(
+        candidates = candidates.len(),
+        matches = scored.len(),
+        "scored candidates"
+    );
+    if narrows {
+        *last = Some(Narrowed {
+            entries: entries.clone(),
+            query: query.to_string(),
+            candidates: scored.iter().map(|&(_, i)| i).collect(),
+        });
+    }
+    // Best first, ties in index order, as `match_entries` has them.
+    if query.is_empty() {
+        scored.sort_by(|a, b| {
+            entries[a.1]
+                .name
+                .cmp(&entries[b.1].name)
+                .then(a.1.cmp(&b.1))
+        });
+    } else {
+        scored.sort_by_key(|&(score, i)| (Reverse(score), i));
+    }
+    if let Some(limit) = opts.limit {
+        scored.truncate(limit);
+    }
+    scored
+        .into_iter()
+        .map(|(score, i)| result(&entries[i], score))
+        .collect()
+}
+
+/// The entries matching `query` with their scores, in index order.
+pub fn score_entries(entries: &[Entry], query: &str, opts: &SearchOptions) -> Vec<SearchResult> {
+    let _span = tracing::debug_span!("score", query, mode = ?opts.mode).entered();
+    let matches: Vec<SearchResult> = entries
+        .par_iter()
+        .filter(|e| !opts.out_of_time() && opts.accepts(e))
+        .map_init(
+            || Scorer::new(query, opts),
+            |scorer, entry| Some(result(entry, scorer.score(entry)?)),
+        )
+        .flatten()
+        .collect();
+    tracing::debug!(
+        candidates = entries.len(),
+        matches = matches.len(),
+        synonyms = ?opts.synonyms,
+        "scored entries"
+    );
+    matches
+}
+
+fn result(entry: &Entry, score: i64) -> SearchResult {
+    SearchResult {
+        score,
+        name: entry.name.clone(),
+        type_: entry.type_.clone(),
+        path: entry.path.clone(),
+        docset: String::new(),
+        container: container(&entry.name).map(str::to_owned),
+        note: None,
+        breadcrumbs: None,
+        enrichment: None,
+    }
+}
+
+/// Separators docsets use between a container and its members, longest first.
+const MEMBER_SEPARATORS: &[&str] = &["::", "->", ".", "#"];
+
+/// The container part of a hierarchical entry name: `Vec` for `Vec::push`,
+/// `os.path` for `os.path.join()`, `None` for top-level names.
+///
+/// A parameter list is ignored, and names with spaces are taken to be prose
+/// such as guide titles rather than qualified names.
+pub fn container(name: &str) -> Option<&str> {
+    split_qualified(name).map(|(container, _)| container)
+}
+
+/// A hierarchical name split into its container and member, `("os.path",
+/// "join")` for `os.path.join()`; see [`container`].
+fn split_qualified(name: &str) -> Option<Qualified<'_>> {
+    let qualified = name.split('(').next().unwrap_or(name).trim_end();
+    if qualified.contains(char::is_whitespace) {
+        return None;
+    }
+    MEMBER_SEPARATORS
+        .iter()
+        .filter_map(|sep| qualified.rfind(sep).map(|at| (at, sep.len())))
+        .max_by_key(|&(at, _)| at)
+        .filter(|&(at, len)| at > 0 && at + len < qualified.len())
+        .map(|(at, len)| (&qualified[..at], &qualified[at + len..]))
+}
+
+/// A name's container and member.
+type Qualified<'a> = (&'a str, &'a str);
+
+/// A container with every member separator spelled `.`, for comparing
+/// containers of docsets that separate members differently.
+fn unify_separators(container: &str) -> String {
+    MEMBER_SEPARATORS
+        .iter()
+        .fold(container.to_string(), |c, sep| c.replace(sep, "."))
+}
+
+/// The parts of a query term between member separators, `["Vec", "push"]`
+/// for `Vec::push`.
+pub fn qualified_parts(term: &str) -> Vec<&str> {
+    let mut parts = vec![term];
+    for sep in MEMBER_SEPARATORS {
+        parts = parts.iter().flat_map(|p| p.split(sep)).collect();
+    }
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+/// Char positions of `name` where `term` first occurs, ignoring case.
+/// Lowercasing can change a char's length, `İ` becoming two chars, so each
+/// lowercased char keeps the position of the char it came from.
+fn substring_positions(name: &str, term: &str) -> Option<Vec<usize>> {
+    let lowered: Vec<(char, usize)> = name
+        .chars()
+        .enumerate()
+        .flat_map(|(at, c)| c.to_lowercase().map(move |l| (l, at)))
+        .collect();
+    let term: Vec<char> = term.chars().flat_map(char::to_lowercase).collect();
+    if term.is_empty() {
+        return None;
+    }
+    let start = lowered
+        .windows(term.len())
+        .position(|window| window.iter().map(|&(c, _)| c).eq(term.iter().copied()))?;
+    let (first, last) = (lowered[start].1, lowered[start + term.len() - 1].1);
+    Some((first..=last).collect())
+}
+
+/// The `k` entries with the greatest `rank`, best first, as `(score, index)`.
+///
+/// `rank` yields an ordering key and the score to report, or `None` for
+/// entries that don't match.
+fn top_k<'e, K, F>(
+    entries: &'e [Entry],
+    query: &str,
+    k: usize,
+    opts: &SearchOptions,
+    rank: F,
+) -> Vec<(i64, usize)>
+where
+    K: Ord + Send,
+    F: Fn(&Scorer, &'e Entry) -> Option<(K, i64)> + Sync,
+{
+    type Heap<K> = BinaryHeap<Reverse<(K, Reverse<usize>, i64)>>;
+    let push = |heap: &mut Heap<K>, item: (K, Reverse<usize>, i64)| {
+        if heap.len() < k {
+            heap.push(Reverse(item));
+        } else if heap.peek().is_some_and(|Reverse(worst)| item > *worst) {
+            heap.pop();
+            heap.push(Reverse(item));
+        }
+    };
+    if k == 0 {
+        return Vec::new();
+    }
+    let heap = entries
+        .par_iter()
+        .enumerate()
+        .filter(|(_, e)| !opts.out_of_time() && opts.accepts(e))
+        .fold(
+            || (Scorer::new(query, opts), Heap::<K>::new()),
+            |(scorer, mut heap), (i, e)| {
+                if let Some((key, score)) = rank(&scorer, e) {
+                    push(&mut heap, (key, Reverse(i), score));
+                }
+                (scorer, heap)
+            },
+        )
+        .map(|(_, heap)| heap)
+        .reduce(Heap::<K>::new, |mut a, b| {
+            for Reverse(item) in b {
+                push(&mut a, item);
+            }
+            a
+        });
+    let mut top: Vec<_> = heap.into_iter().map(|Reverse(item)| item).collect();
+    top.sort_by(|a, b| b.cmp(a));
+    top.into_iter()
+        .map(|(_, Reverse(i), score)| (score, i))
+        .collect()
+}
+
+/// A fuzzy matcher of the chosen [`Matcher`] algorithm.
+enum Fuzzy {
+    Skim(SkimMatcherV2),
+}
+
+impl Fuzzy {
+    fn new(matcher: Matcher) -> Self {
+        match matcher {
+            Matcher::Skim => Fuzzy::Skim(SkimMatcherV2::default()),
+        }
+    }
+
+    fn score(&self, field: &str, term: &str) -> Option<i64> {
+        match self {
+            Fuzzy::Skim(matcher) => matcher.fuzzy_match(field, term),
+        }
+    }
+
+    /// Char positions of `field` that `term` matched.
+    fn indices(&self, field: &str, term: &str) -> Option<Vec<usize>> {
+        match self {
+            Fuzzy::Skim(matcher) => matcher.fuzzy_indices(field, term).map(|(_, at)| at),
+        }
+    }
+}
+
+/// Added to qualified matches whose member is the one asked for, so
+/// `Vec.push` ranks `Vec::push` above `Vec::push_within_capacity`.
+const EXACT_MEMBER_BOOST: i64 = 50;
+
+/// Scores names against one query, for callers that pick their own candidates.
+pub struct Scorer<'a> {
+    matcher: Fuzzy,
+    query: &'a str,
+    opts: &'a SearchOptions,
+}
+
+impl<'a> Scorer<'a> {
+    pub fn new(query: &'a str, opts: &'a SearchOptions) -> Self {
+        Scorer {
+            matcher: Fuzzy::new(opts.matcher),
+            query,
+            opts,
+        }
+    }
+
+    /// Scores an entry, `None` if it doesn't match.
+    ///
+    /// Space separated terms must each match (in any order) and their scores
+    /// add up; `Exact` compares the whole query instead. With `any`, one
+    /// matching term is enough and the best term's score counts.
+    pub fn score(&self, entry: &Entry) -> Option<i64> {
+        if self.query.is_empty() {
+            // List all if no query
+            return Some(0);
+        }
+        std::iter::once(self.query)
+            .chain(self.opts.synonyms.iter().map(String::as_str))
+            .filter_map(|q| match self.opts.mode {
+                MatchMode::Exact if self.opts.any => q
+                    .split_whitespace()
+                    .any(|term| self.fields(entry).any(|f| f.eq_ignore_ascii_case(term)))
+                    .then_some(0),
+                MatchMode::Exact => (self
+                    .fields(entry)
+                    .any(|field| field.eq_ignore_ascii_case(q.trim()))
+                    || self.qualified_exact(entry, q.trim()))
+                .then_some(0),
+                _ => {
+                    let terms = q.split_whitespace().map(|term| {
+                        self.qualified(entry, term)
+                            .into_iter()
+                            .chain(self.fields(entry).filter_map(|f| self.term(f, term)))
+                            .max()
+                    });
+                    let score = if self.opts.any {
+                        terms.flatten().max()
+                    } else {
+                        terms.sum()
+                    };
+                    score.map(|score| match self.opts.mode {
+                        // Substring scores are already ranked by position
+                        // and length, and are negative.
+                        MatchMode::Fuzzy => (score + self.boost(entry, q)).max(0),
+                        _ => score,
+                    })
+                }
+            })
+            .max()
+    }
+
+    /// The [`Boosts`] an entry's name earns for `query` in fuzzy mode, when
+    /// names are matched.
+    fn boost(&self, entry: &Entry, query: &str) -> i64 {
+        let fields = &self.opts.fields;
+        if !fields.is_empty() && !fields.contains(&MatchField::Name) {
+            return 0;
+        }
+        let boosts = self.opts.boosts;
+        let query = query.trim().to_lowercase();
+        let name = entry.name.to_lowercase();
+        let member = split_qualified(&name).map_or(name.as_str(), |(_, member)| member);
+        let closeness = if name == query || member == query {
+            boosts.exact
+        } else if name.starts_with(&query) || member.starts_with(&query) {
+            boosts.prefix
+        } else {
+            0
+        };
+        let extra = name.chars().count() as i64 - query.chars().count() as i64;
+        closeness - boosts.length * extra.clamp(0, Boosts::MAX_LENGTH_CHARS)
+    }
+
+    /// Char positions of `name` the query matched, ascending, for
+    /// highlighting; empty when nothing or the whole name is compared.
+    pub fn positions(&self, name: &str) -> Vec<usize> {
+        let mut at: Vec<usize> = self
+            .query
+            .split_whitespace()
+            .filter_map(|term| match self.opts.mode {
+                MatchMode::Exact => None,
+                MatchMode::Substring => substring_positions(name, term),
+                MatchMode::Fuzzy => self.matcher.indices(name, term),
+            })
+            .flatten()
+            .collect();
+        at.sort_unstable();
+        at.dedup();
+        at
+    }
+
+    fn term(&self, field: &str, term: &str) -> Option<i64> {
+        match self.opts.mode {
+            MatchMode::Substring => substring_score(field, term),
+            _ => self.matcher.score(field, term),
+        }
+    }
+
+    /// Scores a qualified term such as `Vec.push` against a qualified entry
+    /// name part by part, so `::`, `.`, `#` and `->` match one another: the
+    /// container against the container and the member against the member,
+    /// an exact member counting extra. `None` unless both are qualified and
+    /// names are matched.
+    fn qualified(&self, entry: &Entry, term: &str) -> Option<i64> {
+        let ((container, member), (want_container, want_member)) =
+            self.qualified_pair(entry, term)?;
+        let container = self.term(
+            &unify_separators(container),
+            &unify_separators(want_container),
+        )?;
+        let member_score = self.term(member, want_member)?;
+        let exact = if member.eq_ignore_ascii_case(want_member) {
+            EXACT_MEMBER_BOOST
+        } else {
+            0
+        };
+        Some(container + member_score + exact)
+    }
+
+    /// Whether a qualified name equals a qualified query but for separators.
+    fn qualified_exact(&self, entry: &Entry, query: &str) -> bool {
+        self.qualified_pair(entry, query).is_some_and(
+            |((container, member), (want_container, want_member))| {
+                member.eq_ignore_ascii_case(want_member)
+                    && unify_separators(container)
+                        .eq_ignore_ascii_case(&unify_separators(want_container))
+            },
+        )
+    }
+
+    /// The entry's name and `term` split by [`split_qualified`], when names
+    /// are matched at all.
+    fn qualified_pair<'e, 't>(
+        &self,
+        entry: &'e Entry,
+        term: &'t str,
+    ) -> Option<(Qualified<'e>, Qualified<'t>)> {
+        let fields = &self.opts.fields;
+        if !fields.is_empty() && !fields.contains(&MatchField::Name) {
+            return None;
+        }
+        Some((split_qualified(&entry.name)?, split_qualified(term)?))
+    }
+
+    fn fields<'e>(&self, entry: &'e Entry) -> impl Iterator<Item = &'e str> + use<'e, '_> {
+        let fields = &self.opts.fields;
+        let all = fields.is_empty();
+        [
+            (all || fields.contains(&MatchField::Name)).then_some(entry.name.as_str()),
+            fields
+                .contains(&MatchField::Type)
+                .then_some(entry.type_.as_str()),
+            fields
+                .contains(&MatchField::Path)
+                .then(|| relative_path(&entry.path)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Part of an entry path below the docset's Documents directory.
+pub(crate) fn relative_path(path: &Path) -> &str {
+    let path = path.to_str().unwrap_or_default();
+    path.split_once("/Contents/Resources/Documents/")
+        .map_or(path, |(_, rest)| rest)
+}
+
+impl MatchMode {
+    /// Whether everything matching a query also matches every prefix of it,
+    /// so a longer query only needs to look at the previous matches.
+    pub fn narrows_on_extension(self) -> bool {
+        matches!(self, MatchMode::Fuzzy | MatchMode::Substring)
+    }
+}
+
+/// Ranks a substring hit by how early it starts and how little of the name
+/// is left over.
+fn substring_score(name: &str, query: &str) -> Option<i64> {
+    let pos = name.to_lowercase().find(&query.to_lowercase())?;
+    Some(-((pos * 100 + name.len()) as i64))
+}
+
+/// Ranks arbitrary lines against `query` as entry names are ranked, best
+/// first, returning the indexes of the matching lines. An empty query keeps
+/// every line in its order.
+pub fn rank_lines(lines: &[String], query: &str, opts: &SearchOptions) -> Vec<usize> {
+    if query.trim().is_empty() {
+        return (0..lines.len().min(opts.limit.unwrap_or(usize::MAX))).collect();
+    }
+    let entries: Vec<Entry> = lines
+        .iter()
+        .map(|line| Entry {
+            name: line.clone(),
+            type_: String::new(),
+            path: PathBuf::new(),
+        })
+        .collect();
+    let limit = opts.limit.unwrap_or(entries.len());
+    top_k(&entries, query, limit, opts, |scorer, e| {
+        scorer.score(e).map(|score| (score, score))
+    })
+    .into_iter()
+    .map(|(_, i)| i)
+    .collect()
+}
+
+pub fn find_matches(
+    docset_path: &Path,
+    query: &str,
+    opts: &SearchOptions,
+) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+    let mut matches = match_entries(&load_entries(docset_path)?, query, opts);
+    let docset = docset_name(docset_path);
+    for m in &mut matches {
+        m.docset.clone_from(&docset);
+    }
+    Ok(matches)
+}
+
+pub fn sort_matches(matches: &mut [SearchResult], query: &str) {
+    let _span = tracing::debug_span!("sort", results = matches.len()).entered();
+    if query.is_empty() {
+        matches.sort_by(|a, b| a.name.cmp(&b.name));
+    } else {
+        matches.sort_by_key(|m| Reverse(m.score));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_named_reports_docsets_named_alike() {
+        let dir = tempfile::tempdir().unwrap();
+        let docsets: Vec<PathBuf> = ["Foo.docset", "foo.docset", "Bar.docset"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        for docset in &docsets {
+            std::fs::create_dir(docset).unwrap();
+        }
+        let named = |name| find_named(&docsets, name).map_err(|e| e.to_string());
+        assert_eq!(named("foo").unwrap(), Some(&docsets[1]));
+        assert_eq!(named("bar").unwrap(), Some(&docsets[2]));
+        assert_eq!(named("baz").unwrap(), None);
+        assert!(named("FOO").unwrap_err().contains("several docsets"));
+    }
+
+    #[test]
+    fn qualified_queries_are_not_narrowed() {
+        let entries = Arc::new(Vec::new());
+        let narrowed = |query: &str| Narrowed {
+            entries: entries.clone(),
+            query: query.to_string(),
+            candidates: Vec::new(),
+        };
+        assert!(narrowed("Ve").extends(&entries, "Vec"));
+        assert!(!narrowed("Vec").extends(&entries, "Vec."));
+        assert!(!narrowed("Vec::").extends(&entries, "Vec::p"));
+        assert!(!narrowed("Vec").extends(&Arc::new(Vec::new()), "Vecd"));
+    }
+
+    #[test]
+    fn substring_positions_count_chars() {
+        assert_eq!(substring_positions("Größe", "ÖßE"), Some(vec![2, 3, 4]));
+        assert_eq!(
+            substring_positions("İstanbul::tan", "tan"),
+            Some(vec![2, 3, 4])
+        );
+        assert_eq!(
+            substring_positions("Vec::push", "push"),
+            Some(vec![5, 6, 7, 8])
+        );
+    }
+
+    #[test]
+    fn boosts_apply_to_fuzzy_scores_only() {
+        let entry = |name: &str| Entry {
+            name: name.to_string(),
+            type_: "Function".to_string(),
+            path: PathBuf::from("/d/a.html"),
+        };
+        let (map, long) = (entry("map"), entry("unordered_multimap_example_helper"));
+        let fuzzy = SearchOptions::default();
+        let scorer = Scorer::new("map", &fuzzy);
+        assert!(scorer.score(&map) > scorer.score(&long));
+        // The length penalty never takes a fuzzy score below zero.
+        let harsh = SearchOptions {
+            boosts: Boosts {
+                length: 1000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(Scorer::new("map", &harsh).score(&long), Some(0));
+
+        let substring = SearchOptions {
+            mode: MatchMode::Substring,
+            ..Default::default()
+        };
+        let scorer = Scorer::new("map", &substring);
+        assert_eq!(scorer.score(&map), substring_score("map", "map"));
+        assert_eq!(scorer.score(&long), substring_score(&long.name, "map"));
+    }
+}
@@ -0,0 +1,158 @@
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::Deserialize;
+use std::io::{Read, Write};
+
+use crate::download;
+use crate::net::Net;
+
+const RELEASES_URL: &str = "https://api.github.com/repos/fbosch/zeal-cli-rs/releases/latest";
+
+/// Base64 Ed25519 public key release binaries are signed with, compiled in
+/// by release builds from `ZEAL_CLI_RELEASE_KEY`.
+const RELEASE_KEY: Option<&str> = option_env!("ZEAL_CLI_RELEASE_KEY");
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Name of the release asset built for the running platform, e.g.
+/// `zeal-cli-x86_64-linux` or `zeal-cli-x86_64-windows.exe`.
+fn asset_name() -> String {
+    format!(
+        "zeal-cli-{}-{}{}",
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        std::env::consts::EXE_SUFFIX
+    )
+}
+
+fn parse_version(v: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = v.trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = parts.next()?.split('-').next()?.parse().ok()?;
+    Some((major, minor, patch))
+}
+
+fn download(net: &Net, url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut bytes = Vec::new();
+    net.agent_for(url)?
+        .get(url)
+        .call()?
+        .into_reader()
+        .read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Checks that `signature`, base64 as in a release's `<asset>.sig`, is
+/// `key`'s Ed25519 signature of `binary`.
+fn verify_signature(
+    key: &str,
+    binary: &[u8],
+    signature: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let key = base64.decode(key.trim())?;
+    let signature = base64.decode(signature.trim())?;
+    UnparsedPublicKey::new(&ED25519, key)
+        .verify(binary, &signature)
+        .map_err(|_| "The release signature doesn't match its binary".into())
+}
+
+/// Checks GitHub for a newer release and, unless `check_only` is set, replaces
+/// the running executable with the release binary after verifying its SHA-256
+/// checksum against the `<asset>.sha256` file published next to it, and its
+/// signature in `<asset>.sig` against the release key.
+pub fn self_update(net: &Net, check_only: bool) -> Result<(), Box<dyn std::error::Error>> {
+    net.ensure_online()?;
+    let release: Release = net
+        .agent_for(RELEASES_URL)?
+        .get(RELEASES_URL)
+        .call()?
+        .into_json()?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    let latest = release.tag_name.trim_start_matches('v');
+    match (parse_version(current), parse_version(latest)) {
+        (Some(c), Some(l)) if l <= c => {
+            println!("zeal-cli {} is up to date.", current);
+            return Ok(());
+        }
+        (_, None) => return Err(format!("Unrecognized release tag '{}'", release.tag_name).into()),
+        _ => {}
+    }
+
+    println!("Update available: {} -> {}", current, latest);
+    if check_only {
+        return Ok(());
+    }
+
+    let key = RELEASE_KEY
+        .ok_or("This build has no release key to verify updates with; download releases by hand")?;
+    let name = asset_name();
+    let find = |wanted: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == wanted)
+            .map(|a| a.browser_download_url.clone())
+            .ok_or_else(|| format!("Release {} has no asset '{}'", release.tag_name, wanted))
+    };
+    let binary_url = find(&name)?;
+    let checksum_url = find(&format!("{}.sha256", name))?;
+    let signature_url = find(&format!("{}.sig", name))?;
+
+    let binary = download(net, &binary_url)?;
+    let checksum = String::from_utf8(download(net, &checksum_url)?)?;
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .ok_or("Empty checksum file")?
+        .to_lowercase();
+    let actual = download::sha256_hex(&binary[..])?;
+    if actual != expected {
+        return Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            name, expected, actual
+        )
+        .into());
+    }
+    let signature = String::from_utf8(download(net, &signature_url)?)?;
+    verify_signature(key, &binary, &signature)?;
+
+    let mut tmp = tempfile::NamedTempFile::new()?;
+    tmp.write_all(&binary)?;
+    tmp.flush()?;
+    self_replace::self_replace(tmp.path())?;
+    eprintln!("Updated zeal-cli to {}", latest);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    #[test]
+    fn verifies_signatures_of_the_release_key_only() {
+        let base64 = base64::engine::general_purpose::STANDARD;
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let key = base64.encode(pair.public_key().as_ref());
+        let signature = base64.encode(pair.sign(b"binary").as_ref());
+
+        assert!(verify_signature(&key, b"binary", &signature).is_ok());
+        assert!(verify_signature(&key, b"tampered", &signature).is_err());
+        assert!(verify_signature(&key, b"binary", "not base64").is_err());
+    }
+}
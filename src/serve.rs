@@ -0,0 +1,1177 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::export::{self, ExportFormat};
+use crate::render::{self, Flavor};
+use crate::search::{self, Entry, Narrowed, Scorer, SearchOptions, SearchResult, Within};
+use crate::{archive, breadcrumbs, enrich, install, open, plugin, preview, query, toc};
+
+/// How often a `--socket` server checks whether it was asked to stop.
+const STOP_POLL: Duration = Duration::from_millis(100);
+
+/// Long-running search server answering JSON-lines requests.
+///
+/// Each request is one line, `{"id": 1, "method": "search", "params": {...}}`,
+/// answered by one line carrying the same `id` and either `result` or `error`.
+/// A search asked for in `chunk`s is answered by lines of `partial` results,
+/// each sent as soon as it is ready, before the line with the rest as its
+/// `result`.
+pub struct Server {
+    /// Docsets directories, earlier ones shadowing later ones.
+    roots: Vec<PathBuf>,
+    config: Config,
+    /// Loaded `searchIndex` rows per docset directory, dropped when the
+    /// docset changes on disk.
+    indices: Mutex<HashMap<PathBuf, Arc<Vec<Entry>>>>,
+    /// Present with `--watch`; see [`watch`].
+    watcher: Mutex<Option<RecommendedWatcher>>,
+    started: Instant,
+    /// Index lookups answered from `indices`, and those that loaded one.
+    hits: AtomicU64,
+    misses: AtomicU64,
+    /// Requests being answered, see [`Server::busy`].
+    in_flight: AtomicU64,
+    /// Searches and completions being answered, by an ID of their own, for
+    /// `status` to list.
+    running: Mutex<HashMap<u64, Running>>,
+    next_running: AtomicU64,
+    /// The socket [`run_socket`] listens on, which `shutdown` needs.
+    socket: Mutex<Option<PathBuf>>,
+    /// Set by `shutdown`: no more clients are accepted.
+    stopping: AtomicBool,
+}
+
+/// A request being answered; the server doesn't stop until it is dropped.
+pub(crate) struct Busy<'a>(&'a AtomicU64);
+
+impl Drop for Busy<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A search or completion being answered, see [`Server::run`].
+struct Running {
+    method: &'static str,
+    docset: String,
+    query: String,
+    started: Instant,
+}
+
+/// Takes a query off [`Server::running`] when it is answered.
+struct RunningGuard<'a> {
+    server: &'a Server,
+    id: u64,
+}
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.server.running.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Methods [`Server::call`] answers.
+pub const METHODS: &[&str] = &[
+    "search",
+    "complete",
+    "end_session",
+    "open",
+    "get_page",
+    "preview",
+    "list_docsets",
+    "status",
+    "invalidate",
+    "shutdown",
+];
+
+/// Methods that look into or stop the server, answered only to clients on
+/// stdin/stdout or the socket, see [`Connection`].
+const ADMIN_METHODS: &[&str] = &["status", "invalidate", "shutdown"];
+
+/// Wire format of requests and responses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    /// One JSON object per line.
+    Json,
+    /// msgpack-RPC; see [`Server::serve_msgpack`].
+    Msgpack,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    docset: String,
+    #[serde(default)]
+    query: String,
+    /// Results returned; the `serve` limit when left out, all when 0.
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Page, directory or glob results must lie in, see [`search::Within`].
+    #[serde(default)]
+    within: Option<String>,
+    /// Add the `breadcrumbs` of guides and sections.
+    #[serde(default)]
+    breadcrumbs: bool,
+    /// Add the `signature`, `deprecated` and `since` their pages declare.
+    #[serde(default)]
+    enrich: bool,
+    /// Milliseconds to score for before answering with the best matches so
+    /// far, flagged `truncated`.
+    #[serde(default)]
+    timeout_ms: Option<u64>,
+    /// Send the results this many at a time, so clients can show the first
+    /// while the rest are on their way.
+    #[serde(default)]
+    chunk: Option<usize>,
+}
+
+/// What a method answers with.
+struct Reply {
+    result: Value,
+    /// A time limit cut the search short; JSON-lines responses say so.
+    truncated: bool,
+}
+
+impl From<Value> for Reply {
+    fn from(result: Value) -> Self {
+        Reply {
+            result,
+            truncated: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CompleteParams {
+    session: String,
+    docset: String,
+    #[serde(default)]
+    query: String,
+    #[serde(default = "default_complete_limit")]
+    limit: usize,
+}
+
+/// An entry to act on: a `path` from earlier results, or the best match for
+/// `query` in `docset`.
+#[derive(Deserialize)]
+struct TargetParams {
+    #[serde(default)]
+    path: Option<PathBuf>,
+    #[serde(default)]
+    docset: Option<String>,
+    #[serde(default)]
+    query: Option<String>,
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+fn default_complete_limit() -> usize {
+    20
+}
+
+/// State kept per client connection.
+#[derive(Default)]
+pub struct Connection {
+    sessions: Mutex<HashMap<String, Arc<Session>>>,
+    /// Whether [`ADMIN_METHODS`] are answered. HTTP clients, whom only a
+    /// token may tell apart, aren't.
+    admin: bool,
+}
+
+impl Connection {
+    /// A client on stdin/stdout, which started the server, or on the
+    /// socket, which only users with access to its file can connect to.
+    fn local() -> Self {
+        Connection {
+            admin: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// One search-as-you-type session, see [`Server::complete`].
+#[derive(Default)]
+struct Session {
+    /// Bumped by every update; scoring for an older one gives up.
+    generation: AtomicU64,
+    last: Mutex<Option<Narrowed>>,
+}
+
+/// Scoring abandoned because a newer update for the same session arrived.
+#[derive(Debug)]
+struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Cancelled by a newer update")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl Server {
+    pub fn new(roots: Vec<PathBuf>, config: Config) -> Self {
+        Server {
+            roots,
+            config,
+            indices: Mutex::new(HashMap::new()),
+            watcher: Mutex::new(None),
+            started: Instant::now(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            in_flight: AtomicU64::new(0),
+            running: Mutex::new(HashMap::new()),
+            next_running: AtomicU64::new(0),
+            socket: Mutex::new(None),
+            stopping: AtomicBool::new(false),
+        }
+    }
+
+    /// Counts a request as in flight, from reading it to writing its answer.
+    pub(crate) fn busy(&self) -> Busy<'_> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        Busy(&self.in_flight)
+    }
+
+    /// Lists a query in `status` until the guard is dropped.
+    fn run(&self, method: &'static str, docset: &str, query: &str) -> RunningGuard<'_> {
+        let id = self.next_running.fetch_add(1, Ordering::Relaxed);
+        self.running.lock().unwrap().insert(
+            id,
+            Running {
+                method,
+                docset: docset.to_string(),
+                query: query.to_string(),
+                started: Instant::now(),
+            },
+        );
+        RunningGuard { server: self, id }
+    }
+
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub(crate) fn index(
+        &self,
+        docset_path: &Path,
+    ) -> Result<Arc<Vec<Entry>>, Box<dyn std::error::Error>> {
+        if let Some(entries) = self.indices.lock().unwrap().get(docset_path) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(entries.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // Load outside the lock so one slow docset doesn't stall other clients.
+        let entries = Arc::new(search::load_entries(docset_path)?);
+        if let Some(watcher) = self.watcher.lock().unwrap().as_mut() {
+            // Re-registering is harmless; a replaced docset needs a new watch.
+            let _ = watcher.watch(&resources_dir(docset_path), RecursiveMode::NonRecursive);
+        }
+        self.indices
+            .lock()
+            .unwrap()
+            .insert(docset_path.to_path_buf(), entries.clone());
+        Ok(entries)
+    }
+
+    /// Forgets the cached index of the docset directory containing `path`,
+    /// returning that directory if it was loaded.
+    fn invalidate(&self, path: &Path) -> Option<PathBuf> {
+        let (root, relative) = self
+            .roots
+            .iter()
+            .find_map(|root| Some((root, path.strip_prefix(root).ok()?)))?;
+        let docset = root.join(relative.components().next()?);
+        self.indices.lock().unwrap().remove(&docset).map(|_| docset)
+    }
+
+    fn find(&self, docset: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        for root in &self.roots {
+            if let Some(found) = install::find_installed(root, docset)? {
+                return Ok(found);
+            }
+        }
+        Err(format!("Docset '{}' not found", docset).into())
+    }
+
+    /// Searches a docset. Setting `cancelled` ends the search early, with
+    /// the results found by then. With `partial`, results asked for in
+    /// `chunk`s are passed to it a chunk at a time as they are annotated,
+    /// and only the last chunk is returned.
+    fn search(
+        &self,
+        params: Value,
+        cancelled: Option<Arc<AtomicBool>>,
+        partial: Option<&mut Partial>,
+    ) -> Result<Reply, Box<dyn std::error::Error>> {
+        let params: SearchParams = serde_json::from_value(params)?;
+        let _running = self.run("search", &params.docset, &params.query);
+        let timeout = params.timeout_ms.map(Duration::from_millis);
+        let budget = (timeout.is_some() || cancelled.is_some())
+            .then(|| search::Budget::cancellable(timeout, cancelled));
+        let docset_path = self.find(&params.docset)?;
+        let query = query::parse(&params.query)?;
+        let limit = match params.limit {
+            Some(0) => None,
+            Some(limit) => Some(limit),
+            None => self.config.limit_for("serve"),
+        };
+        let opts = SearchOptions {
+            synonyms: self
+                .config
+                .expand_query(&search::docset_name(&docset_path), &query.text),
+            types: query.types,
+            ignore: self.config.ignore_for(&search::docset_name(&docset_path)),
+            boosts: self.config.ranking.boosts,
+            limit: plugin::scoring_limit(&self.config.plugins, limit),
+            within: params.within.as_deref().map(Within::parse).transpose()?,
+            budget: budget.clone(),
+            filters: query.filters,
+            ..Default::default()
+        };
+        let matches = search::match_entries(&self.index(&docset_path)?, &query.text, &opts);
+        let mut matches = plugin::transform(&self.config.plugins, &query.text, matches, limit)?;
+        if let (Some(n), Some(partial)) = (params.chunk.filter(|n| *n > 0), partial) {
+            while matches.len() > n {
+                let mut chunk: Vec<SearchResult> = matches.drain(..n).collect();
+                annotate(&mut chunk, &params);
+                partial(search_results(&chunk))?;
+            }
+        }
+        annotate(&mut matches, &params);
+        Ok(Reply {
+            result: search_results(&matches),
+            truncated: budget.is_some_and(|b| b.truncated()),
+        })
+    }
+
+    fn target(&self, params: &TargetParams) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.target_entry(params).map(|(path, _)| path)
+    }
+
+    /// The entry path `params` name, with the entry's name when it was
+    /// searched for.
+    fn target_entry(
+        &self,
+        params: &TargetParams,
+    ) -> Result<(PathBuf, Option<String>), Box<dyn std::error::Error>> {
+        if let Some(path) = &params.path {
+            return Ok((path.clone(), None));
+        }
+        let (Some(docset), Some(query)) = (&params.docset, &params.query) else {
+            return Err("Expected either 'path' or 'docset' and 'query'".into());
+        };
+        let docset_path = self.find(docset)?;
+        let opts = SearchOptions {
+            synonyms: self
+                .config
+                .expand_query(&search::docset_name(&docset_path), query),
+            ignore: self.config.ignore_for(&search::docset_name(&docset_path)),
+            boosts: self.config.ranking.boosts,
+            limit: Some(1),
+            ..Default::default()
+        };
+        search::match_entries(&self.index(&docset_path)?, query, &opts)
+            .pop()
+            .map(|m| (m.path, Some(m.name)))
+            .ok_or_else(|| format!("No match for '{}' in '{}'", query, docset).into())
+    }
+
+    /// Opens an entry in the browser, answering with its URL. Like
+    /// `get_page`, only opens pages inside the docsets directories, besides
+    /// online entries.
+    fn open(&self, params: Value) -> Result<Value, Box<dyn std::error::Error>> {
+        let (path, name) = self.target_entry(&serde_json::from_value(params)?)?;
+        let url = open::entry_url(&path);
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            self.local_page(&path)?;
+        }
+        open::open_entry(&path, name.as_deref())?;
+        Ok(json!({ "path": path, "url": url }))
+    }
+
+    /// The page behind an entry path, refusing pages outside the docsets
+    /// directories, or the targets of symlinked docsets in them, so clients
+    /// can't read arbitrary files.
+    fn local_page(&self, path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        archive::materialize(path)?;
+        let page = toc::strip_fragment(path).canonicalize()?;
+        let inside = self
+            .roots
+            .iter()
+            .cloned()
+            .chain(self.installed())
+            .filter_map(|dir| dir.canonicalize().ok())
+            .any(|root| page.starts_with(root));
+        if !inside {
+            return Err(format!("{} is not inside a docsets directory", page.display()).into());
+        }
+        Ok(page)
+    }
+
+    /// The file of a page of `docset`, `page` being relative to its
+    /// Documents directory, for serving pages over HTTP.
+    pub(crate) fn page_file(
+        &self,
+        docset: &str,
+        page: &str,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let page = Path::new(page);
+        if !page
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+        {
+            return Err(format!("{} is not a page path", page.display()).into());
+        }
+        let path = self
+            .find(docset)?
+            .join("Contents/Resources/Documents")
+            .join(page);
+        self.local_page(&path)
+    }
+
+    /// The page behind an entry, as Markdown (default), plain text or HTML.
+    ///
+    /// Only pages inside the docsets directories are served.
+    fn get_page(&self, params: Value) -> Result<Value, Box<dyn std::error::Error>> {
+        let params: TargetParams = serde_json::from_value(params)?;
+        let path = self.target(&params)?;
+        let page = self.local_page(&path)?;
+        Ok(json!({
+            "path": path,
+            "url": open::entry_url(&path),
+            "content": export::convert(&page, params.format)?,
+        }))
+    }
+
+    /// The region of the page an entry points at as text, see
+    /// [`preview::region`], with the image files it shows for clients that
+    /// draw them themselves, e.g. in a preview pane.
+    fn preview(&self, params: Value) -> Result<Value, Box<dyn std::error::Error>> {
+        let params: TargetParams = serde_json::from_value(params)?;
+        let path = self.target(&params)?;
+        self.local_page(&path)?;
+        let region = preview::region(&path)?;
+        let drawable = render::html_to(&region, Flavor::Terminal);
+        Ok(json!({
+            "path": path,
+            "text": render::html_to(&region, Flavor::Text),
+            "images": preview::images(&drawable, &path),
+        }))
+    }
+
+    /// Search-as-you-type: each keystroke sends the whole query for a client
+    /// chosen `session` ID and gets the top `limit` results back.
+    ///
+    /// An update cancels scoring still running for the same session, and a
+    /// query extending the previous one only rescores the previous matches.
+    fn complete(
+        &self,
+        params: Value,
+        conn: &Connection,
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        let params: CompleteParams = serde_json::from_value(params)?;
+        let _running = self.run("complete", &params.docset, &params.query);
+        let session = conn
+            .sessions
+            .lock()
+            .unwrap()
+            .entry(params.session.clone())
+            .or_default()
+            .clone();
+        let generation = session.generation.fetch_add(1, Ordering::SeqCst) + 1;
+
+        let docset_path = self.find(&params.docset)?;
+        let entries = self.index(&docset_path)?;
+        let query = query::parse(&params.query)?;
+        let opts = SearchOptions {
+            synonyms: self
+                .config
+                .expand_query(&search::docset_name(&docset_path), &query.text),
+            types: query.types,
+            ignore: self.config.ignore_for(&search::docset_name(&docset_path)),
+            boosts: self.config.ranking.boosts,
+            filters: query.filters,
+            ..Default::default()
+        };
+        let narrowable = opts.narrows_on_extension();
+        let previous = session
+            .last
+            .lock()
+            .unwrap()
+            .take()
+            .filter(|last| narrowable && last.extends(&entries, &query.text));
+        let candidates: Box<dyn Iterator<Item = usize>> = match &previous {
+            Some(last) => Box::new(last.candidates.iter().copied()),
+            None => Box::new(0..entries.len()),
+        };
+
+        let scorer = Scorer::new(&query.text, &opts);
+        let mut scored = Vec::new();
+        for (n, i) in candidates.enumerate() {
+            if n % 256 == 0 && session.generation.load(Ordering::SeqCst) != generation {
+                return Err(Box::new(Cancelled));
+            }
+            if !opts.accepts(&entries[i]) {
+                continue;
+            }
+            if let Some(score) = scorer.score(&entries[i]) {
+                scored.push((score, i));
+            }
+        }
+        if session.generation.load(Ordering::SeqCst) != generation {
+            return Err(Box::new(Cancelled));
+        }
+        tracing::debug!(
+            session = params.session,
+            narrowed = previous.is_some(),
+            matches = scored.len(),
+            "completed"
+        );
+        if narrowable {
+            *session.last.lock().unwrap() = Some(Narrowed {
+                entries: entries.clone(),
+                query: query.text.clone(),
+                candidates: scored.iter().map(|&(_, i)| i).collect(),
+            });
+        }
+
+        let key = |&(score, i): &(i64, usize)| (std::cmp::Reverse(score), i);
+        if scored.len() > params.limit && params.limit > 0 {
+            scored.select_nth_unstable_by_key(params.limit - 1, key);
+        }
+        scored.truncate(params.limit);
+        if query.text.is_empty() {
+            scored.sort_by(|a, b| entries[a.1].name.cmp(&entries[b.1].name));
+        } else {
+            scored.sort_by_key(key);
+        }
+        Ok(Value::Array(
+            scored
+                .iter()
+                .map(|&(score, i)| {
+                    let e = &entries[i];
+                    json!({
+                        "name": e.name,
+                        "type": e.type_,
+                        "path": e.path,
+                        "url": open::entry_url(&e.path),
+                        "score": score,
+                    })
+                })
+                .collect(),
+        ))
+    }
+
+    /// Installed docsets across all roots, sorted by name; earlier roots
+    /// shadow later ones.
+    pub(crate) fn installed(&self) -> Vec<PathBuf> {
+        let mut docsets: Vec<PathBuf> = Vec::new();
+        for root in &self.roots {
+            let Ok(dir) = fs::read_dir(root) else {
+                continue;
+            };
+            let archived = archive::docsets(root);
+            for path in dir.filter_map(|e| e.ok().map(|e| e.path())).chain(archived) {
+                let name = search::docset_name(&path);
+                if search::is_docset_dir(&path)
+                    && !docsets.iter().any(|d| search::docset_name(d) == name)
+                {
+                    docsets.push(path);
+                }
+            }
+        }
+        docsets.sort_by_key(|p| search::docset_name(p));
+        docsets
+    }
+
+    fn list_docsets(&self) -> Result<Value, Box<dyn std::error::Error>> {
+        let names: Vec<String> = self
+            .installed()
+            .iter()
+            .map(|p| search::docset_name(p))
+            .collect();
+        Ok(json!(names))
+    }
+
+    /// Uptime, loaded docsets, index cache hits, requests in flight other
+    /// than this one and the searches and completions among them, longest
+    /// running first.
+    fn status(&self) -> Result<Value, Box<dyn std::error::Error>> {
+        let mut loaded: Vec<String> = self
+            .indices
+            .lock()
+            .unwrap()
+            .keys()
+            .map(|p| search::docset_name(p))
+            .collect();
+        loaded.sort();
+        let mut running: Vec<(Duration, Value)> = self
+            .running
+            .lock()
+            .unwrap()
+            .values()
+            .map(|r| {
+                let elapsed = r.started.elapsed();
+                let query = json!({
+                    "method": r.method,
+                    "docset": r.docset,
+                    "query": r.query,
+                    "elapsed_ms": elapsed.as_millis() as u64,
+                });
+                (elapsed, query)
+            })
+            .collect();
+        running.sort_by_key(|(elapsed, _)| std::cmp::Reverse(*elapsed));
+        Ok(json!({
+            "pid": std::process::id(),
+            "version": env!("CARGO_PKG_VERSION"),
+            "socket": *self.socket.lock().unwrap(),
+            "uptime_secs": self.started.elapsed().as_secs(),
+            "watching": self.watcher.lock().unwrap().is_some(),
+            "docsets": loaded,
+            "index_cache": {
+                "hits": self.hits.load(Ordering::Relaxed),
+                "misses": self.misses.load(Ordering::Relaxed),
+            },
+            "in_flight": self.in_flight.load(Ordering::SeqCst).saturating_sub(1),
+            "queries": running.into_iter().map(|(_, query)| query).collect::<Vec<_>>(),
+        }))
+    }
+
+    /// Drops the loaded indexes of the `docsets` named, or of all, so they
+    /// are read again; answers with the names of those that were loaded.
+    fn invalidate_indices(&self, params: Value) -> Result<Value, Box<dyn std::error::Error>> {
+        #[derive(Deserialize, Default)]
+        struct Params {
+            #[serde(default)]
+            docsets: Vec<String>,
+        }
+        let params: Params = if params.is_null() {
+            Params::default()
+        } else {
+            serde_json::from_value(params)?
+        };
+        let paths = params
+            .docsets
+            .iter()
+            .map(|docset| self.find(docset))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut indices = self.indices.lock().unwrap();
+        let mut dropped: Vec<String> = if paths.is_empty() {
+            indices.drain().map(|(path, _)| path).collect::<Vec<_>>()
+        } else {
+            paths
+                .into_iter()
+                .filter(|path| indices.remove(path).is_some())
+                .collect()
+        }
+        .iter()
+        .map(|path| search::docset_name(path))
+        .collect();
+        dropped.sort();
+        Ok(json!(dropped))
+    }
+
+    /// Stops a `--socket` server: it accepts no more clients and exits once
+    /// the requests in flight, this one included, are answered.
+    fn shutdown(&self) -> Result<Value, Box<dyn std::error::Error>> {
+        if self.socket.lock().unwrap().is_none() {
+            return Err("Only a server listening on --socket can be stopped".into());
+        }
+        self.stopping.store(true, Ordering::SeqCst);
+        Ok(json!({ "in_flight": self.in_flight.load(Ordering::SeqCst).saturating_sub(1) }))
+    }
+
+    /// Answers one request line with one response line. The lines of
+    /// chunked results before it are passed to `partial` as they are ready.
+    pub fn handle(
+        &self,
+        line: &str,
+        conn: &Connection,
+        partial: &mut dyn FnMut(String) -> std::io::Result<()>,
+    ) -> String {
+        match serde_json::from_str(line) {
+            Ok(request) => self.respond(request, conn, partial),
+            Err(e) => json!({ "id": null, "error": format!("Bad request: {}", e) }).to_string(),
+        }
+    }
+
+    /// Runs a method; `cancelled`, when given, stops a search once set,
+    /// and `partial` receives the chunks of one.
+    fn call(
+        &self,
+        method: &str,
+        params: Value,
+        conn: &Connection,
+        cancelled: Option<Arc<AtomicBool>>,
+        partial: Option<&mut Partial>,
+    ) -> Result<Reply, Box<dyn std::error::Error>> {
+        if ADMIN_METHODS.contains(&method) && !conn.admin {
+            return Err(
+                format!("'{}' is only answered on stdin/stdout or --socket", method).into(),
+            );
+        }
+        match method {
+            "search" => self.search(params, cancelled, partial),
+            "complete" => self.complete(params, conn).map(Reply::from),
+            "end_session" => end_session(params, conn).map(Reply::from),
+            "open" => self.open(params).map(Reply::from),
+            "get_page" => self.get_page(params).map(Reply::from),
+            "preview" => self.preview(params).map(Reply::from),
+            "list_docsets" => self.list_docsets().map(Reply::from),
+            "status" => self.status().map(Reply::from),
+            "invalidate" => self.invalidate_indices(params).map(Reply::from),
+            "shutdown" => self.shutdown().map(Reply::from),
+            other => Err(format!(
+                "Unknown method '{}'; expected one of {}",
+                other,
+                METHODS.join(", ")
+            )
+            .into()),
+        }
+    }
+
+    fn respond(
+        &self,
+        request: Request,
+        conn: &Connection,
+        partial: &mut dyn FnMut(String) -> std::io::Result<()>,
+    ) -> String {
+        let id = request.id.clone();
+        let mut chunk = |items: Value| partial(json!({ "id": id, "partial": items }).to_string());
+        match self.call(
+            &request.method,
+            request.params,
+            conn,
+            None,
+            Some(&mut chunk),
+        ) {
+            Ok(Reply { result, truncated }) => {
+                let mut last = json!({ "id": request.id, "result": result });
+                if truncated {
+                    last["truncated"] = json!(true);
+                }
+                last.to_string()
+            }
+            Err(e) if e.is::<Cancelled>() => {
+                json!({ "id": request.id, "cancelled": true }).to_string()
+            }
+            Err(e) => json!({ "id": request.id, "error": e.to_string() }).to_string(),
+        }
+    }
+
+    fn serve_stream(
+        &self,
+        reader: impl BufRead,
+        writer: impl Write + Send,
+        encoding: Encoding,
+    ) -> std::io::Result<()> {
+        match encoding {
+            Encoding::Json => self.serve_json(reader, writer),
+            Encoding::Msgpack => self.serve_msgpack(reader, writer),
+        }
+    }
+
+    fn serve_json(&self, reader: impl BufRead, writer: impl Write + Send) -> std::io::Result<()> {
+        let writer = Mutex::new(writer);
+        let conn = Connection::local();
+        let write = |response: String| -> std::io::Result<()> {
+            let mut writer = writer.lock().unwrap();
+            writeln!(writer, "{}", response)?;
+            writer.flush()
+        };
+        std::thread::scope(|scope| {
+            for line in reader.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let busy = self.busy();
+                match serde_json::from_str::<Request>(&line) {
+                    // Completions run on their own thread so the next
+                    // keystroke can be read, and cancel them, meanwhile.
+                    Ok(request) if request.method == "complete" => {
+                        let (write, conn) = (&write, &conn);
+                        scope.spawn(move || {
+                            let _busy = busy;
+                            let last = self.respond(request, conn, &mut |line| write(line));
+                            if let Err(e) = write(last) {
+                                tracing::warn!("Client write failed: {}", e);
+                            }
+                        });
+                    }
+                    _ => {
+                        let last = self.handle(&line, &conn, &mut |line| write(line));
+                        write(last)?;
+                        drop(busy);
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Speaks msgpack-RPC, as Neovim's `vim.rpcrequest` does: requests are
+    /// `[0, msgid, method, [params]]` and responses `[1, msgid, error, result]`.
+    ///
+    /// Every request runs on its own thread. A `cancel` notification,
+    /// `[2, "cancel", [msgid]]`, answers a pending request with a
+    /// `"Cancelled"` error at once and stops it if it is a search; its
+    /// result is dropped when it arrives.
+    fn serve_msgpack(
+        &self,
+        reader: impl BufRead,
+        writer: impl Write + Send,
+    ) -> std::io::Result<()> {
+        let mut reader = reader;
+        let writer = Mutex::new(writer);
+        let conn = Connection::local();
+        // Requests not answered yet, with the flags that cancel them.
+        let pending: Mutex<HashMap<u64, Arc<AtomicBool>>> = Mutex::default();
+        let respond = |msgid: u64, result: Result<Value, String>| -> std::io::Result<()> {
+            if pending.lock().unwrap().remove(&msgid).is_none() {
+                return Ok(());
+            }
+            let message = match result {
+                Ok(result) => json!([1, msgid, null, result]),
+                Err(e) => json!([1, msgid, e, null]),
+            };
+            let mut writer = writer.lock().unwrap();
+            rmp_serde::encode::write(&mut *writer, &message).map_err(std::io::Error::other)?;
+            writer.flush()
+        };
+        std::thread::scope(|scope| {
+            loop {
+                let message: Vec<Value> = match rmp_serde::decode::from_read(&mut reader) {
+                    Ok(message) => message,
+                    Err(rmp_serde::decode::Error::InvalidMarkerRead(e))
+                        if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+                    {
+                        return Ok(());
+                    }
+                    Err(e) => {
+                        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+                    }
+                };
+                // Neovim passes the call's arguments as an array; the methods
+                // take a single map.
+                let first_param = |params: Option<&Value>| match params {
+                    Some(Value::Array(args)) => args.first().cloned().unwrap_or(Value::Null),
+                    Some(other) => other.clone(),
+                    None => Value::Null,
+                };
+                match message.as_slice() {
+                    [kind, msgid, method, params] if kind == 0 => {
+                        let (Some(msgid), Some(method)) = (msgid.as_u64(), method.as_str()) else {
+                            tracing::warn!("Ignoring malformed msgpack-RPC request");
+                            continue;
+                        };
+                        let cancelled = Arc::new(AtomicBool::new(false));
+                        pending.lock().unwrap().insert(msgid, cancelled.clone());
+                        let (method, params) = (method.to_string(), first_param(Some(params)));
+                        let (respond, conn) = (&respond, &conn);
+                        let busy = self.busy();
+                        scope.spawn(move || {
+                            let _busy = busy;
+                            // msgpack-RPC responses have no room for `truncated`.
+                            let result =
+                                match self.call(&method, params, conn, Some(cancelled), None) {
+                                    Err(e) if e.is::<Cancelled>() => Err("Cancelled".to_string()),
+                                    result => result.map(|r| r.result).map_err(|e| e.to_string()),
+                                };
+                            if let Err(e) = respond(msgid, result) {
+                                tracing::warn!("Client write failed: {}", e);
+                            }
+                        });
+                    }
+                    [kind, method, params] if kind == 2 && method == "cancel" => {
+                        if let Some(msgid) = first_param(Some(params)).as_u64() {
+                            if let Some(cancelled) = pending.lock().unwrap().get(&msgid) {
+                                cancelled.store(true, Ordering::Relaxed);
+                            }
+                            respond(msgid, Err("Cancelled".to_string()))?;
+                        }
+                    }
+                    _ => tracing::warn!("Ignoring unexpected msgpack-RPC message"),
+                }
+            }
+        })
+    }
+}
+
+fn end_session(params: Value, conn: &Connection) -> Result<Value, Box<dyn std::error::Error>> {
+    #[derive(Deserialize)]
+    struct Params {
+        session: String,
+    }
+    let params: Params = serde_json::from_value(params)?;
+    if let Some(session) = conn.sessions.lock().unwrap().remove(&params.session) {
+        // Stop any scoring still running for it.
+        session.generation.fetch_add(1, Ordering::SeqCst);
+    }
+    Ok(Value::Null)
+}
+
+/// Serves requests on stdin/stdout until stdin closes.
+pub fn run_stdio(server: Arc<Server>, encoding: Encoding) -> std::io::Result<()> {
+    let stdin = std::io::stdin();
+    server.serve_stream(stdin.lock(), std::io::stdout(), encoding)
+}
+
+/// Serves requests on a Unix socket, one thread per client, until a
+/// `shutdown` request; the socket is removed once the requests in flight
+/// are answered. With `gzip` what is sent to clients is one gzip stream,
+/// flushed after every response so each can be decompressed as it arrives.
+#[cfg(unix)]
+pub fn run_socket(
+    server: Arc<Server>,
+    path: &Path,
+    encoding: Encoding,
+    gzip: bool,
+) -> std::io::Result<()> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::os::unix::net::UnixListener;
+
+    // A socket left behind by a previous run would make bind fail.
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    eprintln!("Listening on {}", path.display());
+    *server.socket.lock().unwrap() = Some(path.to_path_buf());
+    // Polled so a `shutdown` from a client is noticed between connections.
+    listener.set_nonblocking(true)?;
+    while !server.stopping.load(Ordering::SeqCst) {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(STOP_POLL);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        stream.set_nonblocking(false)?;
+        let server = server.clone();
+        std::thread::spawn(move || {
+            let reader = match stream.try_clone() {
+                Ok(s) => BufReader::new(s),
+                Err(e) => return eprintln!("Client error: {}", e),
+            };
+            let writer: Box<dyn Write + Send> = if gzip {
+                Box::new(GzEncoder::new(&stream, Compression::default()))
+            } else {
+                Box::new(&stream)
+            };
+            if let Err(e) = server.serve_stream(reader, writer, encoding) {
+                eprintln!("Client error: {}", e);
+            }
+        });
+    }
+    while server.in_flight.load(Ordering::SeqCst) > 0 {
+        std::thread::sleep(STOP_POLL);
+    }
+    fs::remove_file(path)?;
+    eprintln!("Stopped");
+    Ok(())
+}
+
+/// Sends one JSON-lines request to the server listening on `socket`, for
+/// `serve status` and the other client commands, returning its result.
+#[cfg(unix)]
+pub fn request(
+    socket: &Path,
+    method: &str,
+    params: Value,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    use std::os::unix::net::UnixStream;
+
+    let stream = UnixStream::connect(socket)
+        .map_err(|e| format!("No server listening on {}: {}", socket.display(), e))?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let request = json!({ "id": 1, "method": method, "params": params });
+    writeln!(&stream, "{}", request)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response: Value = serde_json::from_str(&line).map_err(|_| {
+        format!(
+            "The server on {} doesn't answer in plain JSON lines; is it serving --msgpack or --gzip?",
+            socket.display()
+        )
+    })?;
+    match response.get("error").and_then(Value::as_str) {
+        Some(error) => Err(error.into()),
+        None => Ok(response["result"].clone()),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn request(
+    _socket: &Path,
+    _method: &str,
+    _params: Value,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    Err("--socket needs Unix domain sockets, which this platform lacks".into())
+}
+
+#[cfg(not(unix))]
+pub fn run_socket(
+    _server: Arc<Server>,
+    _path: &Path,
+    _encoding: Encoding,
+    _gzip: bool,
+) -> std::io::Result<()> {
+    Err(std::io::Error::other(
+        "--socket needs Unix domain sockets; use stdio mode on this platform",
+    ))
+}
+
+/// Receives the chunks of a search as they are ready; see [`Server`].
+type Partial<'a> = dyn FnMut(Value) -> std::io::Result<()> + 'a;
+
+/// Adds what `params` asks for besides the matches themselves.
+fn annotate(matches: &mut [SearchResult], params: &SearchParams) {
+    if params.breadcrumbs {
+        breadcrumbs::annotate(matches);
+    }
+    if params.enrich {
+        enrich::annotate(matches);
+    }
+}
+
+fn search_results(matches: &[SearchResult]) -> Value {
+    Value::Array(
+        matches
+            .iter()
+            .map(|m| {
+                let mut result = json!({
+                    "name": m.name,
+                    "type": m.type_,
+                    "path": m.path,
+                    "url": open::entry_url(&m.path),
+                    "score": m.score,
+                });
+                if let Some(crumbs) = &m.breadcrumbs {
+                    result["breadcrumbs"] = json!(crumbs);
+                }
+                if let Some(Value::Object(fields)) = m.enrichment.as_ref().map(|e| json!(e)) {
+                    for (key, value) in fields {
+                        result[key] = value;
+                    }
+                }
+                result
+            })
+            .collect(),
+    )
+}
+
+fn resources_dir(docset_path: &Path) -> PathBuf {
+    docset_path.join("Contents").join("Resources")
+}
+
+/// Watches the docsets directories so installed, updated or removed docsets
+/// are picked up without a restart.
+///
+/// Only the top level and the `Contents/Resources` of docsets that have been
+/// loaded are watched: a recursive watch would need an inotify watch per
+/// Documents subdirectory.
+pub fn watch(server: &Arc<Server>) -> notify::Result<()> {
+    let (tx, rx) = std::sync::mpsc::channel::<notify::Result<notify::Event>>();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for root in &server.roots {
+        watcher.watch(root, RecursiveMode::NonRecursive)?;
+    }
+    *server.watcher.lock().unwrap() = Some(watcher);
+
+    let server = server.clone();
+    std::thread::spawn(move || {
+        for event in rx.into_iter().flatten() {
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in &event.paths {
+                if let Some(docset) = server.invalidate(path) {
+                    eprintln!("Docset changed: {}", search::docset_name(&docset));
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ask(server: &Server, conn: &Connection, method: &str) -> Value {
+        let line = json!({ "id": 1, "method": method }).to_string();
+        serde_json::from_str(&server.handle(&line, conn, &mut |_| Ok(()))).unwrap()
+    }
+
+    #[test]
+    fn answers_admin_methods_only_locally() {
+        let server = Server::new(Vec::new(), Config::default());
+        for method in ["status", "invalidate", "shutdown"] {
+            let error = ask(&server, &Connection::default(), method)["error"].clone();
+            assert!(
+                error.as_str().is_some_and(|e| e.contains("only answered")),
+                "{}: {}",
+                method,
+                error
+            );
+        }
+        let status = ask(&server, &Connection::local(), "status");
+        assert_eq!(status["result"]["pid"], json!(std::process::id()));
+        let invalidated = ask(&server, &Connection::local(), "invalidate");
+        assert!(invalidated.get("error").is_none(), "{}", invalidated);
+    }
+
+    #[test]
+    fn lists_running_queries_until_answered() {
+        let server = Server::new(Vec::new(), Config::default());
+        let queries = |server: &Server| {
+            ask(server, &Connection::local(), "status")["result"]["queries"].clone()
+        };
+        {
+            let _first = server.run("search", "Rust", "vec");
+            std::thread::sleep(Duration::from_millis(5));
+            let _second = server.run("complete", "Go", "fm");
+            let queries = queries(&server);
+            let listed: Vec<_> = queries
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|q| {
+                    (
+                        q["method"].as_str().unwrap(),
+                        q["docset"].as_str().unwrap(),
+                        q["query"].as_str().unwrap(),
+                    )
+                })
+                .collect();
+            assert_eq!(
+                listed,
+                [("search", "Rust", "vec"), ("complete", "Go", "fm")]
+            );
+        }
+        assert_eq!(queries(&server), json!([]));
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::cache;
+
+/// Set by `--slow-fs` or the config's `slow_fs`, for docsets directories on
+/// network mounts (SSHFS, NFS, SMB) where every `stat` is a round trip.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Answers of [`is_file`], so each path is only looked at once a run.
+static FILES: Mutex<Option<HashMap<PathBuf, bool>>> = Mutex::new(None);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// `path.is_file()`, asked of the file system once per path when slow.
+pub fn is_file(path: &Path) -> bool {
+    if !enabled() {
+        return path.is_file();
+    }
+    let mut files = FILES.lock().unwrap_or_else(|e| e.into_inner());
+    *files
+        .get_or_insert_with(HashMap::new)
+        .entry(path.to_path_buf())
+        .or_insert_with(|| path.is_file())
+}
+
+/// Whether a directory entry may be a docset's directory, from what
+/// listing the directory told rather than a `stat` of each entry. Symlinks
+/// are taken to point at directories.
+pub fn is_dir_entry(entry: &fs::DirEntry) -> bool {
+    entry
+        .file_type()
+        .is_ok_and(|t| t.is_dir() || t.is_symlink())
+}
+
+pub fn index_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("zeal-cli")
+        .join("indexes")
+}
+
+/// A local copy of the index at `db_path`, made again when the index's
+/// size or modification time changes, so queries read the local disk
+/// rather than the mount. An index with a write-ahead log is read where it
+/// is, as the copy would miss what the log holds.
+pub fn local_index(db_path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if db_path.with_extension("dsidx-wal").exists() {
+        return Err("index has a write-ahead log".into());
+    }
+    let meta = fs::metadata(db_path)?;
+    let mtime = meta
+        .modified()?
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_nanos();
+    let stamp = format!("{} {}", mtime, meta.len());
+    let key = cache::key(&[&db_path.to_string_lossy()]);
+    let copy = index_dir().join(format!("{}.dsidx", key));
+    let stamp_path = copy.with_extension("stamp");
+    if copy.is_file() && fs::read_to_string(&stamp_path).is_ok_and(|s| s == stamp) {
+        return Ok(copy);
+    }
+    let _span = tracing::debug_span!("copy_index", db = %db_path.display()).entered();
+    fs::create_dir_all(index_dir())?;
+    // Copied aside and renamed so readers never see half a copy.
+    let mut tmp = tempfile::NamedTempFile::new_in(index_dir())?;
+    std::io::copy(&mut fs::File::open(db_path)?, &mut tmp)?;
+    tmp.persist(&copy).map_err(|e| e.error)?;
+    fs::write(&stamp_path, stamp)?;
+    Ok(copy)
+}
@@ -0,0 +1,86 @@
+use rusqlite::types::ValueRef;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+use crate::db;
+
+/// Runs a read-only statement against a docset's index and prints the rows
+/// as an aligned table or a JSON array of objects.
+pub fn run(docset_path: &Path, sql: &str, json: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let (columns, table) = db::read(docset_path, |conn| {
+        let mut stmt = conn.prepare(sql)?;
+        if !stmt.readonly() {
+            return Err(
+                "Only read-only statements (SELECT, read-only PRAGMA, ...) are allowed".into(),
+            );
+        }
+
+        let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+        let mut rows = stmt.query([])?;
+        let mut table: Vec<Vec<Value>> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let mut values = Vec::with_capacity(columns.len());
+            for i in 0..columns.len() {
+                values.push(match row.get_ref(i)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(n) => Value::from(n),
+                    ValueRef::Real(f) => Value::from(f),
+                    ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t).into_owned()),
+                    ValueRef::Blob(b) => Value::from(format!("<{} byte blob>", b.len())),
+                });
+            }
+            table.push(values);
+        }
+        Ok((columns, table))
+    })?;
+
+    if json {
+        let objects: Vec<Value> = table
+            .into_iter()
+            .map(|values| Value::Object(columns.iter().cloned().zip(values).collect::<Map<_, _>>()))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&objects)?);
+        return Ok(());
+    }
+
+    let cells: Vec<Vec<String>> = table
+        .iter()
+        .map(|values| {
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Null => "NULL".to_string(),
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .collect()
+        })
+        .collect();
+    let mut widths: Vec<usize> = columns.iter().map(|c| c.chars().count()).collect();
+    for row in &cells {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.chars().count());
+        }
+    }
+    let print_row = |row: &[String]| {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, w)| format!("{:<width$}", cell, width = w))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+    print_row(&columns);
+    println!(
+        "{}",
+        widths
+            .iter()
+            .map(|w| "-".repeat(*w))
+            .collect::<Vec<_>>()
+            .join("  ")
+    );
+    for row in &cells {
+        print_row(row);
+    }
+    Ok(())
+}
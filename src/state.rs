@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::bookmarks;
+use crate::config::{self, Config};
+use crate::history::{self, Line};
+use crate::notes::{self, Notes};
+use crate::search::SearchResult;
+
+const STATE_VERSION: u32 = 2;
+
+/// Everything worth carrying to another machine, as one JSON document.
+///
+/// `config` holds the config file's tables (groups, synonyms, keywords,
+/// ranking, ...) as they are, so settings round-trip even when this version
+/// of zeal-cli doesn't know them. The search history, bookmarks and notes
+/// come along; state files of version 1 have none.
+#[derive(Serialize, Deserialize)]
+struct State {
+    version: u32,
+    #[serde(default)]
+    config: toml::Table,
+    #[serde(default)]
+    history: Vec<Line>,
+    #[serde(default)]
+    bookmarks: Vec<SearchResult>,
+    #[serde(default)]
+    notes: Notes,
+}
+
+fn config_path(override_path: Option<&Path>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    override_path
+        .map(Path::to_path_buf)
+        .or_else(config::default_config_path)
+        .ok_or_else(|| "No config directory on this platform".into())
+}
+
+fn read_table(path: &Path) -> Result<toml::Table, Box<dyn std::error::Error>> {
+    match fs::read_to_string(path) {
+        Ok(body) => Ok(body
+            .parse::<toml::Table>()
+            .map_err(|e| format!("{}: {}", path.display(), e))?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(toml::Table::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+    }
+}
+
+/// Writes the state as JSON to `out`, or stdout.
+pub fn export(
+    config_override: Option<&Path>,
+    out: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let history = match config::load(config_override)?.history.path() {
+        Some(path) => history::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => Vec::new(),
+    };
+    let state = State {
+        version: STATE_VERSION,
+        config: read_table(&config_path(config_override)?)?,
+        history,
+        bookmarks: bookmarks::load()?,
+        notes: notes::load()?,
+    };
+    let body = serde_json::to_string_pretty(&state)?;
+    match out {
+        Some(path) => fs::write(path, body + "\n")?,
+        None => println!("{}", body),
+    }
+    Ok(())
+}
+
+/// Merges an exported state into the local one; imported values win.
+/// Imported queries join the history in time order, and bookmarks and notes
+/// are added to the local ones.
+///
+/// The previous config file is kept next to it as `config.toml.bak`, since
+/// rewriting it drops its comments.
+pub fn import(
+    config_override: Option<&Path>,
+    file: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let state: State = serde_json::from_str(&fs::read_to_string(file)?)
+        .map_err(|e| format!("{}: {}", file.display(), e))?;
+    if state.version > STATE_VERSION {
+        return Err(format!(
+            "{} was written by a newer zeal-cli (state version {})",
+            file.display(),
+            state.version
+        )
+        .into());
+    }
+
+    let path = config_path(config_override)?;
+    let mut table = read_table(&path)?;
+    merge(&mut table, state.config);
+    let body = toml::to_string_pretty(&table)?;
+    // Refuse to write a config that would fail to load.
+    let config = toml::from_str::<Config>(&body)
+        .map_err(|e| format!("Imported config is invalid: {}", e))?;
+
+    if path.exists() {
+        fs::copy(&path, path.with_extension("toml.bak"))?;
+    } else if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, body)?;
+    eprintln!("Imported state into {}", path.display());
+
+    if let Some(path) = config.history.path().filter(|_| !state.history.is_empty()) {
+        let mut lines = history::read(&path)?;
+        let before = lines.len();
+        for line in state.history {
+            if !lines.contains(&line) {
+                lines.push(line);
+            }
+        }
+        // Interleaved by time, unless a plain history leaves that unknown.
+        if lines.iter().all(|l| l.time.is_some()) {
+            lines.sort_by_key(|l| l.time);
+        }
+        history::write(&path, &lines)?;
+        eprintln!(
+            "Imported {} queries into {}",
+            lines.len() - before,
+            path.display()
+        );
+    }
+    let added = bookmarks::add(&state.bookmarks)?;
+    if added > 0 {
+        eprintln!("Imported {} bookmarks", added);
+    }
+    let merged = notes::merge(state.notes)?;
+    if merged > 0 {
+        eprintln!("Imported {} notes", merged);
+    }
+    Ok(())
+}
+
+fn merge(into: &mut toml::Table, from: toml::Table) {
+    for (key, value) in from {
+        match (into.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(incoming)) => {
+                merge(existing, incoming)
+            }
+            (_, value) => {
+                into.insert(key, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use crate::db;
+
+/// Minimum normalized Damerau-Levenshtein similarity for a name to be suggested.
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// Finds up to `limit` index names closest to `query` by edit distance,
+/// for queries the fuzzy matcher found nothing for.
+pub fn suggestions(
+    docset_paths: &[PathBuf],
+    query: &str,
+    limit: usize,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let query = query.to_lowercase();
+    let mut scored: Vec<(f64, String)> = Vec::new();
+
+    for docset_path in docset_paths {
+        let names = db::read(docset_path, |conn| {
+            let mut stmt = conn.prepare("SELECT DISTINCT name FROM searchIndex")?;
+            let names = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            Ok(names.collect::<rusqlite::Result<Vec<String>>>()?)
+        })?;
+        for name in names {
+            let similarity = strsim::normalized_damerau_levenshtein(&query, &name.to_lowercase());
+            if similarity >= MIN_SIMILARITY {
+                scored.push((similarity, name));
+            }
+        }
+    }
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    let mut names: Vec<String> = Vec::new();
+    for (_, name) in scored {
+        if names.len() == limit {
+            break;
+        }
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
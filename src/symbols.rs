@@ -0,0 +1,324 @@
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::search::{self, Entry, MatchField, MatchMode, Prefilter, SearchOptions};
+use crate::{cache, open, platform};
+
+/// Shortest term the trigram tokenizer can look up.
+const MIN_TERM: usize = 3;
+
+pub fn index_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("zeal-cli")
+        .join("symbols")
+}
+
+/// Symbol index file for a docset, keyed on its `searchIndex`'s modification
+/// time so an updated docset falls back to a full scan until rebuilt.
+fn index_path(docset_path: &Path) -> Option<PathBuf> {
+    let key = cache::docset_key(docset_path, &[])?;
+    Some(index_dir().join(format!("{}.sqlite", key)))
+}
+
+/// Builds the FTS5 trigram index over a docset's entry names and paths,
+/// replacing earlier builds of it. Returns the number of entries indexed.
+pub fn build(docset_path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let path = index_path(docset_path).ok_or("docset has no searchIndex")?;
+    fs::create_dir_all(index_dir())?;
+    let _span = tracing::debug_span!("symbols_build", index = %path.display()).entered();
+    // Built aside and renamed so searches never see half an index.
+    let tmp = tempfile::Builder::new()
+        .suffix(".sqlite")
+        .tempfile_in(index_dir())?;
+    let source = Connection::open(platform::long_path(
+        &docset_path.join("Contents/Resources/docSet.dsidx"),
+    ))?;
+    let mut conn = Connection::open(tmp.path())?;
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE symbols USING fts5(
+             name, type UNINDEXED, path, tokenize = 'trigram'
+         );
+         CREATE TABLE source (docset TEXT NOT NULL);",
+    )?;
+    let tx = conn.transaction()?;
+    let mut count = 0;
+    {
+        let mut insert =
+            tx.prepare("INSERT INTO symbols (name, type, path) VALUES (?1, ?2, ?3)")?;
+        let mut stmt = source.prepare("SELECT name, type, path FROM searchIndex")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let (name, type_, path): (String, String, String) =
+                (row.get(0)?, row.get(1)?, row.get(2)?);
+            insert.execute(params![name, type_, path])?;
+            count += 1;
+        }
+        tx.execute(
+            "INSERT INTO source (docset) VALUES (?1)",
+            params![docset_path.to_string_lossy()],
+        )?;
+    }
+    tx.commit()?;
+    conn.execute_batch("INSERT INTO symbols (symbols) VALUES ('optimize')")?;
+    drop(conn);
+    tmp.persist(&path).map_err(|e| e.error)?;
+    prune(docset_path, &path);
+    Ok(count)
+}
+
+/// Removes a docset's symbol indexes, returning whether there were any.
+pub fn remove(docset_path: &Path) -> bool {
+    let before = indexes_of(docset_path);
+    for path in &before {
+        let _ = fs::remove_file(path);
+    }
+    !before.is_empty()
+}
+
+/// Removes indexes of earlier versions of the docset.
+fn prune(docset_path: &Path, keep: &Path) {
+    for path in indexes_of(docset_path) {
+        if path != keep {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Total size of the docset's indexes.
+pub fn size(docset_path: &Path) -> u64 {
+    indexes_of(docset_path)
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Index files no search will read again: those of docsets since updated
+/// or removed, and builds that never finished.
+pub fn orphans() -> Vec<PathBuf> {
+    let Ok(dir) = fs::read_dir(index_dir()) else {
+        return Vec::new();
+    };
+    dir.filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sqlite"))
+        .filter(|path| {
+            let source = Connection::open(path).and_then(|conn| {
+                conn.query_row("SELECT docset FROM source", [], |row| {
+                    row.get::<_, String>(0)
+                })
+            });
+            match source {
+                Ok(docset) => index_path(Path::new(&docset)).as_ref() != Some(path),
+                Err(_) => true,
+            }
+        })
+        .collect()
+}
+
+/// Every index built for the docset, current or not.
+fn indexes_of(docset_path: &Path) -> Vec<PathBuf> {
+    let Ok(dir) = fs::read_dir(index_dir()) else {
+        return Vec::new();
+    };
+    let docset = docset_path.to_string_lossy();
+    dir.filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sqlite"))
+        .filter(|path| {
+            let source = Connection::open(path).and_then(|conn| {
+                conn.query_row("SELECT docset FROM source", [], |row| {
+                    row.get::<_, String>(0)
+                })
+            });
+            source.is_ok_and(|s| s == docset)
+        })
+        .collect()
+}
+
+/// The FTS5 query for a search, when the index can narrow it down:
+/// substring or exact matching of names and paths, with every term long
+/// enough for a trigram lookup. Fuzzy matches need not contain any term
+/// whole, so fuzzy searches are narrowed only with [`Prefilter::Trigram`].
+/// Paths are indexed as the docset stores them, percent-encoded at times,
+/// so only plain terms are looked up in them.
+fn fts_query(query: &str, opts: &SearchOptions) -> Option<String> {
+    let fields: &[MatchField] = match opts.fields.as_slice() {
+        [] => &[MatchField::Name],
+        fields => fields,
+    };
+    let narrows = match opts.prefilter {
+        Prefilter::Auto => opts.mode != MatchMode::Fuzzy,
+        Prefilter::None => false,
+        Prefilter::Trigram => true,
+    };
+    if !narrows || fields.contains(&MatchField::Type) || !opts.synonyms.is_empty() {
+        return None;
+    }
+    let in_paths = fields.contains(&MatchField::Path);
+    let terms: Vec<&str> = match opts.mode {
+        MatchMode::Exact if !opts.any => vec![query.trim()],
+        _ => query.split_whitespace().collect(),
+    };
+    let plain = |t: &str| {
+        t.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./".contains(c))
+    };
+    if in_paths && !terms.iter().all(|t| plain(t)) {
+        return None;
+    }
+    // Qualified terms match names whatever separator they use, so only
+    // their parts can be looked up.
+    let terms: Vec<&str> = terms
+        .into_iter()
+        .flat_map(search::qualified_parts)
+        .collect();
+    if terms.is_empty() || terms.iter().any(|t| t.chars().count() < MIN_TERM) {
+        return None;
+    }
+    let columns = match (fields.contains(&MatchField::Name), in_paths) {
+        (true, true) => "{name path}",
+        (false, true) => "path",
+        _ => "name",
+    };
+    let phrases: Vec<String> = terms
+        .iter()
+        .map(|t| format!("{}: \"{}\"", columns, t.replace('"', "\"\"")))
+        .collect();
+    Some(phrases.join(if opts.any { " OR " } else { " AND " }))
+}
+
+/// Whether the index was built with paths indexed, which earlier builds
+/// didn't.
+fn indexes_paths(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE name = 'symbols'",
+        [],
+        |row| row.get::<_, String>(0),
+    )
+    .is_ok_and(|sql| !sql.contains("path UNINDEXED"))
+}
+
+/// Entries that may match `query`, read from the symbol index when one is
+/// built and useful for the search, else every entry of the docset. A
+/// fuzzy search the index finds nothing for scores every entry, as the
+/// terms may match without appearing whole.
+pub fn candidates(
+    docset_path: &Path,
+    query: &str,
+    opts: &SearchOptions,
+) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let index = fts_query(query, opts).and_then(|fts| {
+        let path = index_path(docset_path).filter(|p| p.is_file())?;
+        Some((path, fts))
+    });
+    let Some((path, fts)) = index else {
+        return search::load_entries_until(docset_path, opts.budget.as_deref());
+    };
+    let _span = tracing::debug_span!("symbols_query", index = %path.display(), fts).entered();
+    let docs_dir = docset_path.join("Contents/Resources/Documents");
+    let conn = Connection::open(&path)?;
+    if opts.fields.contains(&MatchField::Path) && !indexes_paths(&conn) {
+        tracing::debug!("symbol index predates indexed paths; rebuild it to use it");
+        return search::load_entries_until(docset_path, opts.budget.as_deref());
+    }
+    let mut stmt = conn.prepare("SELECT name, type, path FROM symbols WHERE symbols MATCH ?1")?;
+    let mut rows = stmt.query([&fts])?;
+    let mut entries = Vec::new();
+    while let Some(row) = rows.next()? {
+        if entries.len() % search::ROWS_PER_CHECK == 0 && opts.out_of_time() {
+            break;
+        }
+        let path: String = row.get(2)?;
+        entries.push(Entry {
+            name: row.get(0)?,
+            type_: row.get(1)?,
+            path: open::entry_path(&docs_dir, &path),
+        });
+    }
+    tracing::debug!(rows = entries.len(), "loaded symbol candidates");
+    if entries.is_empty() && opts.mode == MatchMode::Fuzzy && !opts.out_of_time() {
+        tracing::debug!("no symbol candidates for a fuzzy search; scoring every entry");
+        return search::load_entries_until(docset_path, opts.budget.as_deref());
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opts(mode: MatchMode) -> SearchOptions {
+        SearchOptions {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn substring_and_exact_searches_use_the_index() {
+        let substring = opts(MatchMode::Substring);
+        assert_eq!(
+            fts_query("push vec", &substring).as_deref(),
+            Some(r#"name: "push" AND name: "vec""#)
+        );
+        // Qualified terms are looked up by their parts.
+        assert_eq!(
+            fts_query("Vec::push", &opts(MatchMode::Exact)).as_deref(),
+            Some(r#"name: "Vec" AND name: "push""#)
+        );
+        let any = SearchOptions {
+            any: true,
+            ..opts(MatchMode::Substring)
+        };
+        assert_eq!(
+            fts_query("push pop", &any).as_deref(),
+            Some(r#"name: "push" OR name: "pop""#)
+        );
+        let trigram = SearchOptions {
+            prefilter: Prefilter::Trigram,
+            ..opts(MatchMode::Fuzzy)
+        };
+        assert_eq!(
+            fts_query("push", &trigram).as_deref(),
+            Some(r#"name: "push""#)
+        );
+        assert_eq!(
+            fts_query("a\"b\"c", &substring).as_deref(),
+            Some(r#"name: "a""b""c""#)
+        );
+    }
+
+    #[test]
+    fn searches_the_index_cannot_narrow_scan_every_entry() {
+        // Fuzzy matches need not contain a term whole.
+        assert_eq!(fts_query("push", &opts(MatchMode::Fuzzy)), None);
+        let none = SearchOptions {
+            prefilter: Prefilter::None,
+            ..opts(MatchMode::Substring)
+        };
+        assert_eq!(fts_query("push", &none), None);
+        // Trigrams need three chars.
+        assert_eq!(fts_query("Vec::p", &opts(MatchMode::Substring)), None);
+        let synonyms = SearchOptions {
+            synonyms: vec!["append".to_string()],
+            ..opts(MatchMode::Substring)
+        };
+        assert_eq!(fts_query("push", &synonyms), None);
+        let types = SearchOptions {
+            fields: vec![MatchField::Type],
+            ..opts(MatchMode::Substring)
+        };
+        assert_eq!(fts_query("method", &types), None);
+        // Paths may be percent-encoded where the query isn't.
+        let paths = SearchOptions {
+            fields: vec![MatchField::Name, MatchField::Path],
+            ..opts(MatchMode::Substring)
+        };
+        assert_eq!(fts_query("a b%c", &paths), None);
+        assert_eq!(
+            fts_query("tokio/sync", &paths).as_deref(),
+            Some(r#"{name path}: "tokio/sync""#)
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{archive, search, toc};
+
+/// Tags file formats `export-tags` writes.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TagsFormat {
+    /// Sorted Exuberant/Universal Ctags lines, for Vim, Neovim and most
+    /// editors' `tags` files
+    #[default]
+    Ctags,
+    /// Emacs `TAGS` sections, one per page
+    Etags,
+}
+
+/// A docset entry located in its page.
+pub struct Tag {
+    pub name: String,
+    pub kind: String,
+    pub docset: String,
+    pub file: PathBuf,
+    /// 1-based line of the entry's anchor, 1 for whole pages.
+    pub line: usize,
+    /// Byte offset of that line.
+    pub offset: usize,
+    /// That line, as etags searches for it.
+    pub text: String,
+}
+
+/// Where anchors are in one page: their line, byte offset and text.
+type Anchors = BTreeMap<String, (usize, usize, String)>;
+
+/// The tags of a docset's entries on local pages, found at the lines their
+/// anchors are declared on.
+pub fn tags(docset_path: &Path) -> Result<Vec<Tag>, Box<dyn std::error::Error>> {
+    let docset = search::docset_name(docset_path);
+    let mut pages: BTreeMap<PathBuf, Option<Anchors>> = BTreeMap::new();
+    let mut tags = Vec::new();
+    for entry in search::load_entries(docset_path)? {
+        let path = entry.path.to_string_lossy();
+        if path.contains("://") {
+            continue;
+        }
+        let fragment = path.split_once('#').map(|(_, f)| f.to_string());
+        let file = toc::strip_fragment(&entry.path);
+        let anchors = pages
+            .entry(file.clone())
+            .or_insert_with(|| anchors(&file).ok());
+        let found = fragment.and_then(|f| {
+            let f = urlencoding::decode(&f).map_or(f.clone(), |d| d.into_owned());
+            anchors.as_ref()?.get(&f).cloned()
+        });
+        let first = || {
+            let text = anchors
+                .as_ref()
+                .and_then(|a| a.get(""))
+                .map(|(_, _, text)| text.clone())
+                .unwrap_or_default();
+            (1, 0, text)
+        };
+        let (line, offset, text) = found.unwrap_or_else(first);
+        tags.push(Tag {
+            name: one_line(&entry.name),
+            kind: one_line(&entry.type_),
+            docset: docset.clone(),
+            file,
+            line,
+            offset,
+            text,
+        });
+    }
+    Ok(tags)
+}
+
+/// The `id` and `name` attributes of a page by value, with the first line
+/// under the empty key.
+fn anchors(page: &Path) -> io::Result<Anchors> {
+    archive::materialize(page)?;
+    let html = fs::read_to_string(page)?;
+    let mut anchors = Anchors::new();
+    let mut offset = 0;
+    for (i, line) in html.split_inclusive('\n').enumerate() {
+        let text = line.trim_end_matches(['\r', '\n']);
+        if i == 0 {
+            anchors.insert(String::new(), (1, 0, etags_text(text)));
+        }
+        for attribute in ["id=\"", "name=\"", "id='", "name='"] {
+            let quote = &attribute[attribute.len() - 1..];
+            let mut rest = text;
+            while let Some(start) = rest.find(attribute) {
+                rest = &rest[start + attribute.len()..];
+                let Some(end) = rest.find(quote) else {
+                    break;
+                };
+                let value = html_unescape(&rest[..end]);
+                anchors
+                    .entry(value)
+                    .or_insert_with(|| (i + 1, offset, etags_text(text)));
+                rest = &rest[end..];
+            }
+        }
+        offset += line.len();
+    }
+    Ok(anchors)
+}
+
+/// The few entities anchor names contain, such as the `&amp;` of
+/// `operator&amp;`.
+fn html_unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// A line as etags quotes it: without the characters that delimit its
+/// fields, and short.
+fn etags_text(line: &str) -> String {
+    line.chars()
+        .filter(|c| !matches!(c, '\x7f' | '\x01'))
+        .take(80)
+        .collect()
+}
+
+/// Tags name fields are tab separated and one line long.
+fn one_line(s: &str) -> String {
+    s.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Writes `tags` as a tags file; ctags ones are sorted by name as editors
+/// expect for binary search.
+pub fn write(out: &mut dyn Write, tags: &mut [Tag], format: TagsFormat) -> io::Result<()> {
+    match format {
+        TagsFormat::Ctags => {
+            tags.sort_by(|a, b| a.name.as_bytes().cmp(b.name.as_bytes()));
+            writeln!(out, "!_TAG_FILE_FORMAT\t2\t/extended format/")?;
+            writeln!(
+                out,
+                "!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/"
+            )?;
+            writeln!(out, "!_TAG_PROGRAM_NAME\tzeal-cli\t//")?;
+            for tag in tags.iter() {
+                writeln!(
+                    out,
+                    "{}\t{}\t{};\"\tkind:{}\tdocset:{}",
+                    tag.name,
+                    tag.file.display(),
+                    tag.line,
+                    tag.kind,
+                    tag.docset
+                )?;
+            }
+        }
+        TagsFormat::Etags => {
+            let mut by_file: BTreeMap<&Path, Vec<&Tag>> = BTreeMap::new();
+            for tag in tags.iter() {
+                by_file.entry(&tag.file).or_default().push(tag);
+            }
+            for (file, tags) in by_file {
+                let mut section = String::new();
+                for tag in tags {
+                    section.push_str(&format!(
+                        "{}\x7f{}\x01{},{}\n",
+                        tag.text, tag.name, tag.line, tag.offset
+                    ));
+                }
+                write!(
+                    out,
+                    "\x0c\n{},{}\n{}",
+                    file.display(),
+                    section.len(),
+                    section
+                )?;
+            }
+        }
+    }
+    out.flush()
+}
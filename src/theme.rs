@@ -0,0 +1,198 @@
+use ansi_term::{Colour, Style};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use crate::types;
+
+/// The `[colors]` section of the config.
+///
+/// ```toml
+/// [colors]
+/// # "default", "solarized" or "nord": the palette the colours below and
+/// # the built-in ones are drawn from
+/// theme = "nord"
+///
+/// # Icon colours by entry type, any spelling `--type` accepts
+/// [colors.types]
+/// Method = "#88c0d0"
+/// Struct = 208
+///
+/// # Matched characters (`filter --highlight`), example labels and the
+/// # highlighting of code examples
+/// [colors.ui]
+/// match = "yellow"
+/// keyword = "bright-purple"
+/// ```
+///
+/// Colours are names (`red`, `bright-blue`, ...), 256-colour indexes or
+/// `#rrggbb` truecolor values.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Colors {
+    pub theme: Option<String>,
+    pub types: BTreeMap<String, ColorValue>,
+    pub ui: BTreeMap<String, ColorValue>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ColorValue {
+    Index(u8),
+    Name(String),
+}
+
+/// Elements other than entry types that can be coloured.
+pub const UI_ELEMENTS: &[&str] = &[
+    "match",
+    "label",
+    "keyword",
+    "string",
+    "comment",
+    "number",
+    "function",
+    "type",
+    "attribute",
+];
+
+#[derive(Default)]
+struct Theme {
+    /// The theme's stand-ins for the basic colours.
+    palette: Vec<(Colour, Colour)>,
+    /// By lowercase canonical entry type.
+    types: HashMap<String, Colour>,
+    ui: HashMap<String, Colour>,
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Sets the colours output is drawn in from the config, or says what in it
+/// is wrong.
+pub fn init(colors: &Colors) -> Result<(), String> {
+    let palette = match colors.theme.as_deref() {
+        None | Some("default") => Vec::new(),
+        Some("solarized") => vec![
+            (Colour::Red, 0xdc322f),
+            (Colour::Green, 0x859900),
+            (Colour::Yellow, 0xb58900),
+            (Colour::Blue, 0x268bd2),
+            (Colour::Purple, 0x6c71c4),
+            (Colour::Cyan, 0x2aa198),
+        ],
+        Some("nord") => vec![
+            (Colour::Red, 0xbf616a),
+            (Colour::Green, 0xa3be8c),
+            (Colour::Yellow, 0xebcb8b),
+            (Colour::Blue, 0x81a1c1),
+            (Colour::Purple, 0xb48ead),
+            (Colour::Cyan, 0x88c0d0),
+        ],
+        Some(other) => {
+            return Err(format!(
+                "unknown theme '{}'; use default, solarized or nord",
+                other
+            ))
+        }
+    };
+    let mut theme = Theme {
+        palette: palette
+            .into_iter()
+            .map(|(base, rgb)| (base, hex(rgb)))
+            .collect(),
+        ..Default::default()
+    };
+    for (type_, value) in &colors.types {
+        let key = types::normalize(type_).to_lowercase();
+        theme.types.insert(key, parse(value)?);
+    }
+    for (element, value) in &colors.ui {
+        if !UI_ELEMENTS.contains(&element.as_str()) {
+            return Err(format!(
+                "unknown element '{}'; use one of {}",
+                element,
+                UI_ELEMENTS.join(", ")
+            ));
+        }
+        theme.ui.insert(element.clone(), parse(value)?);
+    }
+    let _ = THEME.set(theme);
+    Ok(())
+}
+
+fn hex(rgb: u32) -> Colour {
+    Colour::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+fn parse(value: &ColorValue) -> Result<Colour, String> {
+    let name = match value {
+        ColorValue::Index(n) => return Ok(Colour::Fixed(*n)),
+        ColorValue::Name(name) => name.to_ascii_lowercase(),
+    };
+    if let Some(rgb) = name.strip_prefix('#') {
+        return u32::from_str_radix(rgb, 16)
+            .ok()
+            .filter(|_| rgb.len() == 6)
+            .map(hex)
+            .ok_or_else(|| format!("'{}' is not a #rrggbb colour", name));
+    }
+    if let Ok(n) = name.parse::<u8>() {
+        return Ok(Colour::Fixed(n));
+    }
+    let (bright, base) = match name.strip_prefix("bright-") {
+        Some(base) => (true, base),
+        None => (false, name.as_str()),
+    };
+    let (colour, index) = match base {
+        "black" => (Colour::Black, 0),
+        "red" => (Colour::Red, 1),
+        "green" => (Colour::Green, 2),
+        "yellow" => (Colour::Yellow, 3),
+        "blue" => (Colour::Blue, 4),
+        "purple" | "magenta" => (Colour::Purple, 5),
+        "cyan" => (Colour::Cyan, 6),
+        "white" => (Colour::White, 7),
+        _ => return Err(format!("unknown colour '{}'", name)),
+    };
+    Ok(if bright {
+        Colour::Fixed(index + 8)
+    } else {
+        colour
+    })
+}
+
+fn theme() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
+
+/// `default` drawn from the theme: its colour replaced by the one `set` in
+/// the config, else by the theme's stand-in for it.
+fn restyle(default: Style, set: Option<&Colour>) -> Style {
+    let mut style = default;
+    style.foreground = match set {
+        Some(colour) => Some(*colour),
+        None => default.foreground.map(|c| {
+            let stand_in = theme().palette.iter().find(|(base, _)| *base == c);
+            stand_in.map_or(c, |(_, colour)| *colour)
+        }),
+    };
+    style
+}
+
+/// The style of an entry type's icon, `default` unless configured.
+pub fn type_style(type_: &str, default: Style) -> Style {
+    restyle(default, theme().types.get(&type_.to_lowercase()))
+}
+
+/// The style of one of the [`UI_ELEMENTS`], `default` unless configured.
+pub fn ui_style(element: &str, default: Style) -> Style {
+    restyle(default, theme().ui.get(element))
+}
+
+/// A colour as written in the config.
+pub fn describe(colour: Colour) -> String {
+    match colour {
+        Colour::RGB(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        Colour::Fixed(n) => n.to_string(),
+        other => format!("{:?}", other),
+    }
+}
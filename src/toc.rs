@@ -0,0 +1,84 @@
+use scraper::{Html, Selector};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+
+pub struct TocEntry {
+    pub depth: usize,
+    pub name: String,
+    pub type_: String,
+    pub anchor: String,
+}
+
+/// Drops the `#fragment` part of an index path, leaving the HTML file on disk.
+pub fn strip_fragment(path: &Path) -> PathBuf {
+    let s = path.to_string_lossy();
+    match s.split_once('#') {
+        Some((file, _)) => PathBuf::from(file),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Lists the section anchors of a documentation page.
+///
+/// Dash anchors (`<a name="//apple_ref/cpp/Type/Name" class="dashAnchor">`) are
+/// preferred since they carry the entry type; pages without them fall back to
+/// headings that have an `id`.
+pub fn page_toc(html_path: &Path) -> std::io::Result<Vec<TocEntry>> {
+    archive::materialize(html_path)?;
+    let html = fs::read_to_string(html_path)?;
+    let doc = Html::parse_document(&html);
+
+    let entries = dash_anchors(&doc);
+    if !entries.is_empty() {
+        return Ok(entries);
+    }
+    Ok(headings(&doc))
+}
+
+pub(crate) fn dash_anchors(doc: &Html) -> Vec<TocEntry> {
+    let selector = Selector::parse("a.dashAnchor[name]").unwrap();
+    doc.select(&selector)
+        .filter_map(|a| {
+            let anchor = a.value().attr("name")?;
+            // //apple_ref/cpp/<Type>/<Name> or //dash_ref/<Type>/<Name>/<n>
+            let parts: Vec<&str> = anchor.trim_start_matches('/').split('/').collect();
+            let (type_, name) = match parts.as_slice() {
+                ["apple_ref", _, type_, name, ..] => (type_, name),
+                ["dash_ref", type_, name, ..] => (type_, name),
+                _ => return None,
+            };
+            Some(TocEntry {
+                depth: 0,
+                name: urlencoding::decode(name).ok()?.into_owned(),
+                type_: type_.to_string(),
+                anchor: anchor.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn headings(doc: &Html) -> Vec<TocEntry> {
+    let selector = Selector::parse("h1[id], h2[id], h3[id], h4[id], h5[id], h6[id]").unwrap();
+    let found: Vec<(usize, String, String)> = doc
+        .select(&selector)
+        .filter_map(|h| {
+            let level = h.value().name()[1..].parse::<usize>().ok()?;
+            let name = h.text().collect::<String>();
+            let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+            Some((level, name, h.value().attr("id")?.to_string()))
+        })
+        .collect();
+
+    let top = found.iter().map(|(level, _, _)| *level).min().unwrap_or(1);
+    found
+        .into_iter()
+        .map(|(level, name, anchor)| TocEntry {
+            depth: level - top,
+            name,
+            type_: "Section".to_string(),
+            anchor,
+        })
+        .collect()
+}
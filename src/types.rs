@@ -0,0 +1,158 @@
+/// The entry types Dash documents for docset indexes.
+pub const CANONICAL: &[&str] = &[
+    "Annotation",
+    "Attribute",
+    "Binding",
+    "Builtin",
+    "Callback",
+    "Category",
+    "Class",
+    "Command",
+    "Component",
+    "Constant",
+    "Constructor",
+    "Define",
+    "Delegate",
+    "Diagram",
+    "Directive",
+    "Element",
+    "Entry",
+    "Enum",
+    "Environment",
+    "Error",
+    "Event",
+    "Exception",
+    "Extension",
+    "Field",
+    "File",
+    "Filter",
+    "Framework",
+    "Function",
+    "Global",
+    "Guide",
+    "Hook",
+    "Instance",
+    "Instruction",
+    "Interface",
+    "Keyword",
+    "Library",
+    "Literal",
+    "Macro",
+    "Method",
+    "Mixin",
+    "Modifier",
+    "Module",
+    "Namespace",
+    "Notation",
+    "Object",
+    "Operator",
+    "Option",
+    "Package",
+    "Parameter",
+    "Plugin",
+    "Procedure",
+    "Property",
+    "Protocol",
+    "Provider",
+    "Provisioner",
+    "Query",
+    "Record",
+    "Resource",
+    "Sample",
+    "Section",
+    "Service",
+    "Setting",
+    "Shortcut",
+    "Statement",
+    "Struct",
+    "Style",
+    "Subroutine",
+    "Tag",
+    "Test",
+    "Trait",
+    "Type",
+    "Union",
+    "Value",
+    "Variable",
+    "Word",
+];
+
+/// Abbreviations and synonyms found in the wild, lowercase, from the apple_ref
+/// style codes of older docsets and from Doxygen and Sphinx generators.
+const ALIASES: &[(&str, &str)] = &[
+    ("cat", "Category"),
+    ("cl", "Class"),
+    ("clconst", "Constant"),
+    ("clm", "Method"),
+    ("data", "Variable"),
+    ("econst", "Constant"),
+    ("enumeration", "Enum"),
+    ("ffunc", "Function"),
+    ("func", "Function"),
+    ("instm", "Method"),
+    ("instp", "Property"),
+    ("intf", "Interface"),
+    ("intfcm", "Method"),
+    ("intfm", "Method"),
+    ("intfp", "Property"),
+    ("specialization", "Class"),
+    ("structdata", "Field"),
+    ("tdef", "Type"),
+    ("typedef", "Type"),
+    ("var", "Variable"),
+    ("package attributes", "Attribute"),
+    ("private attributes", "Attribute"),
+    ("protected attributes", "Attribute"),
+    ("public attributes", "Attribute"),
+    ("static package attributes", "Attribute"),
+    ("static private attributes", "Attribute"),
+    ("static protected attributes", "Attribute"),
+    ("static public attributes", "Attribute"),
+    ("package functions", "Method"),
+    ("private member functions", "Method"),
+    ("protected member functions", "Method"),
+    ("public member functions", "Method"),
+    ("static public member functions", "Method"),
+    ("related functions", "Function"),
+];
+
+/// Canonical Dash type for a raw `searchIndex` type, or `None` when the
+/// docset uses a type of its own.
+///
+/// Case, leading underscores (`_struct`) and plurals (`Functions`) are ignored.
+pub fn canonical(raw: &str) -> Option<&'static str> {
+    let lower = raw.trim().trim_start_matches('_').to_lowercase();
+    let lookup = |key: &str| {
+        ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == key)
+            .map(|&(_, ty)| ty)
+            .or_else(|| {
+                CANONICAL
+                    .iter()
+                    .find(|ty| ty.eq_ignore_ascii_case(key))
+                    .copied()
+            })
+    };
+    lookup(&lower).or_else(|| lookup(lower.strip_suffix('s')?))
+}
+
+/// The abbreviations and synonyms that [`canonical`] maps to `canonical`.
+pub fn aliases(canonical: &str) -> Vec<&'static str> {
+    ALIASES
+        .iter()
+        .filter(|(_, ty)| *ty == canonical)
+        .map(|&(alias, _)| alias)
+        .collect()
+}
+
+/// [`canonical`], falling back to the raw type.
+pub fn normalize(raw: &str) -> &str {
+    canonical(raw).unwrap_or(raw)
+}
+
+/// Whether an entry of type `raw` passes a `--type` filter naming `wanted`,
+/// both compared in canonical form.
+pub fn matches(raw: &str, wanted: &str) -> bool {
+    normalize(raw).eq_ignore_ascii_case(normalize(wanted))
+}
@@ -0,0 +1,218 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{related, search, toc};
+
+/// Set from the config's `track_usage`; nothing is recorded otherwise.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// How `stats --usage` prints its report.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UsageFormat {
+    /// Aligned columns, docsets then entries
+    #[default]
+    Table,
+    /// `kind,docset,name,opens,first,last` rows
+    Csv,
+    /// Object with `docsets` and `entries` arrays
+    Json,
+}
+
+/// One open, a line of the usage log.
+#[derive(Serialize, Deserialize)]
+struct Open {
+    /// Seconds since the Unix epoch.
+    time: u64,
+    docset: String,
+    name: String,
+}
+
+/// Opens of one docset or entry, as reported.
+#[derive(Serialize)]
+pub struct Tally {
+    pub docset: String,
+    /// Empty for docset totals.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub opens: u64,
+    /// Dates of the first and last open, `YYYY-MM-DD`.
+    pub first: String,
+    pub last: String,
+}
+
+fn log_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("zeal-cli").join("usage.jsonl"))
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Records that the entry at `path` was opened, when tracking is on, under
+/// its `name`, or its page's for a path opened without one. Failures are
+/// only logged: a usage log is never worth failing an open over.
+pub fn record(path: &Path, name: Option<&str>) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let Some(docset_path) = related::docset_of(path) else {
+        return;
+    };
+    let name = match name.filter(|n| !n.is_empty()) {
+        Some(name) => name.to_string(),
+        None => toc::strip_fragment(path)
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let open = Open {
+        time: now(),
+        docset: search::docset_name(&docset_path),
+        name,
+    };
+    if let Err(e) = append(&open) {
+        tracing::warn!("Could not record usage: {}", e);
+    }
+}
+
+fn append(open: &Open) -> Result<(), Box<dyn std::error::Error>> {
+    let path = log_path().ok_or("No data directory on this platform")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(open)?)?;
+    Ok(())
+}
+
+/// Opens per docset and per entry over the last `days` (all when `None`),
+/// most opened first, at most `limit` of each.
+pub fn report(days: Option<u64>, limit: usize) -> std::io::Result<(Vec<Tally>, Vec<Tally>)> {
+    let since = days.map_or(0, |d| now().saturating_sub(d * 86_400));
+    let opens: Vec<Open> = match log_path().map(fs::File::open) {
+        Some(Ok(file)) => BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .filter(|open: &Open| open.time >= since)
+            .collect(),
+        Some(Err(e)) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+        _ => Vec::new(),
+    };
+    let docsets = tally(&opens, |o| (o.docset.clone(), String::new()), limit);
+    let entries = tally(&opens, |o| (o.docset.clone(), o.name.clone()), limit);
+    Ok((docsets, entries))
+}
+
+fn tally(opens: &[Open], key: impl Fn(&Open) -> (String, String), limit: usize) -> Vec<Tally> {
+    let mut counts: HashMap<(String, String), (u64, u64, u64)> = HashMap::new();
+    for open in opens {
+        let (n, first, last) = counts.entry(key(open)).or_insert((0, u64::MAX, 0));
+        *n += 1;
+        *first = (*first).min(open.time);
+        *last = (*last).max(open.time);
+    }
+    let mut tallies: Vec<_> = counts.into_iter().collect();
+    tallies.sort_by(|((da, na), (a, _, la)), ((db, nb), (b, _, lb))| {
+        b.cmp(a).then(lb.cmp(la)).then(da.cmp(db)).then(na.cmp(nb))
+    });
+    tallies
+        .into_iter()
+        .take(limit)
+        .map(|((docset, name), (opens, first, last))| Tally {
+            docset,
+            name,
+            opens,
+            first: date(first),
+            last: date(last),
+        })
+        .collect()
+}
+
+/// A Unix time as a UTC `YYYY-MM-DD` date.
+fn date(secs: u64) -> String {
+    // Howard Hinnant's civil_from_days.
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Prints the report in `format`.
+pub fn print(
+    docsets: &[Tally],
+    entries: &[Tally],
+    format: UsageFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match format {
+        UsageFormat::Table => {
+            let docset_width = |rows: &[Tally]| rows.iter().map(|t| t.docset.len()).max();
+            let w = docset_width(docsets).unwrap_or(0);
+            println!("Docsets");
+            for t in docsets {
+                println!("{:>6}  {:<w$}  last {}", t.opens, t.docset, t.last);
+            }
+            println!();
+            println!("Entries");
+            let w = docset_width(entries).unwrap_or(0);
+            let n = entries.iter().map(|t| t.name.len()).max().unwrap_or(0);
+            for t in entries {
+                println!(
+                    "{:>6}  {:<w$}  {:<n$}  last {}",
+                    t.opens, t.docset, t.name, t.last
+                );
+            }
+        }
+        UsageFormat::Csv => {
+            println!("kind,docset,name,opens,first,last");
+            let rows = docsets
+                .iter()
+                .map(|t| ("docset", t))
+                .chain(entries.iter().map(|t| ("entry", t)));
+            for (kind, t) in rows {
+                println!(
+                    "{},{},{},{},{},{}",
+                    kind,
+                    csv_field(&t.docset),
+                    csv_field(&t.name),
+                    t.opens,
+                    t.first,
+                    t.last
+                );
+            }
+        }
+        UsageFormat::Json => {
+            let report = serde_json::json!({ "docsets": docsets, "entries": entries });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+    Ok(())
+}
+
+/// Quotes a CSV field when it holds a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
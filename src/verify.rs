@@ -0,0 +1,188 @@
+use flate2::read::GzDecoder;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+
+use crate::download;
+use crate::feed::FeedDocset;
+use crate::install::{self, DocsetMeta};
+use crate::versions;
+
+/// File in an installed docset's directory listing the SHA-256 of every
+/// file its archive held, by path below the docset directory.
+pub const CHECKSUMS: &str = "checksums.json";
+
+/// Files zeal-cli writes into a docset itself, which no archive holds.
+const OWN_FILES: [&str; 2] = ["meta.json", CHECKSUMS];
+
+/// The SHA-256 of each file in a docset archive, by path below its
+/// top-level `<Name>.docset` directory.
+pub fn archive_checksums(
+    archive_path: &Path,
+) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    let mut archive = tar::Archive::new(GzDecoder::new(fs::File::open(archive_path)?));
+    let mut sums = BTreeMap::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?;
+        let relative: Vec<_> = path
+            .components()
+            .skip(1)
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+        let relative = relative.join("/");
+        if relative.is_empty() || OWN_FILES.contains(&relative.as_str()) {
+            continue;
+        }
+        sums.insert(relative, download::sha256_hex(entry)?);
+    }
+    Ok(sums)
+}
+
+/// Writes the checksums of `archive_path` into the unpacked docset `dir`.
+pub fn record(archive_path: &Path, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let sums = archive_checksums(archive_path)?;
+    fs::write(dir.join(CHECKSUMS), serde_json::to_string_pretty(&sums)?)?;
+    Ok(())
+}
+
+/// What `verify` found wrong with a docset; empty when nothing was.
+#[derive(Default)]
+pub struct Report {
+    /// Files whose contents differ from the archive's.
+    pub modified: Vec<String>,
+    /// Files of the archive that are gone.
+    pub missing: Vec<String>,
+    /// Files the archive didn't have.
+    pub added: Vec<String>,
+    /// Disagreements between the recorded, stored and feed archive hashes.
+    pub archive: Vec<String>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty()
+            && self.missing.is_empty()
+            && self.added.is_empty()
+            && self.archive.is_empty()
+    }
+
+    pub fn print(&self, name: &str) {
+        if self.is_clean() {
+            println!("{}: OK", name);
+            return;
+        }
+        for (label, paths) in [
+            ("modified", &self.modified),
+            ("missing", &self.missing),
+            ("added", &self.added),
+        ] {
+            for path in paths {
+                println!("{}: {}: {}", name, label, path);
+            }
+        }
+        for problem in &self.archive {
+            println!("{}: {}", name, problem);
+        }
+    }
+}
+
+/// Recomputes the checksums of an installed docset's files and compares
+/// them with those its archive had, then compares the archive's hash as
+/// installed with the stored copy kept for `rollback` and with `feed`'s
+/// entry of the same version.
+pub fn verify(
+    docset_path: &Path,
+    feed: Option<&[FeedDocset]>,
+) -> Result<Report, Box<dyn std::error::Error>> {
+    let meta = install::read_meta(docset_path)
+        .ok_or("not installed from a feed, so there is nothing to verify it against")?;
+    let recorded = fs::read_to_string(docset_path.join(CHECKSUMS)).map_err(|_| {
+        format!(
+            "no checksums were recorded when it was installed; reinstall {} to record them",
+            meta.name
+        )
+    })?;
+    let expected: BTreeMap<String, String> = serde_json::from_str(&recorded)?;
+
+    let mut report = Report::default();
+    let mut found = BTreeMap::new();
+    files(docset_path, docset_path, &mut found)?;
+    for (path, sum) in &expected {
+        match found.remove(path) {
+            None => report.missing.push(path.clone()),
+            Some(actual) if !actual.eq_ignore_ascii_case(sum) => report.modified.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+    report.added = found.into_keys().collect();
+    report.archive = check_archive(&meta, feed);
+    Ok(report)
+}
+
+/// The SHA-256 of each file below `dir`, by path below `root`.
+fn files(
+    root: &Path,
+    dir: &Path,
+    sums: &mut BTreeMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files(root, &path, sums)?;
+            continue;
+        }
+        let relative = path
+            .strip_prefix(root)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if !OWN_FILES.contains(&relative.as_str()) {
+            sums.insert(relative, download::sha256_hex(fs::File::open(&path)?)?);
+        }
+    }
+    Ok(())
+}
+
+fn check_archive(meta: &DocsetMeta, feed: Option<&[FeedDocset]>) -> Vec<String> {
+    let mut problems = Vec::new();
+    let Some(installed) = &meta.sha256 else {
+        return problems;
+    };
+    let published = feed
+        .into_iter()
+        .flatten()
+        .find(|d| {
+            d.name.eq_ignore_ascii_case(&meta.name)
+                && d.version == meta.version
+                && d.revision == meta.revision
+        })
+        .and_then(|d| d.sha256.as_deref());
+    if let Some(published) = published.filter(|p| !p.trim().eq_ignore_ascii_case(installed)) {
+        problems.push(format!(
+            "the feed now publishes {} for version {}, but {} was installed",
+            published.trim(),
+            meta.version,
+            installed
+        ));
+    }
+    let stored = versions::load(&meta.name)
+        .versions
+        .into_iter()
+        .find(|v| v.meta.version == meta.version && v.meta.revision == meta.revision);
+    let stored_path = stored.and_then(|v| Some(versions::store_dir(&meta.name)?.join(v.archive)));
+    if let Some(path) = stored_path.filter(|p| p.exists()) {
+        match download::verify_sha256(&path, installed) {
+            Ok(()) => {}
+            Err(e) => problems.push(format!("stored archive: {}", e)),
+        }
+    }
+    problems
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::install::{self, DocsetMeta};
+
+/// How many archives to keep per docset; the pinned one is always kept.
+const KEEP_VERSIONS: usize = 3;
+
+/// `manifest.json` next to a docset's stored archives.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Manifest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned: Option<String>,
+    /// Stored archives, oldest install first.
+    #[serde(default)]
+    pub versions: Vec<StoredVersion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredVersion {
+    #[serde(flatten)]
+    pub meta: DocsetMeta,
+    /// Archive file name inside the docset's store directory.
+    pub archive: String,
+    /// Unix time the archive was (last) installed.
+    pub installed_at: u64,
+}
+
+/// `<data dir>/zeal-cli/archives/`, holding a directory per docset.
+pub fn store_root() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("zeal-cli").join("archives"))
+}
+
+/// `<data dir>/zeal-cli/archives/<Name>/`, holding archives and the manifest.
+pub fn store_dir(name: &str) -> Option<PathBuf> {
+    store_root().map(|d| d.join(name))
+}
+
+pub fn load(name: &str) -> Manifest {
+    store_dir(name)
+        .and_then(|dir| fs::read_to_string(dir.join("manifest.json")).ok())
+        .and_then(|body| serde_json::from_str(&body).ok())
+        .unwrap_or_default()
+}
+
+fn save(name: &str, manifest: &Manifest) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = store_dir(name).ok_or("No data directory for the archive store")?;
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        dir.join("manifest.json"),
+        serde_json::to_string_pretty(manifest)?,
+    )?;
+    Ok(())
+}
+
+fn archive_name(meta: &DocsetMeta) -> String {
+    let safe = |s: &str| -> String {
+        s.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    };
+    format!("{}-{}.tgz", safe(&meta.version), safe(&meta.revision))
+}
+
+/// Moves a freshly downloaded archive into the store and records it as the
+/// newest version, pruning old archives beyond [`KEEP_VERSIONS`].
+pub fn store(archive: &Path, meta: &DocsetMeta) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = store_dir(&meta.name).ok_or("No data directory for the archive store")?;
+    fs::create_dir_all(&dir)?;
+    let file_name = archive_name(meta);
+    let target = dir.join(&file_name);
+    if fs::rename(archive, &target).is_err() {
+        // Cache and data dirs may live on different filesystems.
+        fs::copy(archive, &target)?;
+        fs::remove_file(archive)?;
+    }
+
+    let mut manifest = load(&meta.name);
+    manifest.versions.retain(|v| v.archive != file_name);
+    manifest.versions.push(StoredVersion {
+        meta: meta.clone(),
+        archive: file_name,
+        installed_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+    });
+    while manifest.versions.len() > KEEP_VERSIONS {
+        let Some(i) = manifest
+            .versions
+            .iter()
+            .position(|v| manifest.pinned.as_deref() != Some(v.meta.version.as_str()))
+        else {
+            break;
+        };
+        let old = manifest.versions.remove(i);
+        let _ = fs::remove_file(dir.join(&old.archive));
+    }
+    save(&meta.name, &manifest)?;
+    Ok(target)
+}
+
+fn reinstall(
+    name: &str,
+    version: &StoredVersion,
+    docsets_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = store_dir(name).ok_or("No data directory for the archive store")?;
+    install::extract(&dir.join(&version.archive), &version.meta, docsets_dir)?;
+    println!(
+        "Installed {} {} (revision {})",
+        name, version.meta.version, version.meta.revision
+    );
+    Ok(())
+}
+
+fn installed_name(docsets_dir: &Path, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(install::find_installed(docsets_dir, name)?
+        .and_then(|path| install::read_meta(&path))
+        .map(|m| m.name)
+        .unwrap_or_else(|| name.to_string()))
+}
+
+/// Pins `name` to `version`, reinstalling that version from the store if a
+/// different one is installed. Pinned docsets are skipped by `update`.
+pub fn pin(
+    name: &str,
+    version: &str,
+    docsets_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let name = installed_name(docsets_dir, name)?;
+    let mut manifest = load(&name);
+    let stored = manifest
+        .versions
+        .iter()
+        .rev()
+        .find(|v| v.meta.version == version)
+        .cloned()
+        .ok_or_else(|| {
+            format!(
+                "Version {} of {} is not in the archive store",
+                version, name
+            )
+        })?;
+
+    let installed =
+        install::find_installed(docsets_dir, &name)?.and_then(|p| install::read_meta(&p));
+    let is_installed = installed
+        .is_some_and(|m| m.version == stored.meta.version && m.revision == stored.meta.revision);
+    if !is_installed {
+        reinstall(&name, &stored, docsets_dir)?;
+    }
+    manifest.pinned = Some(version.to_string());
+    save(&name, &manifest)?;
+    println!("Pinned {} to version {}", name, version);
+    Ok(())
+}
+
+pub fn unpin(name: &str, docsets_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let name = installed_name(docsets_dir, name)?;
+    let mut manifest = load(&name);
+    if manifest.pinned.take().is_none() {
+        println!("{} is not pinned", name);
+        return Ok(());
+    }
+    save(&name, &manifest)?;
+    println!("Unpinned {}", name);
+    Ok(())
+}
+
+/// Reinstalls the version stored before the installed one and pins it, so
+/// the next `update` doesn't immediately undo the rollback.
+pub fn rollback(name: &str, docsets_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let name = installed_name(docsets_dir, name)?;
+    let mut manifest = load(&name);
+    let installed = install::find_installed(docsets_dir, &name)?
+        .and_then(|p| install::read_meta(&p))
+        .ok_or_else(|| format!("{} has no meta.json; it wasn't installed by zeal-cli", name))?;
+    let current = manifest
+        .versions
+        .iter()
+        .position(|v| v.meta.version == installed.version && v.meta.revision == installed.revision)
+        .unwrap_or(manifest.versions.len());
+    let previous = current
+        .checked_sub(1)
+        .and_then(|i| manifest.versions.get(i))
+        .cloned()
+        .ok_or_else(|| format!("No earlier version of {} is in the archive store", name))?;
+
+    reinstall(&name, &previous, docsets_dir)?;
+    manifest.pinned = Some(previous.meta.version.clone());
+    save(&name, &manifest)?;
+    println!(
+        "Pinned {} to version {}; run `unpin {}` to resume updates",
+        name, previous.meta.version, name
+    );
+    Ok(())
+}
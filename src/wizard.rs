@@ -0,0 +1,285 @@
+use console::Term;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use crate::search;
+
+/// What `init` needs from the rest of the program.
+pub struct Setup<'a> {
+    /// The docsets directory searched now, if one was found.
+    pub detected: Option<PathBuf>,
+    /// The variable that points zeal-cli at another docsets directory.
+    pub docset_dir_env: &'a str,
+    pub config_path: PathBuf,
+    /// Icons as `--icons` draws them, to see whether the font has them.
+    pub icons: String,
+    /// The command line, to complete.
+    pub cli: clap::Command,
+}
+
+/// Walks a new user through setup on the terminal: where docsets are, a
+/// starter config, shell completions and whether `--icons` can be used.
+pub fn run(setup: Setup) -> Result<(), Box<dyn std::error::Error>> {
+    let term = Term::stderr();
+    if !std::io::stdin().is_terminal() || !term.is_term() {
+        return Err("init asks questions; run it in a terminal".into());
+    }
+    let docsets = docsets_dir(&term, &setup)?;
+    term.write_line("")?;
+    starter_config(&term, &setup.config_path, &docsets)?;
+    term.write_line("")?;
+    completions(&term, &setup.cli)?;
+    term.write_line("")?;
+    icons(&term, &setup.icons)?;
+    term.write_line("")?;
+    let bin = setup.cli.get_name();
+    match docsets.first() {
+        Some(docset) => term.write_line(&format!(
+            "Done. Try `{} search {} <query>`.",
+            bin,
+            search::docset_name(docset)
+        ))?,
+        None => term.write_line(&format!(
+            "Done. Install a docset with `{} docset install <name>`; `{} docset available` lists them.",
+            bin, bin
+        ))?,
+    }
+    Ok(())
+}
+
+/// Asks where docsets are, offering to create the directory, and returns
+/// those installed there.
+fn docsets_dir(term: &Term, setup: &Setup) -> io::Result<Vec<PathBuf>> {
+    let detected = setup
+        .detected
+        .as_ref()
+        .map(|d| d.display().to_string())
+        .unwrap_or_default();
+    let dir = loop {
+        let answer = ask(term, "Docsets directory", &detected)?;
+        if !answer.is_empty() {
+            break expand_home(&answer);
+        }
+    };
+    if !dir.is_dir() {
+        let question = format!("{} doesn't exist. Create it?", dir.display());
+        if !confirm(term, &question, true)? {
+            return Ok(Vec::new());
+        }
+        fs::create_dir_all(&dir)?;
+    }
+    if setup.detected.as_ref() != Some(&dir) {
+        term.write_line(&format!(
+            "zeal-cli looks there when {} is set, as with this in your shell profile:\n  export {}={}",
+            setup.docset_dir_env,
+            setup.docset_dir_env,
+            dir.display()
+        ))?;
+    }
+    let mut docsets: Vec<PathBuf> = fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| search::is_docset_dir(path))
+        .collect();
+    docsets.sort();
+    term.write_line(&format!("{} docsets installed", docsets.len()))?;
+    Ok(docsets)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Writes a config naming the docsets searched by default, with the other
+/// settings commented out, unless the user keeps the one they have.
+fn starter_config(term: &Term, path: &Path, docsets: &[PathBuf]) -> io::Result<()> {
+    if path.exists() {
+        let question = format!(
+            "{} exists. Replace it with a starter config?",
+            path.display()
+        );
+        if !confirm(term, &question, false)? {
+            return Ok(());
+        }
+    } else if !confirm(
+        term,
+        &format!("Write a config to {}?", path.display()),
+        true,
+    )? {
+        return Ok(());
+    }
+    let mut defaults = Vec::new();
+    if !docsets.is_empty() {
+        let names: Vec<String> = docsets.iter().map(|d| search::docset_name(d)).collect();
+        term.write_line(&format!("Installed: {}", names.join(", ")))?;
+        let answer = ask(
+            term,
+            "Docsets to search when none is named, comma separated (blank to ask each time)",
+            "",
+        )?;
+        for name in answer.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            match search::find_named(docsets, name) {
+                Ok(Some(docset)) => defaults.push(search::docset_name(docset)),
+                Ok(None) => {
+                    term.write_line(&format!("Skipping '{}', which isn't installed", name))?
+                }
+                Err(e) => term.write_line(&format!("Skipping '{}': {}", name, e))?,
+            }
+        }
+    }
+    let quoted: Vec<String> = defaults.iter().map(|d| format!("{:?}", d)).collect();
+    let body = STARTER_CONFIG.replace("{default_docsets}", &quoted.join(", "));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, body)?;
+    term.write_line(&format!("Wrote {}", path.display()))
+}
+
+const STARTER_CONFIG: &str = r#"# zeal-cli settings; uncomment and change what you need.
+
+# Searched by `search <query>` when no docset is named
+default_docsets = [{default_docsets}]
+# Count opened entries for `stats --usage`
+# track_usage = true
+
+# Named docset lists, searched with `search @web <query>`
+# [groups]
+# web = ["HTML", "CSS", "JavaScript"]
+
+# `search py:os.path` searches the docsets listed for `py`
+# [keywords]
+# py = "Python_3"
+
+# Past queries, for `history`
+# [history]
+# enabled = true
+
+# "default", "solarized" or "nord"
+# [colors]
+# theme = "nord"
+"#;
+
+/// Offers to install completion of subcommands and options for the user's
+/// shell, when it is one scripts are written for.
+fn completions(term: &Term, cli: &clap::Command) -> io::Result<()> {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    let shell = Path::new(&shell)
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let bin = cli.get_name();
+    let (path, script) = match shell {
+        "bash" => (
+            dirs::data_dir().map(|d| d.join("bash-completion/completions").join(bin)),
+            bash_completions(cli),
+        ),
+        "fish" => (
+            dirs::config_dir().map(|d| d.join("fish/completions").join(format!("{}.fish", bin))),
+            fish_completions(cli),
+        ),
+        _ => (None, String::new()),
+    };
+    let Some(path) = path else {
+        return term.write_line("Shell completions are written for bash and fish only");
+    };
+    let question = format!("Install {} completions to {}?", shell, path.display());
+    if !confirm(term, &question, true)? {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, script)?;
+    term.write_line(&format!(
+        "Wrote {}; new {} sessions complete {}",
+        path.display(),
+        shell,
+        bin
+    ))
+}
+
+/// Subcommands shown in `--help`, with their one-line descriptions.
+fn subcommands(cli: &clap::Command) -> Vec<(String, String)> {
+    cli.get_subcommands()
+        .filter(|c| !c.is_hide_set())
+        .map(|c| {
+            let about = c.get_about().map(|a| a.to_string()).unwrap_or_default();
+            (c.get_name().to_string(), about)
+        })
+        .collect()
+}
+
+fn long_options(cli: &clap::Command) -> Vec<String> {
+    cli.get_arguments()
+        .filter(|a| !a.is_hide_set())
+        .filter_map(|a| a.get_long().map(|l| format!("--{}", l)))
+        .collect()
+}
+
+fn bash_completions(cli: &clap::Command) -> String {
+    let mut words: Vec<String> = subcommands(cli).into_iter().map(|(name, _)| name).collect();
+    words.extend(long_options(cli));
+    format!(
+        "# Written by `{0} init`\ncomplete -o default -W \"{1}\" {0}\n",
+        cli.get_name(),
+        words.join(" ")
+    )
+}
+
+fn fish_completions(cli: &clap::Command) -> String {
+    let bin = cli.get_name();
+    let quote = |s: &str| format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"));
+    let mut script = format!("# Written by `{} init`\n", bin);
+    for (name, about) in subcommands(cli) {
+        script.push_str(&format!(
+            "complete -c {} -f -n __fish_use_subcommand -a {} -d {}\n",
+            bin,
+            quote(&name),
+            quote(&about)
+        ));
+    }
+    for option in long_options(cli) {
+        script.push_str(&format!("complete -c {} -l {}\n", bin, &option[2..]));
+    }
+    script
+}
+
+/// Shows the icons and asks whether the font draws them.
+fn icons(term: &Term, icons: &str) -> io::Result<()> {
+    term.write_line(&format!("  {}", icons))?;
+    if confirm(term, "Do these show as icons, not boxes or blanks?", true)? {
+        term.write_line(
+            "Your font has them: add --icons to searches to see entry types at a glance",
+        )
+    } else {
+        term.write_line(
+            "--icons needs a Nerd Font (https://www.nerdfonts.com) set as the terminal's font; \
+             leave it off until then",
+        )
+    }
+}
+
+fn ask(term: &Term, question: &str, default: &str) -> io::Result<String> {
+    if default.is_empty() {
+        term.write_str(&format!("{}: ", question))?;
+    } else {
+        term.write_str(&format!("{} [{}]: ", question, default))?;
+    }
+    let answer = term.read_line()?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_string())
+}
+
+fn confirm(term: &Term, question: &str, default: bool) -> io::Result<bool> {
+    let hint = if default { "Y/n" } else { "y/N" };
+    term.write_str(&format!("{} [{}] ", question, hint))?;
+    let answer = term.read_line()?.trim().to_lowercase();
+    Ok(match answer.as_str() {
+        "" => default,
+        answer => answer.starts_with('y'),
+    })
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Flatpak application ID of Zeal; its sandboxed home is `~/.var/app/<id>`.
+const FLATPAK_ID: &str = "org.zealdocs.Zeal";
+/// Snap name of Zeal; its home is `~/snap/<name>/current`.
+const SNAP_NAME: &str = "zeal";
+
+/// Settings written by the Zeal application itself, as `section -> key -> value`.
+#[derive(Debug, Default)]
+pub struct ZealConf {
+    sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl ZealConf {
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.sections.get(section)?.get(key).map(String::as_str)
+    }
+
+    /// The keys and values of a section, none when it's missing.
+    pub fn section(&self, section: &str) -> impl Iterator<Item = (&str, &str)> {
+        self.sections
+            .get(section)
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+}
+
+/// Where Zeal keeps its settings: `Zeal.conf` (an INI file) on Linux and
+/// the BSDs, a preferences plist on macOS.
+pub fn path() -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        dirs::home_dir().map(|h| h.join("Library/Preferences/org.zealdocs.Zeal.plist"))
+    } else {
+        dirs::config_dir().map(|d| d.join("Zeal").join("Zeal.conf"))
+    }
+}
+
+/// Reads Zeal's settings, or `None` when Zeal has never been run.
+///
+/// Windows keeps them in the registry, which is not read.
+pub fn load() -> Option<ZealConf> {
+    load_from(&path()?)
+}
+
+pub fn load_from(path: &Path) -> Option<ZealConf> {
+    if path.extension().is_some_and(|ext| ext == "plist") {
+        let dict: plist::Dictionary = plist::from_file(path).ok()?;
+        let mut conf = ZealConf::default();
+        for (key, value) in dict {
+            // QSettings flattens `[section] key` to `section.key` in plists.
+            let (section, key) = key.split_once('.').unwrap_or(("General", &key));
+            if let Some(value) = value.as_string() {
+                conf.sections
+                    .entry(section.to_string())
+                    .or_default()
+                    .insert(key.to_string(), value.to_string());
+            }
+        }
+        Some(conf)
+    } else {
+        Some(parse_ini(&fs::read_to_string(path).ok()?))
+    }
+}
+
+fn parse_ini(body: &str) -> ZealConf {
+    let mut conf = ZealConf::default();
+    let mut section = "General".to_string();
+    for line in body.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            conf.sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_string(), value.replace("\\\\", "\\"));
+        }
+    }
+    conf
+}
+
+/// The docsets directory chosen in Zeal's preferences, if any.
+pub fn docsets_path() -> Option<PathBuf> {
+    load()?
+        .get("docsets", "path")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The docsets Zeal's `[keywords]` settings list for `keyword`, as
+/// `py=Python_3, Python_2`, the comma separated form QSettings writes lists
+/// in.
+pub fn keyword_docsets(keyword: &str) -> Vec<String> {
+    let Some(conf) = load() else {
+        return Vec::new();
+    };
+    conf.section("keywords")
+        .filter(|(k, _)| k.eq_ignore_ascii_case(keyword))
+        .flat_map(|(_, docsets)| docsets.split(','))
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Where Zeal keeps docsets unless told otherwise: its per-user local data
+/// directory, `%LOCALAPPDATA%\Zeal\Zeal\docsets` on Windows.
+pub fn default_docsets_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|d| d.join("Zeal").join("Zeal").join("docsets"))
+}
+
+/// Other places Windows installs of Zeal keep docsets, those that exist:
+/// the roaming profile of older releases, `%ProgramData%` for per-machine
+/// installs, and the `docsets` directory portable builds keep beside
+/// `zeal.exe` under Program Files or the per-user Programs directory.
+pub fn windows_docsets_dirs() -> Vec<PathBuf> {
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+    let env_dir = |var: &str| std::env::var_os(var).map(PathBuf::from);
+    let zeal = |dir: PathBuf| dir.join("Zeal").join("Zeal").join("docsets");
+    [
+        dirs::data_dir().map(zeal),
+        env_dir("ProgramData").map(zeal),
+        env_dir("ProgramFiles").map(|d| d.join("Zeal").join("docsets")),
+        dirs::data_local_dir().map(|d| d.join("Programs").join("Zeal").join("docsets")),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|dir| dir.is_dir())
+    .collect()
+}
+
+/// Docsets directories of Flatpak and Snap installs of Zeal that exist, each
+/// the path set in that install's own preferences or its default.
+pub fn sandboxed_docsets_dirs() -> Vec<PathBuf> {
+    if !cfg!(target_os = "linux") {
+        return Vec::new();
+    }
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    let flatpak = home.join(".var/app").join(FLATPAK_ID);
+    let snap = home.join("snap").join(SNAP_NAME).join("current");
+    [
+        (flatpak.join("config"), flatpak.join("data")),
+        (snap.join(".config"), snap.join(".local/share")),
+    ]
+    .into_iter()
+    .map(|(config, data)| {
+        load_from(&config.join("Zeal").join("Zeal.conf"))
+            .and_then(|conf| conf.get("docsets", "path").map(PathBuf::from))
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| data.join("Zeal").join("Zeal").join("docsets"))
+    })
+    .filter(|dir| dir.is_dir())
+    .collect()
+}